@@ -0,0 +1,97 @@
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+
+use crate::config::expand_home;
+
+/// If the first argument after argv0 is `@name`, replace it with the args
+/// for that alias.  Alias args may start with `~/`.  Returns an error for an
+/// unknown alias.
+pub fn expand(
+    args: Vec<OsString>,
+    aliases: &BTreeMap<String, Vec<String>>,
+    home: Option<OsString>,
+) -> Result<Vec<OsString>, String> {
+    let Some(name) = args
+        .get(1)
+        .and_then(|a| a.to_str())
+        .and_then(|a| a.strip_prefix('@'))
+    else {
+        return Ok(args);
+    };
+    let Some(expansion) = aliases.get(name) else {
+        return Err(name.to_string());
+    };
+
+    let mut res: Vec<OsString> = Vec::with_capacity(args.len() + expansion.len());
+    res.push(args[0].clone());
+    res.extend(
+        expansion
+            .iter()
+            .map(|a| expand_home(a, home.clone()).into_os_string()),
+    );
+    res.extend(args.into_iter().skip(2));
+    Ok(res)
+}
+
+/// One line per alias, for `cope alias`.
+pub fn format(aliases: &BTreeMap<String, Vec<String>>) -> String {
+    aliases
+        .iter()
+        .map(|(name, args)| format!("@{name} = {}\n", args.join(" ")))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn os(args: &[&str]) -> Vec<OsString> {
+        args.iter().map(OsString::from).collect()
+    }
+
+    fn aliases() -> BTreeMap<String, Vec<String>> {
+        BTreeMap::from([(
+            "api".to_string(),
+            vec![
+                "~/src/api/backend".to_string(),
+                "--profile".to_string(),
+                "Backend".to_string(),
+            ],
+        )])
+    }
+
+    #[test]
+    fn test_expand() {
+        let home = Some(OsString::from("/home/me"));
+        assert_eq!(
+            expand(os(&["cope", "@api", "-r"]), &aliases(), home.clone()).unwrap(),
+            os(&[
+                "cope",
+                "/home/me/src/api/backend",
+                "--profile",
+                "Backend",
+                "-r"
+            ])
+        );
+        assert_eq!(
+            expand(os(&["cope", "x", "@api"]), &aliases(), home.clone()).unwrap(),
+            os(&["cope", "x", "@api"])
+        );
+        assert_eq!(
+            expand(os(&["cope"]), &aliases(), home.clone()).unwrap(),
+            os(&["cope"])
+        );
+        assert_eq!(
+            expand(os(&["cope", "@nope"]), &aliases(), home),
+            Err("nope".into())
+        );
+    }
+
+    #[test]
+    fn test_format() {
+        assert_eq!(
+            format(&aliases()),
+            "@api = ~/src/api/backend --profile Backend\n"
+        );
+    }
+}
@@ -0,0 +1,209 @@
+use serde::{Deserialize, Serialize};
+use std::ffi::OsString;
+use std::path::Path;
+
+/// How a flag of one of code's subcommands uses its value.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Value {
+    /// Not a path; passed through as it is.
+    Plain,
+    /// A path on this machine.
+    Path,
+}
+
+/// The flags of `code chat` that take a value.  The rest of its args are
+/// the prompt, which is never a file name.
+pub const CHAT_FLAGS: &[(&str, Value)] = &[
+    ("-a", Value::Path),
+    ("--add-file", Value::Path),
+    ("-m", Value::Plain),
+    ("--mode", Value::Plain),
+    ("--profile", Value::Plain),
+];
+
+/// The flags of `code serve-web` that take a value.  serve-web takes no
+/// other args.
+pub const SERVE_WEB_FLAGS: &[(&str, Value)] = &[
+    ("--host", Value::Plain),
+    ("--port", Value::Plain),
+    ("--socket-path", Value::Path),
+    ("--connection-token", Value::Plain),
+    ("--connection-token-file", Value::Path),
+    ("--server-base-path", Value::Plain),
+    ("--server-data-dir", Value::Path),
+    ("--user-data-dir", Value::Path),
+    ("--extensions-dir", Value::Path),
+    ("--cli-data-dir", Value::Path),
+    ("--log", Value::Plain),
+];
+
+/// The flags of `code tunnel` and its subcommands (`user login`, `service
+/// install`, `rename` and so on) that take a value.  The other args name
+/// subcommands, or the tunnel, never files.
+pub const TUNNEL_FLAGS: &[(&str, Value)] = &[
+    ("--name", Value::Plain),
+    ("--install-extension", Value::Plain),
+    ("--server-data-dir", Value::Path),
+    ("--extensions-dir", Value::Path),
+    ("--cli-data-dir", Value::Path),
+    ("--log", Value::Plain),
+    ("--parent-process-id", Value::Plain),
+    ("--access-token", Value::Plain),
+    ("--refresh-token", Value::Plain),
+    ("--provider", Value::Plain),
+];
+
+/// The flags that take a value for code's subcommand name, if it is one
+/// that cope knows.
+pub fn flags(name: &str) -> Option<&'static [(&'static str, Value)]> {
+    match name {
+        "chat" => Some(CHAT_FLAGS),
+        "serve-web" => Some(SERVE_WEB_FLAGS),
+        "tunnel" => Some(TUNNEL_FLAGS),
+        _ => None,
+    }
+}
+
+/// Make path absolute, relative to cwd.
+fn absolute(path: &OsString, cwd: &Path) -> OsString {
+    cwd.join(path).into_os_string()
+}
+
+/// Rewrite the args after a subcommand, given the flags that take a value.
+/// Path values are made absolute, since the editor may not start in this
+/// directory (under flatpak, for instance); everything else, and anything
+/// after `--`, is left alone.
+pub fn rewrite(
+    mut args: impl Iterator<Item = OsString>,
+    flags: &[(&str, Value)],
+    cwd: &Path,
+) -> Vec<OsString> {
+    let mut res = Vec::new();
+    while let Some(a) = args.next() {
+        let Some(s) = a.to_str() else {
+            res.push(a);
+            continue;
+        };
+        if s == "--" {
+            res.push(a);
+            res.extend(args);
+            break;
+        }
+        let (flag, inline) = match s.split_once('=') {
+            Some((f, v)) if f.starts_with("--") => (f, Some(v)),
+            _ => (s, None),
+        };
+        match flags
+            .iter()
+            .find(|(name, _)| *name == flag)
+            .map(|(_, v)| *v)
+        {
+            Some(kind) => match inline {
+                Some(v) => {
+                    let v = OsString::from(v);
+                    let v = if kind == Value::Path {
+                        absolute(&v, cwd)
+                    } else {
+                        v
+                    };
+                    let mut joined = OsString::from(format!("{flag}="));
+                    joined.push(v);
+                    res.push(joined);
+                }
+                None => {
+                    res.push(a.clone());
+                    if let Some(v) = args.next() {
+                        res.push(if kind == Value::Path {
+                            absolute(&v, cwd)
+                        } else {
+                            v
+                        });
+                    }
+                }
+            },
+            None => res.push(a),
+        }
+    }
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(args: &[&str], flags: &[(&str, Value)]) -> Vec<String> {
+        rewrite(args.iter().map(OsString::from), flags, Path::new("/r"))
+            .iter()
+            .map(|a| a.to_string_lossy().into())
+            .collect()
+    }
+
+    #[test]
+    fn test_chat() {
+        assert_eq!(
+            run(
+                &["-m", "ask", "-a", "src/main.rs", "explain", "src/lib.rs"],
+                CHAT_FLAGS
+            ),
+            ["-m", "ask", "-a", "/r/src/main.rs", "explain", "src/lib.rs"]
+        );
+        assert_eq!(
+            run(
+                &["--add-file=a.rs", "--maximize", "--", "-a", "b"],
+                CHAT_FLAGS
+            ),
+            ["--add-file=/r/a.rs", "--maximize", "--", "-a", "b"]
+        );
+        assert_eq!(
+            run(&["--add-file", "/abs"], CHAT_FLAGS),
+            ["--add-file", "/abs"]
+        );
+        assert_eq!(run(&["-a"], CHAT_FLAGS), ["-a"]);
+    }
+
+    #[test]
+    fn test_serve_web() {
+        assert_eq!(
+            run(
+                &[
+                    "--port",
+                    "8080",
+                    "--server-data-dir",
+                    "data",
+                    "--without-connection-token"
+                ],
+                SERVE_WEB_FLAGS
+            ),
+            [
+                "--port",
+                "8080",
+                "--server-data-dir",
+                "/r/data",
+                "--without-connection-token"
+            ]
+        );
+        assert_eq!(
+            run(&["--host=0.0.0.0", "--socket-path=s.sock"], SERVE_WEB_FLAGS),
+            ["--host=0.0.0.0", "--socket-path=/r/s.sock"]
+        );
+        assert_eq!(flags("serve-web"), Some(SERVE_WEB_FLAGS));
+        assert_eq!(flags("src"), None);
+    }
+
+    #[test]
+    fn test_tunnel() {
+        assert_eq!(
+            run(
+                &["--name", "src", "--cli-data-dir", "cli", "--random-name"],
+                TUNNEL_FLAGS
+            ),
+            ["--name", "src", "--cli-data-dir", "/r/cli", "--random-name"]
+        );
+        assert_eq!(
+            run(&["user", "login", "--provider", "github"], TUNNEL_FLAGS),
+            ["user", "login", "--provider", "github"]
+        );
+        assert_eq!(run(&["rename", "src"], TUNNEL_FLAGS), ["rename", "src"]);
+    }
+}
@@ -0,0 +1,139 @@
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::command_utils::capture;
+
+/// The `"codespaces"` setting for a repo that should be looked up with
+/// `gh`, rather than named.
+pub const AUTO: &str = "auto";
+
+/// The git repo that holds pth, if any.
+pub fn git_root(pth: &Path) -> Option<PathBuf> {
+    pth.ancestors()
+        .find(|dir| dir.join(".git").exists())
+        .map(Path::to_path_buf)
+}
+
+/// The `owner/repo` of a GitHub remote URL, over either https or ssh.
+pub fn repo_slug(url: &str) -> Option<String> {
+    let rest = url
+        .strip_prefix("git@github.com:")
+        .or_else(|| url.strip_prefix("ssh://git@github.com/"))
+        .or_else(|| url.strip_prefix("https://github.com/"))?;
+    let slug = rest.trim_end_matches('/').trim_end_matches(".git");
+    (slug.split('/').count() == 2).then(|| slug.into())
+}
+
+/// The codespace for a repo, out of the output of `gh codespace list --json
+/// name,repository,state`.  One that is already running is best.
+pub fn pick(json_data: &str, slug: &str) -> Option<String> {
+    let v: serde_jsonc::Value = serde_jsonc::from_str(json_data).ok()?;
+    let matching: Vec<&serde_jsonc::Value> = v
+        .as_array()?
+        .iter()
+        .filter(|c| c["repository"].as_str() == Some(slug))
+        .collect();
+    matching
+        .iter()
+        .find(|c| c["state"].as_str() == Some("Available"))
+        .or(matching.first())
+        .and_then(|c| c["name"].as_str())
+        .map(String::from)
+}
+
+/// The `owner/repo` of the GitHub repo that root was cloned from.
+pub fn origin_slug(root: &Path) -> Option<String> {
+    let url = capture(
+        Command::new("git")
+            .arg("-C")
+            .arg(root)
+            .args(["remote", "get-url", "origin"]),
+    )?;
+    repo_slug(url.trim())
+}
+
+/// The name of the checkout in a codespace: the repo part of its
+/// `owner/repo`, whatever the local clone's directory is called.  Without
+/// a GitHub remote, that directory's name is the best guess.
+pub fn repo_name(slug: Option<&str>, root: &Path) -> String {
+    slug.and_then(|s| s.split('/').nth(1))
+        .map(String::from)
+        .unwrap_or_else(|| {
+            root.file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .into()
+        })
+}
+
+/// Ask `gh` for a codespace of the GitHub repo that root was cloned from.
+pub fn detect(root: &Path) -> Option<String> {
+    let slug = origin_slug(root)?;
+    let list =
+        capture(Command::new("gh").args(["codespace", "list", "--json", "name,repository,state"]))?;
+    pick(&list, &slug)
+}
+
+/// The argument that opens the path rel, inside the repo called repo_name,
+/// in a codespace.  Codespaces check the repo out under /workspaces.
+pub fn uri(name: &str, repo_name: &str, rel: &Path, folder: bool) -> String {
+    format!(
+        "--{}-uri=vscode-remote://codespaces+{name}/workspaces/{repo_name}/{}",
+        if folder { "folder" } else { "file" },
+        rel.display()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    #[test]
+    fn test_git_root() {
+        let cur = env::current_dir().unwrap();
+        assert_eq!(git_root(&cur.join("src/main.rs")), Some(cur));
+    }
+
+    #[test]
+    fn test_repo_name() {
+        assert_eq!(
+            repo_name(Some("hildjj/cope"), Path::new("/src/myfork")),
+            "cope"
+        );
+        assert_eq!(repo_name(None, Path::new("/src/myfork")), "myfork");
+    }
+
+    #[test]
+    fn test_repo_slug() {
+        assert_eq!(
+            repo_slug("git@github.com:hildjj/cope.git"),
+            Some("hildjj/cope".into())
+        );
+        assert_eq!(
+            repo_slug("https://github.com/hildjj/cope"),
+            Some("hildjj/cope".into())
+        );
+        assert_eq!(repo_slug("https://gitlab.com/hildjj/cope"), None);
+    }
+
+    #[test]
+    fn test_pick() {
+        let list = r#"[
+            {"name": "old-space", "repository": "hildjj/cope", "state": "Shutdown"},
+            {"name": "other", "repository": "hildjj/other", "state": "Available"},
+            {"name": "fuzzy-space", "repository": "hildjj/cope", "state": "Available"}
+        ]"#;
+        assert_eq!(pick(list, "hildjj/cope"), Some("fuzzy-space".into()));
+        assert_eq!(pick(list, "hildjj/nope"), None);
+        assert_eq!(pick("[]", "hildjj/cope"), None);
+    }
+
+    #[test]
+    fn test_uri() {
+        assert_eq!(
+            uri("fuzzy-space", "cope", Path::new("src/main.rs"), false),
+            "--file-uri=vscode-remote://codespaces+fuzzy-space/workspaces/cope/src/main.rs"
+        );
+    }
+}
@@ -0,0 +1,156 @@
+use std::io::Read;
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::trace::Span;
+
+/// Commands that talk to the network or a container engine, which are never
+/// run for information when offline.
+const NETWORK_COMMANDS: &[&str] = &["curl", "docker", "gh", "kubectl", "podman", "wget"];
+
+static OFFLINE: AtomicBool = AtomicBool::new(false);
+
+/// Turn offline mode on or off for the rest of the run.
+pub fn set_offline(offline: bool) {
+    OFFLINE.store(offline, Ordering::Relaxed);
+}
+
+pub fn is_offline() -> bool {
+    OFFLINE.load(Ordering::Relaxed)
+}
+
+/// How long a query may take before cope gives up on it, so that a wedged
+/// docker daemon can't stop the editor from opening.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+
+static TIMEOUT_MS: AtomicU64 = AtomicU64::new(DEFAULT_TIMEOUT.as_millis() as u64);
+
+/// Set the query timeout for the rest of the run.
+pub fn set_timeout(timeout: Duration) {
+    TIMEOUT_MS.store(timeout.as_millis() as u64, Ordering::Relaxed);
+}
+
+pub fn timeout() -> Duration {
+    Duration::from_millis(TIMEOUT_MS.load(Ordering::Relaxed))
+}
+
+/// Parse a timeout given in (possibly fractional) seconds.
+pub fn parse_timeout(s: &str) -> Option<Duration> {
+    s.parse::<f64>()
+        .ok()
+        .and_then(|t| Duration::try_from_secs_f64(t).ok())
+}
+
+/// Would running program break offline mode?
+pub fn blocked(program: &str, offline: bool) -> bool {
+    offline && NETWORK_COMMANDS.contains(&program)
+}
+
+/// Run a command to completion, returning its trimmed stdout if it
+/// succeeded.  Commands that can't be run, fail, or take longer than the
+/// timeout give None, as do network and container engine commands in
+/// offline mode.
+pub fn capture(cmd: &mut Command) -> Option<String> {
+    let program = cmd.get_program().to_string_lossy().into_owned();
+    if blocked(&program, is_offline()) {
+        return None;
+    }
+    let _span = Span::enter(format!("run {program}"));
+    capture_within(cmd, timeout())
+}
+
+/// Like `capture`, with an explicit timeout.  The command is killed if it
+/// runs out of time.  If it leaves something behind that still holds its
+/// output open, that is given up on at the deadline too.
+pub fn capture_within(cmd: &mut Command, limit: Duration) -> Option<String> {
+    let mut child = cmd
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .ok()?;
+    // Read on another thread, so a chatty command can't fill the pipe and
+    // stall while we wait for it.  The thread is left behind if it never
+    // finishes.
+    let mut stdout = child.stdout.take().expect("Piped stdout");
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        let mut buf: Vec<u8> = Vec::new();
+        let _ = stdout.read_to_end(&mut buf);
+        let _ = tx.send(buf);
+    });
+
+    let deadline = Instant::now() + limit;
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break status,
+            Ok(None) if Instant::now() < deadline => thread::sleep(Duration::from_millis(10)),
+            _ => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return None;
+            }
+        }
+    };
+    let buf = rx
+        .recv_timeout(deadline.saturating_duration_since(Instant::now()))
+        .ok()?;
+    status
+        .success()
+        .then(|| String::from_utf8_lossy(&buf).trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_capture() {
+        assert_eq!(capture(Command::new("echo").arg(" hi ")), Some("hi".into()));
+        assert_eq!(capture(&mut Command::new("false")), None);
+        assert_eq!(capture(&mut Command::new("___NO_SUCH_COMMAND___")), None);
+    }
+
+    #[test]
+    fn test_capture_within() {
+        let start = Instant::now();
+        assert_eq!(
+            capture_within(Command::new("sleep").arg("5"), Duration::from_millis(50)),
+            None
+        );
+        assert!(start.elapsed() < Duration::from_secs(2));
+        assert_eq!(
+            capture_within(Command::new("echo").arg("ok"), Duration::from_secs(5)),
+            Some("ok".into())
+        );
+        // The shell exits at once, but the sleep it leaves behind keeps
+        // stdout open.
+        let start = Instant::now();
+        assert_eq!(
+            capture_within(
+                Command::new("sh").args(["-c", "sleep 5 & echo ok"]),
+                Duration::from_millis(200)
+            ),
+            None
+        );
+        assert!(start.elapsed() < Duration::from_secs(2));
+    }
+
+    #[test]
+    fn test_parse_timeout() {
+        assert_eq!(parse_timeout("2.5"), Some(Duration::from_millis(2500)));
+        assert_eq!(parse_timeout("0"), Some(Duration::ZERO));
+        assert_eq!(parse_timeout("-1"), None);
+        assert_eq!(parse_timeout("soon"), None);
+    }
+
+    #[test]
+    fn test_blocked() {
+        assert!(blocked("docker", true));
+        assert!(!blocked("docker", false));
+        assert!(!blocked("git", true));
+    }
+}
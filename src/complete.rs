@@ -0,0 +1,115 @@
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+/// cope's own subcommands, offered for the first argument.
+pub const SUBCOMMANDS: &[&str] = &[
+    "alias",
+    "attach",
+    "browse",
+    "doctor",
+    "down",
+    "exec",
+    "forward",
+    "help",
+    "info",
+    "k8s",
+    "logs",
+    "outdated",
+    "projects",
+    "ps",
+    "record",
+    "replay",
+    "reverse",
+    "setup",
+    "shell",
+    "shim",
+    "stats",
+    "status",
+    "tunnels",
+    "update-config",
+    "update-flags",
+    "validate",
+    "which",
+    "z",
+];
+
+/// Asking the container has to be quick, or completion feels broken.
+pub const CONTAINER_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// The files in cwd that complete word.  Directories end with `/`, like
+/// the shell's own completion.
+pub fn local_matches(cwd: &Path, word: &str) -> Vec<String> {
+    let (dir, prefix) = match word.rsplit_once('/') {
+        Some((d, p)) => (format!("{d}/"), p),
+        None => (String::new(), word),
+    };
+    let Ok(entries) = fs::read_dir(cwd.join(&dir)) else {
+        return vec![];
+    };
+    entries
+        .filter_map(|e| e.ok())
+        .filter_map(|e| {
+            let name = e.file_name().to_string_lossy().into_owned();
+            // Hidden files only when asked for, as usual.
+            if !name.starts_with(prefix) || (name.starts_with('.') && !prefix.starts_with('.')) {
+                return None;
+            }
+            let slash = if e.path().is_dir() { "/" } else { "" };
+            Some(format!("{dir}{name}{slash}"))
+        })
+        .collect()
+}
+
+/// The shell script that lists the completions of `$1` inside the
+/// container, in the same format as `local_matches`.
+pub fn container_script() -> String {
+    r#"for f in "$1"*; do if [ -d "$f" ]; then echo "$f/"; elif [ -e "$f" ]; then echo "$f"; fi; done"#
+        .into()
+}
+
+/// The candidates for the word, given how many args come before it.
+pub fn candidates(position: usize, word: &str, files: Vec<String>) -> Vec<String> {
+    let mut res: Vec<String> = files;
+    if position == 1 {
+        res.extend(
+            SUBCOMMANDS
+                .iter()
+                .filter(|s| s.starts_with(word))
+                .map(|&s| s.into()),
+        );
+    }
+    res.sort();
+    res.dedup();
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    #[test]
+    fn test_local_matches() {
+        let cur = env::current_dir().unwrap();
+        let mut m = local_matches(&cur, "sr");
+        m.sort();
+        assert_eq!(m, vec!["src/"]);
+        assert!(local_matches(&cur, "src/mai").contains(&"src/main.rs".to_string()));
+        assert!(!local_matches(&cur, "").iter().any(|f| f.starts_with('.')));
+        assert!(local_matches(&cur, ".devc").contains(&".devcontainer/".to_string()));
+        assert!(local_matches(&cur, "___nope/x").is_empty());
+    }
+
+    #[test]
+    fn test_candidates() {
+        assert_eq!(
+            candidates(1, "s", vec!["src/".into()]),
+            vec!["setup", "shell", "shim", "src/", "stats", "status"]
+        );
+        assert_eq!(
+            candidates(2, "s", vec!["src/".into(), "src/".into()]),
+            vec!["src/"]
+        );
+    }
+}
@@ -0,0 +1,376 @@
+use serde::Deserialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::file_utils::normalize;
+
+/// devcontainer.json allows either a single string or an array of strings
+/// for some properties, such as `dockerComposeFile`.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum StringOrList {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl StringOrList {
+    pub fn to_vec(&self) -> Vec<String> {
+        match self {
+            StringOrList::One(s) => vec![s.clone()],
+            StringOrList::Many(v) => v.clone(),
+        }
+    }
+}
+
+/// Everything needed to point `docker compose` at a config's containers.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Compose {
+    pub project: String,
+    pub files: Vec<PathBuf>,
+    pub service: Option<String>,
+}
+
+impl Compose {
+    /// Work out the compose project for a devcontainer.json that uses
+    /// `dockerComposeFile`.  COMPOSE_PROJECT_NAME wins, if given.
+    pub fn new(
+        config_file: &Path,
+        spec: &StringOrList,
+        service: Option<String>,
+        env_project: Option<String>,
+    ) -> Compose {
+        let files = compose_files(config_file, spec);
+        let project = env_project
+            .or_else(|| {
+                // Later files override earlier ones, just like compose does.
+                files
+                    .iter()
+                    .filter_map(|f| fs::read_to_string(f).ok())
+                    .filter_map(|contents| declared_name(&contents))
+                    .next_back()
+            })
+            .unwrap_or_else(|| default_project_name(&files[0]));
+        Compose {
+            project,
+            files,
+            service,
+        }
+    }
+
+    /// The `docker compose` arguments that select this project.
+    pub fn args(&self) -> Vec<String> {
+        let mut res: Vec<String> = vec!["compose".into(), "-p".into(), self.project.clone()];
+        for f in &self.files {
+            res.extend(["-f".into(), f.display().to_string()]);
+        }
+        res
+    }
+}
+
+/// The compose files, which are relative to the devcontainer.json.
+pub fn compose_files(config_file: &Path, spec: &StringOrList) -> Vec<PathBuf> {
+    let dir = config_file.parent().expect("Config file in a directory");
+    spec.to_vec()
+        .iter()
+        .map(|f| normalize(dir.join(f).as_os_str()))
+        .collect()
+}
+
+/// Compose only allows lowercase letters, digits, dashes and underscores in
+/// project names.
+pub fn sanitize(name: &str) -> String {
+    name.to_lowercase()
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || *c == '-' || *c == '_')
+        .collect()
+}
+
+/// The name the Dev Containers extension uses when nothing else is
+/// specified: the name of the directory holding the first compose file,
+/// prefixed with its parent if that directory is `.devcontainer`.
+pub fn default_project_name(first_file: &Path) -> String {
+    let dir = first_file.parent().expect("Compose file in a directory");
+    let base = dir.file_name().unwrap_or_default().to_string_lossy();
+    let name = if base == ".devcontainer" {
+        let parent = dir
+            .parent()
+            .and_then(Path::file_name)
+            .unwrap_or_default()
+            .to_string_lossy();
+        format!("{parent}_devcontainer")
+    } else {
+        base.into_owned()
+    };
+    sanitize(&name)
+}
+
+/// Find a top-level `name:` in a compose file.  This isn't a full YAML
+/// parser; it just looks for an unindented key.
+pub fn declared_name(contents: &str) -> Option<String> {
+    contents.lines().find_map(|line| {
+        let value = line.strip_prefix("name:")?;
+        let value = value.split(" #").next().unwrap_or_default().trim();
+        let value = value.trim_matches(|c| c == '"' || c == '\'');
+        (!value.is_empty()).then(|| value.to_string())
+    })
+}
+
+/// The names of the services in a compose file, in order.  Like
+/// `declared_name`, this only understands the usual block-style layout.
+pub fn service_names(contents: &str) -> Vec<String> {
+    let mut res: Vec<String> = Vec::new();
+    let mut indent: Option<usize> = None;
+    let lines = contents
+        .lines()
+        .skip_while(|l| l.trim_end() != "services:")
+        .skip(1);
+    for line in lines {
+        let trimmed = line.trim_start();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        let this_indent = line.len() - trimmed.len();
+        if this_indent == 0 {
+            break;
+        }
+        if *indent.get_or_insert(this_indent) == this_indent
+            && let Some(name) = trimmed
+                .strip_suffix(':')
+                .or_else(|| trimmed.split_once(": ").map(|(k, _)| k))
+        {
+            res.push(name.trim_matches(|c| c == '"' || c == '\'').to_string());
+        }
+    }
+    res
+}
+
+/// The `(source, target)` of each of a service's volumes, in either the
+/// short `- ..:/workspace:cached` syntax or the long one with `source:` and
+/// `target:` keys.  Like `service_names`, this only understands the usual
+/// block-style layout.
+pub fn service_volumes(contents: &str, service: &str) -> Vec<(String, String)> {
+    let unquote = |s: &str| s.trim().trim_matches(|c| c == '"' || c == '\'').to_string();
+    let mut res: Vec<(String, String)> = Vec::new();
+    let mut service_indent: Option<usize> = None;
+    let mut in_service = false;
+    let mut volumes_indent: Option<usize> = None;
+    // The long syntax item being read.
+    let mut long: Option<(String, String)> = None;
+    let lines = contents
+        .lines()
+        .skip_while(|l| l.trim_end() != "services:")
+        .skip(1);
+    for line in lines {
+        let trimmed = line.trim_start();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        let indent = line.len() - trimmed.len();
+        if indent == 0 {
+            break;
+        }
+        if *service_indent.get_or_insert(indent) == indent {
+            in_service = unquote(trimmed.trim_end_matches(':')) == service;
+            volumes_indent = None;
+            continue;
+        }
+        if !in_service {
+            continue;
+        }
+        match volumes_indent {
+            Some(vi) if indent > vi => {
+                let item = trimmed.strip_prefix("- ");
+                if item.is_some() {
+                    res.extend(long.take().filter(|(s, t)| !s.is_empty() && !t.is_empty()));
+                }
+                let entry = item.unwrap_or(trimmed);
+                if let Some((key, value)) = entry.split_once(": ") {
+                    let l = long.get_or_insert_with(Default::default);
+                    match key.trim() {
+                        "source" => l.0 = unquote(value),
+                        "target" => l.1 = unquote(value),
+                        _ => {}
+                    }
+                } else if let Some(short) = item {
+                    let short = unquote(short);
+                    let mut parts = short.splitn(3, ':');
+                    if let (Some(s), Some(t)) = (parts.next(), parts.next()) {
+                        res.push((s.into(), t.into()));
+                    }
+                }
+            }
+            _ => {
+                res.extend(long.take().filter(|(s, t)| !s.is_empty() && !t.is_empty()));
+                volumes_indent = (trimmed.trim_end() == "volumes:").then_some(indent);
+            }
+        }
+    }
+    res.extend(long.take().filter(|(s, t)| !s.is_empty() && !t.is_empty()));
+    res
+}
+
+/// Where the repo at root ends up in the service's container, from the
+/// bind mounts in the compose files.  Sources are relative to their
+/// compose file; later files win, as they do for compose.
+pub fn workspace_folder(root: &Path, files: &[PathBuf], service: &str) -> Option<String> {
+    files
+        .iter()
+        .filter_map(|f| {
+            let dir = f.parent()?;
+            let contents = fs::read_to_string(f).ok()?;
+            service_volumes(&contents, service)
+                .into_iter()
+                .filter(|(source, _)| source.starts_with('.') || source.starts_with('/'))
+                .find_map(|(source, target)| {
+                    let source = normalize(dir.join(source).as_os_str());
+                    let rel = root.strip_prefix(&source).ok()?;
+                    let target = Path::new(&target).join(rel);
+                    Some(
+                        target
+                            .display()
+                            .to_string()
+                            .trim_end_matches('/')
+                            .to_string(),
+                    )
+                })
+        })
+        .next_back()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_string_or_list() {
+        let one: StringOrList = serde_jsonc::from_str(r#""a.yml""#).unwrap();
+        assert_eq!(one.to_vec(), vec!["a.yml"]);
+        let many: StringOrList = serde_jsonc::from_str(r#"["a.yml", "b.yml"]"#).unwrap();
+        assert_eq!(many.to_vec(), vec!["a.yml", "b.yml"]);
+    }
+
+    #[test]
+    fn test_compose_files() {
+        let files = compose_files(
+            Path::new("/repo/.devcontainer/devcontainer.json"),
+            &StringOrList::Many(vec!["../docker-compose.yml".into(), "extra.yml".into()]),
+        );
+        assert_eq!(
+            files,
+            vec![
+                PathBuf::from("/repo/docker-compose.yml"),
+                PathBuf::from("/repo/.devcontainer/extra.yml")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_default_project_name() {
+        assert_eq!(
+            default_project_name(Path::new("/src/My Repo/.devcontainer/compose.yml")),
+            "myrepo_devcontainer"
+        );
+        assert_eq!(
+            default_project_name(Path::new("/src/my-repo/compose.yml")),
+            "my-repo"
+        );
+    }
+
+    #[test]
+    fn test_declared_name() {
+        assert_eq!(
+            declared_name("services:\n  name: nope\nname: \"proj\" # comment\n"),
+            Some("proj".into())
+        );
+        assert_eq!(declared_name("services: {}\n"), None);
+    }
+
+    #[test]
+    fn test_service_names() {
+        let contents = "name: x\nservices:\n  # the app\n  app:\n    image: foo\n    ports:\n      - 80\n\n  \"db\":\n    image: pg\nvolumes:\n  data:\n";
+        assert_eq!(service_names(contents), vec!["app", "db"]);
+        assert!(service_names("version: 3\n").is_empty());
+    }
+
+    #[test]
+    fn test_service_volumes() {
+        let contents = "services:
+  db:
+    volumes:
+      - data:/var/lib/postgresql
+  app:
+    image: foo
+    volumes:
+      - ..:/workspace:cached
+      - type: bind
+        source: \"../cache\"
+        target: /cache
+    ports:
+      - 80
+volumes:
+  data:
+";
+        assert_eq!(
+            service_volumes(contents, "app"),
+            vec![
+                ("..".to_string(), "/workspace".to_string()),
+                ("../cache".to_string(), "/cache".to_string())
+            ]
+        );
+        assert_eq!(
+            service_volumes(contents, "db"),
+            vec![("data".to_string(), "/var/lib/postgresql".to_string())]
+        );
+        assert!(service_volumes(contents, "nope").is_empty());
+    }
+
+    #[test]
+    fn test_workspace_folder() {
+        let dir = std::env::temp_dir().join(format!("cope-test-compose-ws-{}", std::process::id()));
+        let dc = dir.join(".devcontainer");
+        fs::create_dir_all(&dc).unwrap();
+        let file = dc.join("compose.yml");
+        fs::write(
+            &file,
+            "services:\n  app:\n    volumes:\n      - ../..:/src:cached\n",
+        )
+        .unwrap();
+        assert_eq!(
+            workspace_folder(&dir, std::slice::from_ref(&file), "app"),
+            Some(format!(
+                "/src/{}",
+                dir.file_name().unwrap().to_string_lossy()
+            ))
+        );
+        assert_eq!(workspace_folder(&dir, &[file], "db"), None);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_compose() {
+        let c = Compose::new(
+            Path::new("/___NO_SUCH_REPO___/.devcontainer/devcontainer.json"),
+            &StringOrList::One("compose.yml".into()),
+            Some("app".into()),
+            None,
+        );
+        assert_eq!(c.project, "___no_such_repo____devcontainer");
+        assert_eq!(
+            c.args(),
+            vec![
+                "compose",
+                "-p",
+                "___no_such_repo____devcontainer",
+                "-f",
+                "/___NO_SUCH_REPO___/.devcontainer/compose.yml"
+            ]
+        );
+        let c = Compose::new(
+            Path::new("/r/.devcontainer/devcontainer.json"),
+            &StringOrList::One("compose.yml".into()),
+            None,
+            Some("env".into()),
+        );
+        assert_eq!(c.project, "env");
+    }
+}
@@ -0,0 +1,489 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::env;
+use std::ffi::OsString;
+use std::fs;
+use std::iter::once;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use crate::i18n::t;
+
+/// A repo that is opened through a tunnel, rather than locally.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct TunnelProject {
+    /// The tunnel's name.
+    pub tunnel: String,
+
+    /// Where the repo is on the tunnel's machine, if not the same place as
+    /// here.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+}
+
+/// A repo whose devcontainer runs on an SSH host, where the repo is also
+/// checked out.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SshProject {
+    /// `[user@]host`, as for ssh.
+    pub host: String,
+
+    /// Where the repo is on the host, if not the same place as here.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+}
+
+/// Settings read from the user's config file.  Every field has a default, so
+/// an empty (or missing) file is valid.
+#[derive(Deserialize, Serialize, Debug, Default, Clone)]
+#[serde(default, rename_all = "camelCase")]
+pub struct Config {
+    /// Directories containing project checkouts, scanned by `cope projects`.
+    pub projects: Vec<String>,
+
+    /// `cope @name` expands to these args.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub aliases: BTreeMap<String, Vec<String>>,
+
+    /// Names of the config discovery strategies to use, in order.  See
+    /// `discovery::default_names` for the default.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub discovery: Option<Vec<String>>,
+
+    /// How many levels of subdirs of `.devcontainer` to search for
+    /// devcontainer.json files.  The default is 1.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub discovery_depth: Option<usize>,
+
+    /// Names of subdirs of `.devcontainer` not to search.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub discovery_ignore: Vec<String>,
+
+    /// The editor binary to run instead of `code`: a name to look for on
+    /// the PATH, or a path.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code_bin: Option<String>,
+
+    /// Repos to open in a GitHub codespace instead of a local container,
+    /// with the codespace's name, or "auto" to ask `gh` for one.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub codespaces: BTreeMap<String, String>,
+
+    /// Repos that live on a machine reached through a VS Code tunnel.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub tunnel_projects: BTreeMap<String, TunnelProject>,
+
+    /// Repos whose devcontainers run on an SSH host.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub ssh_projects: BTreeMap<String, SshProject>,
+
+    /// Names that cope may be symlinked as, and the editor (by name, or
+    /// binary) to run when it is invoked that way.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub invoked_as: BTreeMap<String, String>,
+
+    /// The editor to run, by name, such as "insiders", if there is no
+    /// codeBin.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub editor: Option<String>,
+
+    /// "microsoft" or "open-remote": which devcontainer extension's URIs
+    /// to make.  Detected from the editor if not given.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub uri_variant: Option<String>,
+
+    /// "docker" or "podman".  Detected if not given.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub engine: Option<String>,
+
+    /// The docker host, such as `ssh://me@build`, when containers run on
+    /// another machine.  DOCKER_HOST if not given.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub docker_host: Option<String>,
+
+    /// The docker context named in devcontainer URIs, like
+    /// COPE_DOCKER_CONTEXT.  Detected if not given.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub docker_context: Option<String>,
+
+    /// The `"localDocker"` value in devcontainer URIs.  Detected if not
+    /// given.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub local_docker: Option<bool>,
+
+    /// Never query the network or the container engine, like
+    /// `--cope-offline`.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub offline: bool,
+
+    /// Keep local launch statistics, shown by `cope stats`.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub stats: bool,
+
+    /// `cope complete` also offers files from the running container.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub container_completion: bool,
+
+    /// Show desktop notifications, like `--cope-notify`.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub notify: bool,
+
+    /// Expand glob patterns in path args, like `--cope-glob`.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub glob: bool,
+
+    /// Seconds to wait for docker, registries and the like, like
+    /// `--cope-timeout`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<f64>,
+
+    /// Append OTLP/JSON traces of each run to this file.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub trace_file: Option<String>,
+}
+
+/// A repo's own settings for cope, from `.devcontainer/cope.json` (JSON
+/// with comments, like devcontainer.json).
+#[derive(Deserialize, Debug, Default, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectConfig {
+    /// Extra args for code whenever the repo is opened, such as
+    /// `["--profile", "Backend"]`.  Only the flags in PROJECT_FLAGS are
+    /// used.
+    #[serde(default)]
+    pub args: Vec<String>,
+
+    /// Where the repo really is in the container, when the extension puts
+    /// it somewhere other than devcontainer.json says.  This wins over
+    /// workspaceFolder and workspaceMount.
+    #[serde(default)]
+    pub workspace_folder: Option<String>,
+}
+
+/// The flags that a repo may ask for, and how many parameters each takes.
+/// A repo is just something that was cloned, so flags that could install
+/// or load code (--install-extension, --extensions-dir, --user-data-dir
+/// and the like) are left out.
+pub const PROJECT_FLAGS: &[(&str, usize)] = &[
+    ("--profile", 1),
+    ("--disable-extension", 1),
+    ("--disable-extensions", 0),
+    ("--disable-gpu", 0),
+    ("--locale", 1),
+    ("--log", 1),
+    ("--new-window", 0),
+    ("-n", 0),
+    ("--reuse-window", 0),
+    ("-r", 0),
+    ("--skip-add-to-recently-opened", 0),
+];
+
+impl ProjectConfig {
+    /// The args that are in PROJECT_FLAGS (with their parameters), and the
+    /// ones that aren't, each with any parameters it has.
+    pub fn code_args(&self) -> (Vec<String>, Vec<String>) {
+        let mut allowed = Vec::new();
+        let mut rejected = Vec::new();
+        let mut it = self.args.iter();
+        while let Some(a) = it.next() {
+            let (flag, inline) = match a.split_once('=') {
+                Some((f, _)) if f.starts_with("--") => (f, true),
+                _ => (a.as_str(), false),
+            };
+            match PROJECT_FLAGS.iter().find(|(f, _)| *f == flag) {
+                Some((_, 1)) if inline => allowed.push(a.clone()),
+                Some((_, n)) if !inline => {
+                    allowed.push(a.clone());
+                    allowed.extend(it.by_ref().take(*n).cloned());
+                }
+                _ => {
+                    // Its parameters go with it, so they aren't taken as
+                    // flags of their own.
+                    let n = if inline {
+                        0
+                    } else {
+                        crate::flags::param_size(flag).unwrap_or(0)
+                    };
+                    let words: Vec<&str> = once(a.as_str())
+                        .chain(it.by_ref().take(n).map(String::as_str))
+                        .collect();
+                    rejected.push(words.join(" "));
+                }
+            }
+        }
+        (allowed, rejected)
+    }
+}
+
+/// Where a repo's own settings are kept.
+pub fn project_file(root: &Path) -> PathBuf {
+    root.join(".devcontainer").join("cope.json")
+}
+
+/// Read a repo's settings, returning parse errors.  A missing file means
+/// there are none.
+pub fn try_read_project(root: &Path) -> Result<ProjectConfig, String> {
+    let file_name = project_file(root);
+    match fs::read_to_string(&file_name) {
+        Ok(json_data) => serde_jsonc::from_str(&json_data)
+            .map_err(|er| format!("Error parsing project settings {file_name:?} {er}")),
+        Err(_) => Ok(ProjectConfig::default()),
+    }
+}
+
+/// Read a repo's settings.  A file that can't be parsed is reported, and
+/// ignored, as are args that a repo isn't allowed to ask for.
+pub fn read_project(root: &Path) -> ProjectConfig {
+    let mut project = try_read_project(root).unwrap_or_else(|er| {
+        eprintln!("{}", t!("bad-project", error = er));
+        ProjectConfig::default()
+    });
+    let (allowed, rejected) = project.code_args();
+    for arg in rejected {
+        eprintln!(
+            "{}",
+            t!(
+                "project-arg-ignored",
+                arg = arg,
+                file = project_file(root).display()
+            )
+        );
+    }
+    project.args = allowed;
+    project
+}
+
+/// Pick an XDG base directory: the environment variable if it is set to an
+/// absolute path, otherwise the given fallback under the home directory.
+pub fn xdg_dir(var: Option<OsString>, home: Option<OsString>, fallback: &str) -> PathBuf {
+    match var.map(PathBuf::from) {
+        Some(p) if p.is_absolute() => p,
+        _ => PathBuf::from(home.unwrap_or_else(|| "/".into())).join(fallback),
+    }
+}
+
+/// Where cope keeps its configuration, e.g. ~/.config/cope
+pub fn config_dir() -> PathBuf {
+    xdg_dir(
+        env::var_os("XDG_CONFIG_HOME"),
+        env::var_os("HOME"),
+        ".config",
+    )
+    .join("cope")
+}
+
+/// Where cope keeps data it can regenerate, e.g. ~/.cache/cope
+pub fn cache_dir() -> PathBuf {
+    xdg_dir(env::var_os("XDG_CACHE_HOME"), env::var_os("HOME"), ".cache").join("cope")
+}
+
+/// Where cope keeps data it can't regenerate, e.g. ~/.local/share/cope
+pub fn data_dir() -> PathBuf {
+    xdg_dir(
+        env::var_os("XDG_DATA_HOME"),
+        env::var_os("HOME"),
+        ".local/share",
+    )
+    .join("cope")
+}
+
+/// The config file to use.  COPE_CONFIG overrides the default location.
+pub fn config_file() -> PathBuf {
+    env::var_os("COPE_CONFIG")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| config_dir().join("config.json"))
+}
+
+/// Expand a leading `~/` to the home directory.
+pub fn expand_home(s: &str, home: Option<OsString>) -> PathBuf {
+    match (s.strip_prefix("~/"), home) {
+        (Some(rest), Some(h)) => PathBuf::from(h).join(rest),
+        _ => PathBuf::from(s),
+    }
+}
+
+/// Read the config file at the given path, returning parse errors.  A
+/// missing file gives the defaults.
+pub fn try_read_config(file_name: &Path) -> Result<Config, String> {
+    match fs::read_to_string(file_name) {
+        Ok(json_data) => serde_jsonc::from_str(&json_data)
+            .map_err(|er| format!("Error parsing config {file_name:?} {er}")),
+        Err(_) => Ok(Config::default()),
+    }
+}
+
+/// A config, along with whatever keys in its file cope doesn't know.
+#[derive(Deserialize)]
+struct WithUnknown {
+    #[serde(flatten)]
+    _config: Config,
+    #[serde(flatten)]
+    unknown: BTreeMap<String, serde_jsonc::Value>,
+}
+
+/// Would writing the config back out lose some of this text?  Comments,
+/// JSON5 syntax and keys cope doesn't know all go.
+fn lossy(json_data: &str) -> bool {
+    if json_data.trim().is_empty() {
+        return false;
+    }
+    // to_json only drops comments and rewrites JSON5, apart from spacing.
+    let squeeze = |s: &str| s.split_whitespace().collect::<String>();
+    squeeze(&crate::json5::to_json(json_data)) != squeeze(json_data)
+        || serde_jsonc::from_str::<WithUnknown>(json_data).map_or(true, |w| !w.unknown.is_empty())
+}
+
+/// Write the config file, creating its directory if needed.  An old file
+/// with comments or settings that would be lost is kept next to it, as
+/// `config.json.bak`.
+pub fn write_config(file_name: &Path, config: &Config) -> std::io::Result<()> {
+    if let Some(parent) = file_name.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    if let Ok(old) = fs::read_to_string(file_name)
+        && lossy(&old)
+    {
+        let backup = file_name.with_extension("json.bak");
+        fs::write(&backup, old)?;
+        eprintln!(
+            "{}",
+            t!(
+                "config-backup",
+                file = file_name.display(),
+                backup = backup.display()
+            )
+        );
+    }
+    let json_data = serde_jsonc::to_string_pretty(config).expect("Serializing config");
+    fs::write(file_name, json_data + "\n")
+}
+
+/// The user's config file, read once per run.  One that can't be parsed is
+/// reported, and the defaults are used, so that a typo doesn't stop every
+/// command (`cope doctor` among them) from working.
+pub fn load() -> Config {
+    static CONFIG: OnceLock<Config> = OnceLock::new();
+    CONFIG
+        .get_or_init(|| {
+            try_read_config(&config_file()).unwrap_or_else(|er| {
+                eprintln!("{}", t!("bad-config", error = er));
+                Config::default()
+            })
+        })
+        .clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_xdg_dir() {
+        assert_eq!(
+            xdg_dir(Some("/x".into()), Some("/home/me".into()), ".cache"),
+            PathBuf::from("/x")
+        );
+        // Relative values are ignored, per the XDG spec.
+        assert_eq!(
+            xdg_dir(Some("x".into()), Some("/home/me".into()), ".cache"),
+            PathBuf::from("/home/me/.cache")
+        );
+        assert_eq!(xdg_dir(None, None, ".cache"), PathBuf::from("/.cache"));
+    }
+
+    #[test]
+    fn test_expand_home() {
+        assert_eq!(
+            expand_home("~/src", Some("/home/me".into())),
+            PathBuf::from("/home/me/src")
+        );
+        assert_eq!(
+            expand_home("/src", Some("/home/me".into())),
+            PathBuf::from("/src")
+        );
+        assert_eq!(expand_home("~/src", None), PathBuf::from("~/src"));
+    }
+
+    #[test]
+    fn test_lossy() {
+        assert!(!lossy(""));
+        assert!(!lossy(
+            "{\n  \"projects\": [\"~/src\"],\n  \"stats\": true\n}\n"
+        ));
+        assert!(lossy("{\n  // mine\n  \"projects\": []\n}"));
+        assert!(lossy(r#"{"projects": [], "myNote": "keep me"}"#));
+        assert!(lossy("{"));
+    }
+
+    #[test]
+    fn test_write_config_backup() {
+        let dir = env::temp_dir().join(format!("cope-write-config-{}", std::process::id()));
+        let file = dir.join("config.json");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(&file, "{ /* mine */ }").unwrap();
+        write_config(&file, &Config::default()).unwrap();
+        assert_eq!(
+            fs::read_to_string(dir.join("config.json.bak")).unwrap(),
+            "{ /* mine */ }"
+        );
+        assert!(try_read_config(&file).is_ok());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_bad_config() {
+        let res = try_read_config(Path::new("Cargo.toml"));
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn test_read_project() {
+        let dir = env::temp_dir().join(format!("cope-project-{}", std::process::id()));
+        fs::create_dir_all(dir.join(".devcontainer")).unwrap();
+        assert_eq!(read_project(&dir), ProjectConfig::default());
+        fs::write(
+            project_file(&dir),
+            r#"{
+                // Comments are fine.
+                "args": ["--profile", "Backend", "--install-extension", "x.vsix", "--log=info", "-n"]
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            read_project(&dir).args,
+            ["--profile", "Backend", "--log=info", "-n"]
+        );
+        fs::write(project_file(&dir), "{\"args\": 1}").unwrap();
+        assert!(try_read_project(&dir).is_err());
+        assert_eq!(read_project(&dir), ProjectConfig::default());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_code_args() {
+        let project = ProjectConfig {
+            args: [
+                "--extensions-dir",
+                "/tmp/x",
+                "--disable-extension",
+                "foo",
+                "--profile=Work",
+                "--new-window=x",
+            ]
+            .map(String::from)
+            .into(),
+            workspace_folder: None,
+        };
+        let (allowed, rejected) = project.code_args();
+        assert_eq!(allowed, ["--disable-extension", "foo", "--profile=Work"]);
+        assert_eq!(rejected, ["--extensions-dir /tmp/x", "--new-window=x"]);
+    }
+
+    #[test]
+    fn test_missing_config() {
+        let c = try_read_config(Path::new("___NO_SUCH_CONFIG___.json")).unwrap();
+        assert!(c.projects.is_empty());
+    }
+}
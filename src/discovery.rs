@@ -0,0 +1,359 @@
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::compose;
+use crate::config;
+use crate::file_utils::{files_matching, has_dir};
+use crate::i18n::t;
+use crate::string_utils::hex;
+
+const CONFIG_FILE: &str = "devcontainer.json";
+
+/// A way of finding devcontainer configs for a directory.  The directory is
+/// treated as a repo root if any enabled strategy finds something there.
+pub trait Strategy {
+    /// The name used to turn this strategy on or off in the config file.
+    fn name(&self) -> &'static str;
+
+    /// The devcontainer.json-format files that apply to root.
+    fn configs(&self, root: &Path) -> Vec<PathBuf>;
+
+    /// Are there any configs for root?  This is asked of every directory
+    /// on the way up, so it must not write anything.
+    fn found(&self, root: &Path) -> bool {
+        !self.configs(root).is_empty()
+    }
+}
+
+/// How many levels of subdirs of `.devcontainer` are searched, unless the
+/// config file's `"discoveryDepth"` says otherwise.
+pub const DEFAULT_DEPTH: usize = 1;
+
+/// `.devcontainer/devcontainer.json`, and `.devcontainer/*/devcontainer.json`,
+/// or deeper.
+#[derive(Clone)]
+pub struct DevcontainerDir {
+    pub dir: String,
+    pub depth: usize,
+    /// Names of subdirs not to search.
+    pub ignore: Vec<String>,
+}
+
+impl DevcontainerDir {
+    /// Search dir to the default depth.
+    pub fn new(dir: &str) -> DevcontainerDir {
+        DevcontainerDir {
+            dir: dir.into(),
+            depth: DEFAULT_DEPTH,
+            ignore: vec![],
+        }
+    }
+}
+
+impl Strategy for DevcontainerDir {
+    fn name(&self) -> &'static str {
+        "devcontainer-dir"
+    }
+
+    fn configs(&self, root: &Path) -> Vec<PathBuf> {
+        files_matching(&root.join(&self.dir), CONFIG_FILE, self.depth, &self.ignore)
+    }
+}
+
+/// A single `.devcontainer.json` in the repo root.
+pub struct RootFile;
+
+impl Strategy for RootFile {
+    fn name(&self) -> &'static str {
+        "root-file"
+    }
+
+    fn configs(&self, root: &Path) -> Vec<PathBuf> {
+        let p = root.join(".devcontainer.json");
+        if p.is_file() { vec![p] } else { vec![] }
+    }
+}
+
+/// `*.code-workspace` files in the repo root whose settings name a config
+/// file with `"cope.configFile"`, relative to the workspace file.
+pub struct CodeWorkspace;
+
+impl CodeWorkspace {
+    /// The config file named in a workspace file's contents, if any.
+    pub fn named_config(contents: &str) -> Option<String> {
+        let v: serde_jsonc::Value = serde_jsonc::from_str(contents).ok()?;
+        v.get("settings")?
+            .get("cope.configFile")?
+            .as_str()
+            .map(String::from)
+    }
+}
+
+impl Strategy for CodeWorkspace {
+    fn name(&self) -> &'static str {
+        "code-workspace"
+    }
+
+    fn configs(&self, root: &Path) -> Vec<PathBuf> {
+        let Ok(entries) = fs::read_dir(root) else {
+            return vec![];
+        };
+        let mut res: Vec<PathBuf> = entries
+            .filter_map(|e| e.ok().map(|e| e.path()))
+            .filter(|p| p.extension().is_some_and(|e| e == "code-workspace"))
+            .filter_map(|p| {
+                let named = CodeWorkspace::named_config(&fs::read_to_string(&p).ok()?)?;
+                let config_file = root.join(named);
+                config_file.is_file().then_some(config_file)
+            })
+            .collect();
+        res.sort();
+        res
+    }
+}
+
+const COMPOSE_FILES: &[&str] = &[
+    "compose.yaml",
+    "compose.yml",
+    "docker-compose.yaml",
+    "docker-compose.yml",
+];
+
+/// Repos that have a compose file but no devcontainer config.  A config is
+/// generated (in cope's cache directory) that uses the compose file's first
+/// service.  Off by default, since it's a guess.
+pub struct ComposeOnly;
+
+impl ComposeOnly {
+    /// The compose file in root, and the first service in it.
+    fn service(root: &Path) -> Option<(PathBuf, String)> {
+        let compose_file = COMPOSE_FILES
+            .iter()
+            .map(|f| root.join(f))
+            .find(|p| p.is_file())?;
+        let contents = fs::read_to_string(&compose_file).unwrap_or_default();
+        let service = compose::service_names(&contents).into_iter().next()?;
+        Some((compose_file, service))
+    }
+
+    /// The generated devcontainer.json for this compose file.
+    pub fn generated(root: &Path, compose_file: &Path, service: &str) -> String {
+        let name = root.file_name().unwrap_or_default().to_string_lossy();
+        serde_jsonc::json!({
+            "name": format!("{name} (compose)"),
+            "dockerComposeFile": compose_file,
+            "service": service,
+            "workspaceFolder": format!("/workspaces/{name}"),
+        })
+        .to_string()
+    }
+}
+
+impl Strategy for ComposeOnly {
+    fn name(&self) -> &'static str {
+        "compose-only"
+    }
+
+    fn configs(&self, root: &Path) -> Vec<PathBuf> {
+        let Some((compose_file, service)) = ComposeOnly::service(root) else {
+            return vec![];
+        };
+        let dir = config::cache_dir()
+            .join("generated")
+            .join(hex(root.as_os_str().as_encoded_bytes()));
+        let config_file = dir.join(CONFIG_FILE);
+        let json_data = ComposeOnly::generated(root, &compose_file, &service);
+        if fs::create_dir_all(&dir).is_err() || fs::write(&config_file, json_data).is_err() {
+            return vec![];
+        }
+        vec![config_file]
+    }
+
+    fn found(&self, root: &Path) -> bool {
+        ComposeOnly::service(root).is_some()
+    }
+}
+
+/// The strategies to use, in the order given, with dir as the
+/// devcontainer-dir strategy.  Unknown names are skipped with a warning.
+pub fn from_names(names: &[String], dir: &DevcontainerDir) -> Vec<Box<dyn Strategy>> {
+    let mut seen: BTreeSet<&str> = BTreeSet::new();
+    names
+        .iter()
+        .filter(|n| seen.insert(n.as_str()))
+        .filter_map(|n| -> Option<Box<dyn Strategy>> {
+            match n.as_str() {
+                "devcontainer-dir" => Some(Box::new(dir.clone())),
+                "root-file" => Some(Box::new(RootFile)),
+                "code-workspace" => Some(Box::new(CodeWorkspace)),
+                "compose-only" => Some(Box::new(ComposeOnly)),
+                _ => {
+                    eprintln!("{}", t!("unknown-strategy", name = format!("{n:?}")));
+                    None
+                }
+            }
+        })
+        .collect()
+}
+
+/// The strategies used when the config file doesn't say.
+pub fn default_names() -> Vec<String> {
+    ["devcontainer-dir", "root-file", "code-workspace"]
+        .iter()
+        .map(|&s| s.into())
+        .collect()
+}
+
+/// Does any strategy find configs in root?
+pub fn has_configs(root: &Path, strategies: &[Box<dyn Strategy>]) -> bool {
+    strategies.iter().any(|s| s.found(root))
+}
+
+/// Walk up from pth to find the closest repo root that any strategy finds
+/// configs in, stopping after the directory that contains `stop`.  Returns
+/// the root and the configs, deduplicated, in strategy order.
+pub fn find_root(
+    pth: &Path,
+    strategies: &[Box<dyn Strategy>],
+    stop: Option<&str>,
+) -> Option<(PathBuf, Vec<PathBuf>)> {
+    for root in pth.ancestors() {
+        let mut configs: Vec<PathBuf> = Vec::new();
+        // Only the root that is found gets its configs, since some
+        // strategies write them out.
+        for s in strategies.iter().filter(|s| s.found(root)) {
+            for c in s.configs(root) {
+                if !configs.contains(&c) {
+                    configs.push(c);
+                }
+            }
+        }
+        if !configs.is_empty() {
+            return Some((root.to_path_buf(), configs));
+        }
+        // Found .git before any configs, which means we are unlikely to be
+        // in a devcontainer directory.
+        if let Some(stop) = stop
+            && has_dir(root, stop)
+        {
+            break;
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    #[test]
+    fn test_devcontainer_dir() {
+        let cur = env::current_dir().unwrap();
+        let s = DevcontainerDir::new(".devcontainer");
+        assert_eq!(
+            s.configs(&cur),
+            vec![cur.join(".devcontainer/devcontainer.json")]
+        );
+        assert!(s.configs(&cur.join("src")).is_empty());
+
+        let tmp = env::temp_dir().join(format!("cope-depth-{}", std::process::id()));
+        let deep = tmp.join(".devcontainer/envs/gpu");
+        fs::create_dir_all(&deep).unwrap();
+        fs::create_dir_all(tmp.join(".devcontainer/node_modules/x")).unwrap();
+        fs::write(deep.join(CONFIG_FILE), "{}").unwrap();
+        fs::write(
+            tmp.join(".devcontainer/node_modules/x").join(CONFIG_FILE),
+            "{}",
+        )
+        .unwrap();
+        assert!(s.configs(&tmp).is_empty());
+        let s = DevcontainerDir {
+            depth: 2,
+            ignore: vec!["node_modules".into()],
+            ..s
+        };
+        assert_eq!(s.configs(&tmp), vec![deep.join(CONFIG_FILE)]);
+        fs::remove_dir_all(&tmp).unwrap();
+    }
+
+    #[test]
+    fn test_root_file() {
+        let cur = env::current_dir().unwrap();
+        assert!(RootFile.configs(&cur).is_empty());
+    }
+
+    #[test]
+    fn test_named_config() {
+        assert_eq!(
+            CodeWorkspace::named_config(
+                r#"{"folders": [], "settings": {"cope.configFile": ".config/dev.json"}}"#
+            ),
+            Some(".config/dev.json".into())
+        );
+        assert_eq!(CodeWorkspace::named_config(r#"{"folders": []}"#), None);
+        assert_eq!(CodeWorkspace::named_config("nope"), None);
+    }
+
+    #[test]
+    fn test_generated() {
+        let json_data = ComposeOnly::generated(
+            Path::new("/src/app"),
+            Path::new("/src/app/compose.yml"),
+            "web",
+        );
+        let v: serde_jsonc::Value = serde_jsonc::from_str(&json_data).unwrap();
+        assert_eq!(v["service"], "web");
+        assert_eq!(v["workspaceFolder"], "/workspaces/app");
+    }
+
+    #[test]
+    fn test_compose_only_probe() {
+        let tmp = env::temp_dir().join(format!("cope-compose-only-{}", std::process::id()));
+        fs::create_dir_all(tmp.join("sub")).unwrap();
+        fs::write(tmp.join("compose.yml"), "services:\n  web:\n    image: x\n").unwrap();
+        assert!(ComposeOnly.found(&tmp));
+        assert!(!ComposeOnly.found(&tmp.join("sub")));
+        let generated = config::cache_dir()
+            .join("generated")
+            .join(hex(tmp.as_os_str().as_encoded_bytes()));
+        let _ = fs::remove_dir_all(&generated);
+        assert!(has_configs(&tmp, &[Box::new(ComposeOnly)]));
+        assert!(!generated.exists());
+        fs::remove_dir_all(&tmp).unwrap();
+    }
+
+    #[test]
+    fn test_from_names() {
+        let names: Vec<String> = vec![
+            "root-file".into(),
+            "bogus".into(),
+            "devcontainer-dir".into(),
+            "root-file".into(),
+        ];
+        let s = from_names(&names, &DevcontainerDir::new(".devcontainer"));
+        let found: Vec<&str> = s.iter().map(|s| s.name()).collect();
+        assert_eq!(found, vec!["root-file", "devcontainer-dir"]);
+    }
+
+    #[test]
+    fn test_find_root() {
+        let cur = env::current_dir().unwrap();
+        let strategies = from_names(&default_names(), &DevcontainerDir::new(".devcontainer"));
+        let (root, configs) =
+            find_root(&cur.join("src/main.rs"), &strategies, Some(".git")).unwrap();
+        assert_eq!(root, cur);
+        assert_eq!(configs.len(), 1);
+
+        let none = from_names(
+            &["root-file".into()],
+            &DevcontainerDir::new(".devcontainer"),
+        );
+        assert_eq!(
+            find_root(&cur.join("src/main.rs"), &none, Some(".git")),
+            None
+        );
+        assert_eq!(find_root(&cur.join("src/main.rs"), &[], None), None);
+    }
+}
@@ -0,0 +1,239 @@
+use std::path::Path;
+use std::process::Command;
+
+use crate::command_utils::capture;
+use crate::engine;
+
+/// Labels that the Dev Containers extension puts on the containers it
+/// creates.
+pub const LOCAL_FOLDER_LABEL: &str = "devcontainer.local_folder";
+pub const CONFIG_FILE_LABEL: &str = "devcontainer.config_file";
+
+/// The devcontainer.json properties that the devcontainer CLI and the
+/// extension bake into the images they build, and the features they use.
+pub const METADATA_LABEL: &str = "devcontainer.metadata";
+
+/// What a container's metadata label says about how to use it.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Metadata {
+    pub workspace_folder: Option<String>,
+    pub remote_user: Option<String>,
+    pub container_user: Option<String>,
+}
+
+impl Metadata {
+    /// The user to run as: remoteUser, else containerUser.
+    pub fn user(&self) -> Option<&str> {
+        self.remote_user
+            .as_deref()
+            .or(self.container_user.as_deref())
+    }
+}
+
+/// Parse the metadata label, which is an array of devcontainer.json
+/// fragments (or just one).  Later fragments override earlier ones, as
+/// they do when the extension merges them.
+pub fn parse_metadata(label: &str) -> Metadata {
+    let Ok(v) = serde_jsonc::from_str::<serde_jsonc::Value>(label) else {
+        return Metadata::default();
+    };
+    let fragments = match v {
+        serde_jsonc::Value::Array(a) => a,
+        other => vec![other],
+    };
+    let mut res = Metadata::default();
+    for f in fragments {
+        let get = |key: &str| f[key].as_str().map(String::from);
+        res.workspace_folder = get("workspaceFolder").or(res.workspace_folder);
+        res.remote_user = get("remoteUser").or(res.remote_user);
+        res.container_user = get("containerUser").or(res.container_user);
+    }
+    res
+}
+
+/// A container found by `inspect_container`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Inspected {
+    /// The name, with its leading `/`.
+    pub name: String,
+    pub working_dir: String,
+    pub metadata: Metadata,
+}
+
+impl Inspected {
+    /// Where to open or run things: the metadata's workspace folder, the
+    /// image's working directory, or `/`.
+    pub fn folder(&self) -> String {
+        match &self.metadata.workspace_folder {
+            Some(f) => f.clone(),
+            None if self.working_dir.is_empty() => "/".into(),
+            None => self.working_dir.clone(),
+        }
+    }
+}
+
+/// The filters that select the container for this repo and config.
+pub fn container_filters(root: &Path, config_file: &Path) -> Vec<String> {
+    vec![
+        "--filter".into(),
+        format!("label={LOCAL_FOLDER_LABEL}={}", root.display()),
+        "--filter".into(),
+        format!("label={CONFIG_FILE_LABEL}={}", config_file.display()),
+    ]
+}
+
+/// Find the id of the container for this repo and config.  Unless `all` is
+/// set, only running containers are found.
+pub fn find_container(root: &Path, config_file: &Path, all: bool) -> Option<String> {
+    let out = capture(
+        Command::new(engine::program())
+            .args(["ps", if all { "-aq" } else { "-q" }])
+            .args(container_filters(root, config_file)),
+    )?;
+    out.lines().next().map(String::from)
+}
+
+/// The name, working directory and devcontainer metadata of any container,
+/// given its name or id.  The metadata is there even when no local
+/// devcontainer.json describes the container, since it comes with the
+/// image.
+pub fn inspect_container(name_or_id: &str) -> Option<Inspected> {
+    let format = format!(
+        "{{{{.Name}}}}\t{{{{.Config.WorkingDir}}}}\t{{{{index .Config.Labels {METADATA_LABEL:?}}}}}"
+    );
+    let out = capture(
+        Command::new(engine::program()).args(["inspect", "--format", &format, name_or_id]),
+    )?;
+    parse_inspect(&out)
+}
+
+/// Parse the output of `inspect_container`'s `docker inspect`.
+fn parse_inspect(out: &str) -> Option<Inspected> {
+    let mut parts = out.splitn(3, '\t');
+    Some(Inspected {
+        name: parts.next()?.into(),
+        working_dir: parts.next()?.into(),
+        metadata: parse_metadata(parts.next().unwrap_or_default()),
+    })
+}
+
+/// Is path a directory inside the running container?
+pub fn is_dir(id: &str, path: &str) -> bool {
+    capture(Command::new(engine::program()).args(["exec", id, "test", "-d", path])).is_some()
+}
+
+/// The output of `docker port` for a container.
+pub fn published_ports(id: &str) -> String {
+    capture(Command::new(engine::program()).args(["port", id])).unwrap_or_default()
+}
+
+/// Arguments for docker that run a command in the container, as the given
+/// user, in the given directory.  Stdin is always connected; a tty is only
+/// allocated if asked for.
+pub fn exec_args(
+    id: &str,
+    user: Option<&str>,
+    dir: &str,
+    tty: bool,
+    cmd: &[String],
+) -> Vec<String> {
+    let mut args: Vec<String> = vec![
+        engine::program().into(),
+        "exec".into(),
+        if tty { "-it" } else { "-i" }.into(),
+    ];
+    if let Some(u) = user {
+        args.extend(["-u".into(), u.into()]);
+    }
+    args.extend(["-w".into(), dir.into(), id.into()]);
+    args.extend(cmd.iter().cloned());
+    args
+}
+
+/// Arguments for docker that start an interactive login shell in the
+/// container, as the given user, in the given directory.
+pub fn shell_args(id: &str, user: Option<&str>, dir: &str) -> Vec<String> {
+    // Not every image has bash.
+    exec_args(
+        id,
+        user,
+        dir,
+        true,
+        &[
+            "sh".into(),
+            "-c".into(),
+            "command -v bash >/dev/null && exec bash -l || exec sh -l".into(),
+        ],
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_container_filters() {
+        let f = container_filters(
+            Path::new("/foo"),
+            Path::new("/foo/.devcontainer/devcontainer.json"),
+        );
+        assert_eq!(f[1], "label=devcontainer.local_folder=/foo");
+        assert_eq!(
+            f[3],
+            "label=devcontainer.config_file=/foo/.devcontainer/devcontainer.json"
+        );
+    }
+
+    #[test]
+    fn test_parse_metadata() {
+        let m = parse_metadata(
+            r#"[{"id": "ghcr.io/devcontainers/features/common-utils:2"}, {"remoteUser": "root"}, {"remoteUser": "vscode", "workspaceFolder": "/src"}]"#,
+        );
+        assert_eq!(m.user(), Some("vscode"));
+        assert_eq!(m.workspace_folder.as_deref(), Some("/src"));
+        let m = parse_metadata(r#"{"containerUser": "node"}"#);
+        assert_eq!(m.user(), Some("node"));
+        assert_eq!(parse_metadata(""), Metadata::default());
+    }
+
+    #[test]
+    fn test_parse_inspect() {
+        let c = parse_inspect("/redis\t/data\t").unwrap();
+        assert_eq!((c.name.as_str(), c.folder()), ("/redis", "/data".into()));
+        let c = parse_inspect("/dev\t\t[{\"workspaceFolder\": \"/w\"}]").unwrap();
+        assert_eq!(c.folder(), "/w");
+        let c = parse_inspect("/dev\t\t").unwrap();
+        assert_eq!(c.folder(), "/");
+        assert_eq!(parse_inspect("junk"), None);
+    }
+
+    #[test]
+    fn test_exec_args() {
+        let a = exec_args("abc", None, "/w/src", false, &["ls".into(), "-l".into()]);
+        assert_eq!(
+            a,
+            ["docker", "exec", "-i", "-w", "/w/src", "abc", "ls", "-l"]
+        );
+    }
+
+    #[test]
+    fn test_shell_args() {
+        let a = shell_args("abc", Some("vscode"), "/workspaces/foo");
+        assert_eq!(
+            a[..9],
+            [
+                "docker",
+                "exec",
+                "-it",
+                "-u",
+                "vscode",
+                "-w",
+                "/workspaces/foo",
+                "abc",
+                "sh"
+            ]
+        );
+        let a = shell_args("abc", None, "/w");
+        assert_eq!(a[3], "-w");
+    }
+}
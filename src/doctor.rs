@@ -0,0 +1,143 @@
+use std::env;
+use std::process::Command;
+
+use crate::command_utils::{capture, is_offline};
+use crate::config;
+use crate::discovery;
+use crate::editor;
+use crate::engine::{self, Engine};
+use crate::i18n::t;
+
+/// The result of one health check.
+#[derive(Debug, PartialEq)]
+pub struct Check {
+    pub name: String,
+    pub ok: bool,
+    pub detail: String,
+}
+
+/// Check the things cope needs in order to work.
+pub fn run() -> Vec<Check> {
+    checks(is_offline())
+}
+
+/// The checks, without asking the container engine anything if offline.
+fn checks(offline: bool) -> Vec<Check> {
+    let path = env::var_os("PATH");
+    let code = editor::locate(editor::bin(), path.as_deref(), env::var_os("HOME"));
+    let docker = if offline {
+        None
+    } else {
+        capture(Command::new(engine::program()).args([
+            "version",
+            "--format",
+            "{{.Server.Version}}",
+        ]))
+    };
+    let config_file = config::config_file();
+    let cfg = config::try_read_config(&config_file);
+    let names = cfg
+        .as_ref()
+        .ok()
+        .and_then(|c| c.discovery.clone())
+        .unwrap_or_else(discovery::default_names);
+    let strategies = discovery::from_names(
+        &names,
+        &discovery::DevcontainerDir::new(crate::DEVCONTAINER_DIR),
+    );
+    let enabled: Vec<&str> = strategies.iter().map(|s| s.name()).collect();
+
+    let engine = engine::current();
+    let engine_detail = match engine {
+        Engine::Podman { rootless: true } => {
+            let socket = env::var_os("XDG_RUNTIME_DIR").map(|d| engine::rootless_socket(&d));
+            t!(
+                "doctor-engine-rootless",
+                socket = socket.map_or("?".into(), |s| s.display().to_string())
+            )
+        }
+        _ => engine.program().to_string(),
+    };
+
+    vec![
+        Check {
+            name: t!("doctor-engine"),
+            ok: true,
+            detail: engine_detail,
+        },
+        Check {
+            name: t!("doctor-code"),
+            ok: code.is_some(),
+            detail: code.map_or(t!("doctor-code-missing"), |p| p.display().to_string()),
+        },
+        Check {
+            name: t!("doctor-docker"),
+            ok: docker.is_some() || offline,
+            detail: match docker {
+                Some(v) => t!("doctor-docker-version", version = v),
+                None if offline => t!("doctor-offline"),
+                None => t!("doctor-docker-missing"),
+            },
+        },
+        Check {
+            name: t!("doctor-config"),
+            ok: cfg.is_ok(),
+            detail: cfg
+                .err()
+                .unwrap_or_else(|| config_file.display().to_string()),
+        },
+        Check {
+            name: t!("doctor-discovery"),
+            ok: !enabled.is_empty(),
+            detail: if enabled.is_empty() {
+                t!("doctor-discovery-none")
+            } else {
+                enabled.join(", ")
+            },
+        },
+    ]
+}
+
+/// One line per check.
+pub fn format(checks: &[Check]) -> String {
+    checks
+        .iter()
+        .map(|c| {
+            format!(
+                "[{}] {}: {}\n",
+                if c.ok { "ok" } else { "!!" },
+                c.name,
+                c.detail
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format() {
+        let checks = [
+            Check {
+                name: "a".into(),
+                ok: true,
+                detail: "fine".into(),
+            },
+            Check {
+                name: "b".into(),
+                ok: false,
+                detail: "broken".into(),
+            },
+        ];
+        assert_eq!(format(&checks), "[ok] a: fine\n[!!] b: broken\n");
+    }
+
+    #[test]
+    fn test_run() {
+        let checks = checks(true);
+        assert_eq!(checks.len(), 5);
+        assert_eq!(checks[2].detail, t!("doctor-offline"));
+    }
+}
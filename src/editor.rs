@@ -0,0 +1,451 @@
+use std::collections::BTreeMap;
+use std::env;
+use std::ffi::{OsStr, OsString};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use crate::config;
+use crate::packaging;
+use crate::shim;
+
+/// Which devcontainer extension the editor has, which decides how the
+/// config file is described in the URI.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UriVariant {
+    /// Microsoft's Dev Containers extension.
+    Microsoft,
+    /// The community "Open Remote - Containers" extension that VSCodium
+    /// users install from Open VSX, which wants a plainer description.
+    OpenRemote,
+}
+
+impl UriVariant {
+    /// The name used in the config file.
+    pub fn from_name(name: &str) -> Option<UriVariant> {
+        match name {
+            "microsoft" => Some(UriVariant::Microsoft),
+            "open-remote" => Some(UriVariant::OpenRemote),
+            _ => None,
+        }
+    }
+}
+
+/// The editors that cope knows how to drive.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Profile {
+    Code,
+    /// VS Code Insiders, which installs alongside VS Code as
+    /// `code-insiders`, with its own instance and its own copy of the Dev
+    /// Containers extension.
+    Insiders,
+    /// VSCodium, or a code-oss build, which still open
+    /// `vscode-remote://dev-container+` URIs, through the Open Remote
+    /// extension.
+    Codium,
+    /// Cursor, which takes the same `--folder-uri` and `--file-uri`
+    /// arguments as VS Code.
+    Cursor,
+    /// Windsurf, which is built from code-oss and gets its extensions from
+    /// Open VSX, like VSCodium.
+    Windsurf,
+}
+
+impl Profile {
+    /// The name used in the config file and on the command line.
+    pub fn from_name(name: &str) -> Option<Profile> {
+        match name {
+            "code" => Some(Profile::Code),
+            "insiders" | "code-insiders" => Some(Profile::Insiders),
+            "codium" | "vscodium" => Some(Profile::Codium),
+            "cursor" => Some(Profile::Cursor),
+            "windsurf" => Some(Profile::Windsurf),
+            _ => None,
+        }
+    }
+
+    /// The binary to look for on the PATH.
+    pub fn bin(&self) -> &'static str {
+        match self {
+            Profile::Code => "code",
+            Profile::Insiders => "code-insiders",
+            Profile::Codium => "codium",
+            Profile::Cursor => "cursor",
+            Profile::Windsurf => "windsurf",
+        }
+    }
+
+    /// The profile whose binary this is.
+    pub fn from_bin(bin: &str) -> Option<Profile> {
+        Profile::from_name(bin).filter(|p| p.bin() == bin)
+    }
+
+    /// The flatpak application ID, for the builds on Flathub.
+    pub fn flatpak_id(&self) -> Option<&'static str> {
+        match self {
+            Profile::Code => Some("com.visualstudio.code"),
+            Profile::Codium => Some("com.vscodium.codium"),
+            _ => None,
+        }
+    }
+
+    /// The snap name, for the builds in the Snap Store.
+    pub fn snap_name(&self) -> Option<&'static str> {
+        match self {
+            Profile::Code => Some("code"),
+            Profile::Insiders => Some("code-insiders"),
+            Profile::Codium => Some("codium"),
+            _ => None,
+        }
+    }
+
+    /// Insiders uses the same extension, and so the same URIs, as VS Code,
+    /// and Cursor's own devcontainer extension accepts them too.  VSCodium
+    /// and Windsurf can't use Microsoft's extension.
+    pub fn variant(&self) -> UriVariant {
+        match self {
+            Profile::Codium | Profile::Windsurf => UriVariant::OpenRemote,
+            _ => UriVariant::Microsoft,
+        }
+    }
+}
+
+/// The profile of a binary, from its name.
+pub fn profile_for(bin_name: &str) -> Profile {
+    if bin_name.starts_with("code-insiders") {
+        Profile::Insiders
+    } else if bin_name.starts_with("codium") || bin_name.starts_with("code-oss") {
+        Profile::Codium
+    } else if bin_name.starts_with("cursor") {
+        Profile::Cursor
+    } else if bin_name.starts_with("windsurf") {
+        Profile::Windsurf
+    } else {
+        Profile::Code
+    }
+}
+
+/// The URI variant that suits a binary, from its name.
+pub fn variant_for(bin_name: &str) -> UriVariant {
+    profile_for(bin_name).variant()
+}
+
+/// The name of the binary that really runs, following symlinks, since some
+/// distributions install VSCodium as `code`.
+pub fn target_name(bin: &Path) -> String {
+    fs::canonicalize(bin)
+        .ok()
+        .as_deref()
+        .unwrap_or(bin)
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// The editor binary: the `--cope-editor` or `--cope-code-bin` flag (or
+/// `--cope-insiders`, or the name cope was invoked as), then COPE_CODE_BIN,
+/// then the `"codeBin"` or `"editor"` setting, then `code`.
+pub fn choose_bin(flag: Option<String>, env: Option<String>, setting: Option<String>) -> String {
+    flag.or(env)
+        .or(setting)
+        .filter(|b| !b.is_empty())
+        .unwrap_or_else(|| "code".into())
+}
+
+/// The binary for an editor's name, such as "insiders", or anything else
+/// as a binary to look for or a path.
+pub fn named(name: &str) -> String {
+    Profile::from_name(name).map_or_else(|| name.into(), |p| p.bin().into())
+}
+
+/// The editor that cope was invoked as, through a symlink, from the
+/// `"invokedAs"` mapping, or because the name is one of the known editors'.
+pub fn for_argv0(argv0: &OsStr, mapping: &BTreeMap<String, String>) -> Option<String> {
+    let name = Path::new(argv0).file_name()?.to_str()?;
+    if let Some(target) = mapping.get(name) {
+        return Some(named(target));
+    }
+    (name != "code" && profile_for(name).bin() == name).then(|| name.into())
+}
+
+/// The binary the config file asks for, if any.
+pub fn setting(cfg: &config::Config) -> Option<String> {
+    cfg.code_bin.clone().or_else(|| {
+        cfg.editor
+            .as_deref()
+            .and_then(Profile::from_name)
+            .map(|p| p.bin().into())
+    })
+}
+
+static BIN: OnceLock<String> = OnceLock::new();
+
+/// Pick the editor binary for this run, given the command line flag.
+pub fn init(flag: Option<String>) {
+    let _ = BIN.set(choose_bin(
+        flag,
+        env::var("COPE_CODE_BIN").ok(),
+        setting(&config::load()),
+    ));
+}
+
+/// The editor binary for this run, either a name to look for on the PATH
+/// or a path.
+pub fn bin() -> &'static str {
+    BIN.get_or_init(|| {
+        choose_bin(
+            None,
+            env::var("COPE_CODE_BIN").ok(),
+            setting(&config::load()),
+        )
+    })
+}
+
+/// Where the editor binary is, skipping the `cope shim`.
+pub fn find(bin: &str, path: Option<&std::ffi::OsStr>) -> Option<PathBuf> {
+    if bin.contains('/') {
+        let p = PathBuf::from(bin);
+        p.is_file().then_some(p)
+    } else {
+        shim::real(bin, path)
+    }
+}
+
+/// Tried in order when `code` isn't on the PATH.
+const FALLBACK_NAMES: &[&str] = &["code", "code-insiders", "codium"];
+
+/// Where VS Code gets installed without putting `code` on the PATH.  Ones
+/// starting with `~/` are in the home directory.
+const INSTALL_PATHS: &[&str] = &[
+    "/usr/share/code/bin/code",
+    "/usr/lib/code/bin/code",
+    "/opt/visual-studio-code/bin/code",
+    "/snap/bin/code",
+    "/Applications/Visual Studio Code.app/Contents/Resources/app/bin/code",
+    "~/Applications/Visual Studio Code.app/Contents/Resources/app/bin/code",
+    "~/.local/share/code/bin/code",
+];
+
+/// Find the editor binary.  If it is just the default `code`, look for the
+/// other VS Code builds, then in the usual install locations.
+pub fn locate(bin: &str, path: Option<&OsStr>, home: Option<OsString>) -> Option<PathBuf> {
+    if let Some(found) = find(bin, path) {
+        return Some(found);
+    }
+    if bin != "code" {
+        return None;
+    }
+    FALLBACK_NAMES
+        .iter()
+        .find_map(|name| shim::real(name, path))
+        .or_else(|| {
+            INSTALL_PATHS
+                .iter()
+                .map(|p| config::expand_home(p, home.clone()))
+                .find(|p| p.is_file())
+        })
+}
+
+/// Is the editor at bin the Windows one, seen from WSL through a drive
+/// mount such as `/mnt/c`?  An editor installed in the distribution isn't.
+pub fn is_windows_side(bin: &Path) -> bool {
+    let mut parts = bin.components().map(|c| c.as_os_str().to_string_lossy());
+    parts.next().as_deref() == Some("/")
+        && parts.next().as_deref() == Some("mnt")
+        && parts
+            .next()
+            .is_some_and(|drive| drive.len() == 1 && drive.chars().all(|c| c.is_ascii_alphabetic()))
+}
+
+/// The command line that runs the editor, before its arguments: the words
+/// of a command-line binary, the binary that `locate` finds, or a flatpak
+/// or snap wrapper.  None if the editor can't be found.
+pub fn command(
+    bin: &str,
+    path: Option<&OsStr>,
+    home: Option<OsString>,
+    root: &Path,
+) -> Option<Vec<OsString>> {
+    if let Some(words) = packaging::words(bin) {
+        return Some(words.into_iter().map(OsString::from).collect());
+    }
+    if let Some(found) = locate(bin, path, home.clone()) {
+        return Some(vec![found.into_os_string()]);
+    }
+    let p = packaging::detect(
+        Profile::from_bin(bin)?,
+        Path::new(&home.unwrap_or_default()),
+        root,
+    )?;
+    Some(p.prefix().into_iter().map(OsString::from).collect())
+}
+
+/// The URI variant for this run: the `"uriVariant"` setting, or whatever
+/// suits the editor that will be run.
+pub fn current_variant() -> UriVariant {
+    static VARIANT: OnceLock<UriVariant> = OnceLock::new();
+    *VARIANT.get_or_init(|| {
+        config::load()
+            .uri_variant
+            .as_deref()
+            .and_then(UriVariant::from_name)
+            .or_else(|| {
+                locate(bin(), env::var_os("PATH").as_deref(), env::var_os("HOME"))
+                    .map(|p| variant_for(&target_name(&p)))
+            })
+            .unwrap_or(UriVariant::Microsoft)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_windows_side() {
+        assert!(is_windows_side(Path::new(
+            "/mnt/c/Users/me/AppData/Local/Programs/Microsoft VS Code/bin/code"
+        )));
+        assert!(!is_windows_side(Path::new("/usr/bin/code")));
+        assert!(!is_windows_side(Path::new("/mnt/data/bin/code")));
+        assert!(!is_windows_side(Path::new("code")));
+    }
+
+    #[test]
+    fn test_variant_for() {
+        assert_eq!(variant_for("code"), UriVariant::Microsoft);
+        assert_eq!(variant_for("codium"), UriVariant::OpenRemote);
+        assert_eq!(variant_for("codium-insiders"), UriVariant::OpenRemote);
+        assert_eq!(
+            UriVariant::from_name("open-remote"),
+            Some(UriVariant::OpenRemote)
+        );
+        assert_eq!(UriVariant::from_name("nope"), None);
+    }
+
+    #[test]
+    fn test_profile() {
+        assert_eq!(profile_for("code-insiders"), Profile::Insiders);
+        assert_eq!(profile_for("code"), Profile::Code);
+        assert_eq!(profile_for("code-oss"), Profile::Codium);
+        assert_eq!(Profile::from_name("vscodium"), Some(Profile::Codium));
+        assert_eq!(profile_for("cursor"), Profile::Cursor);
+        assert_eq!(Profile::Cursor.variant(), UriVariant::Microsoft);
+        assert_eq!(variant_for("windsurf"), UriVariant::OpenRemote);
+        assert_eq!(
+            Profile::from_name("insiders").map(|p| p.bin()),
+            Some("code-insiders")
+        );
+        assert_eq!(Profile::from_name("nope"), None);
+        assert_eq!(Profile::from_bin("windsurf"), Some(Profile::Windsurf));
+        assert_eq!(Profile::from_bin("insiders"), None);
+        assert_eq!(Profile::Codium.flatpak_id(), Some("com.vscodium.codium"));
+        assert_eq!(Profile::Cursor.snap_name(), None);
+        let cfg = config::Config {
+            editor: Some("insiders".into()),
+            ..Default::default()
+        };
+        assert_eq!(setting(&cfg), Some("code-insiders".into()));
+        assert_eq!(variant_for("code-insiders"), UriVariant::Microsoft);
+    }
+
+    #[test]
+    fn test_named() {
+        assert_eq!(named("cursor"), "cursor");
+        assert_eq!(named("insiders"), "code-insiders");
+        assert_eq!(
+            named("/tmp/VSCode-linux-x64/bin/code"),
+            "/tmp/VSCode-linux-x64/bin/code"
+        );
+    }
+
+    #[test]
+    fn test_for_argv0() {
+        let mut mapping = BTreeMap::new();
+        assert_eq!(for_argv0(OsStr::new("/usr/local/bin/cope"), &mapping), None);
+        assert_eq!(for_argv0(OsStr::new("code"), &mapping), None);
+        assert_eq!(
+            for_argv0(OsStr::new("/home/me/bin/codium"), &mapping),
+            Some("codium".into())
+        );
+        assert_eq!(
+            for_argv0(OsStr::new("code-insiders"), &mapping),
+            Some("code-insiders".into())
+        );
+        mapping.insert("ci".into(), "insiders".into());
+        mapping.insert("dev".into(), "/opt/code-dev/bin/code".into());
+        assert_eq!(
+            for_argv0(OsStr::new("ci"), &mapping),
+            Some("code-insiders".into())
+        );
+        assert_eq!(
+            for_argv0(OsStr::new("dev"), &mapping),
+            Some("/opt/code-dev/bin/code".into())
+        );
+    }
+
+    #[test]
+    fn test_choose_bin() {
+        assert_eq!(choose_bin(None, None, None), "code");
+        assert_eq!(
+            choose_bin(None, None, Some("/opt/code/bin/code".into())),
+            "/opt/code/bin/code"
+        );
+        assert_eq!(
+            choose_bin(None, Some("env".into()), Some("cfg".into())),
+            "env"
+        );
+        assert_eq!(
+            choose_bin(Some("flag".into()), Some("env".into()), None),
+            "flag"
+        );
+        assert_eq!(choose_bin(None, Some(String::new()), None), "code");
+    }
+
+    #[test]
+    fn test_find() {
+        assert_eq!(find("/___NO_SUCH_DIR___/code", None), None);
+        assert!(find("sh", env::var_os("PATH").as_deref()).is_some());
+    }
+
+    #[test]
+    fn test_locate() {
+        let none = Some(OsStr::new("/___NO_SUCH_DIR___"));
+        assert_eq!(locate("cursor", none, None), None);
+        let dir = env::temp_dir().join(format!("cope-test-locate-{}", std::process::id()));
+        let bin = dir.join(".local/share/code/bin/code");
+        fs::create_dir_all(bin.parent().unwrap()).unwrap();
+        fs::write(&bin, "").unwrap();
+        assert_eq!(locate("code", none, Some(dir.clone().into())), Some(bin));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_command() {
+        let none = Path::new("/___NO_SUCH_DIR___");
+        let path = env::var_os("PATH");
+        assert_eq!(command("cursor", Some(none.as_os_str()), None, none), None);
+        assert_eq!(
+            command("flatpak run com.visualstudio.code", None, None, none).map(|c| c.len()),
+            Some(3)
+        );
+        assert_eq!(
+            command("sh", path.as_deref(), None, none),
+            Some(vec![
+                locate("sh", path.as_deref(), None)
+                    .unwrap()
+                    .into_os_string()
+            ])
+        );
+    }
+
+    #[test]
+    fn test_target_name() {
+        assert_eq!(
+            target_name(Path::new("/___NO_SUCH_DIR___/codium")),
+            "codium"
+        );
+        assert_eq!(target_name(Path::new("Cargo.toml")), "Cargo.toml");
+    }
+}
@@ -0,0 +1,348 @@
+use serde::Serialize;
+use std::env;
+use std::ffi::OsStr;
+use std::path::PathBuf;
+use std::process::Command;
+use std::sync::OnceLock;
+
+use crate::command_utils::capture;
+use crate::config;
+use crate::file_utils::which;
+
+/// The container engine that runs the devcontainers.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Engine {
+    Docker,
+    Podman { rootless: bool },
+}
+
+impl Engine {
+    /// The command that talks to the engine.
+    pub fn program(&self) -> &'static str {
+        match self {
+            Engine::Docker => "docker",
+            Engine::Podman { .. } => "podman",
+        }
+    }
+
+    /// The context that the Dev Containers extension records in the
+    /// devcontainer URI, if the engine can't be asked.  Rootless Podman has
+    /// no Docker Desktop context, so the extension uses the default one.
+    pub fn default_context(&self) -> &'static str {
+        match self {
+            Engine::Docker => "desktop-linux",
+            Engine::Podman { .. } => "default",
+        }
+    }
+}
+
+/// Where rootless Podman's Docker-compatible socket lives.
+pub fn rootless_socket(runtime_dir: &OsStr) -> PathBuf {
+    PathBuf::from(runtime_dir).join("podman/podman.sock")
+}
+
+/// Work out the engine.  An `"engine"` setting wins; otherwise a DOCKER_HOST
+/// that points at a Podman socket, or a PATH with podman but no docker,
+/// means Podman.  Podman running as anyone other than root is rootless.
+pub fn detect(
+    setting: Option<&str>,
+    docker_host: Option<&str>,
+    path: Option<&OsStr>,
+    root: bool,
+) -> Engine {
+    let podman = match setting {
+        Some("podman") => true,
+        Some("docker") => false,
+        _ => {
+            docker_host.is_some_and(|h| h.contains("podman"))
+                || (which("docker", path).is_none() && which("podman", path).is_some())
+        }
+    };
+    if podman {
+        Engine::Podman { rootless: !root }
+    } else {
+        Engine::Docker
+    }
+}
+
+/// The engine for this run.
+pub fn current() -> Engine {
+    static ENGINE: OnceLock<Engine> = OnceLock::new();
+    *ENGINE.get_or_init(|| {
+        detect(
+            config::load().engine.as_deref(),
+            env::var("DOCKER_HOST").ok().as_deref(),
+            env::var_os("PATH").as_deref(),
+            nix::unistd::geteuid().is_root(),
+        )
+    })
+}
+
+/// The context name out of the output of `docker context show`.
+pub fn parse_context(output: &str) -> Option<String> {
+    output
+        .lines()
+        .next()
+        .map(str::trim)
+        .filter(|c| !c.is_empty())
+        .map(String::from)
+}
+
+/// The docker context that is active, such as "desktop-linux", "colima" or
+/// "orbstack", so that the extension attaches to the same daemon.  Podman
+/// has no contexts.
+pub fn detect_context(engine: Engine) -> String {
+    let asked = match engine {
+        Engine::Docker => capture(Command::new("docker").args(["context", "show"]))
+            .and_then(|o| parse_context(&o)),
+        Engine::Podman { .. } => None,
+    };
+    asked.unwrap_or_else(|| engine.default_context().into())
+}
+
+/// The context: COPE_DOCKER_CONTEXT, then the `"dockerContext"` setting,
+/// and only then whatever detect finds.
+pub fn pick_context(
+    env: Option<String>,
+    setting: Option<String>,
+    detect: impl FnOnce() -> String,
+) -> String {
+    env.filter(|c| !c.is_empty())
+        .or(setting)
+        .unwrap_or_else(detect)
+}
+
+/// The context for this run.
+pub fn context() -> &'static str {
+    static CONTEXT: OnceLock<String> = OnceLock::new();
+    CONTEXT.get_or_init(|| {
+        pick_context(
+            env::var("COPE_DOCKER_CONTEXT").ok(),
+            config::load().docker_context,
+            || detect_context(current()),
+        )
+    })
+}
+
+/// A docker host on another machine, from the `"dockerHost"` setting or
+/// DOCKER_HOST.  Local sockets don't count.
+pub fn remote_host(setting: Option<&str>, docker_host: Option<&str>) -> Option<String> {
+    setting
+        .or(docker_host)
+        .filter(|h| h.starts_with("ssh://") || h.starts_with("tcp://"))
+        .map(String::from)
+}
+
+/// The host that goes in the container id.  Rootless Podman listens on a
+/// socket of its own rather than the Docker one, so the extension has to be
+/// told where it is, even though it's local: DOCKER_HOST if that is set,
+/// otherwise the socket under XDG_RUNTIME_DIR.
+pub fn host(
+    engine: Engine,
+    setting: Option<&str>,
+    docker_host: Option<&str>,
+    runtime_dir: Option<&OsStr>,
+) -> Option<String> {
+    match engine {
+        Engine::Podman { rootless: true } => setting
+            .or(docker_host)
+            .map(String::from)
+            .or_else(|| runtime_dir.map(|d| format!("unix://{}", rootless_socket(d).display()))),
+        _ => remote_host(setting, docker_host),
+    }
+}
+
+/// The `"settings"` object for the container id.  The extension only adds
+/// the host for remote engines, before the context.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Settings {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub host: Option<String>,
+    pub context: String,
+}
+
+/// The `"settings"` object for this run.
+pub fn settings() -> &'static Settings {
+    static SETTINGS: OnceLock<Settings> = OnceLock::new();
+    SETTINGS.get_or_init(|| Settings {
+        host: host(
+            current(),
+            config::load().docker_host.as_deref(),
+            env::var("DOCKER_HOST").ok().as_deref(),
+            env::var_os("XDG_RUNTIME_DIR").as_deref(),
+        ),
+        context: context().into(),
+    })
+}
+
+/// Is this plain Docker running on this machine, rather than Docker
+/// Desktop's VM, another engine, or a remote host?  The extension marks
+/// those ids with `"localDocker":true`.
+pub fn is_local_docker(engine: Engine, context: &str, host: Option<&str>) -> bool {
+    engine == Engine::Docker && context == "default" && host.is_none()
+}
+
+/// The `"localDocker"` value for this run: the setting, or detected.
+pub fn local_docker() -> bool {
+    static LOCAL: OnceLock<bool> = OnceLock::new();
+    *LOCAL.get_or_init(|| {
+        let cfg = config::load();
+        cfg.local_docker.unwrap_or_else(|| {
+            let host = remote_host(
+                cfg.docker_host.as_deref(),
+                env::var("DOCKER_HOST").ok().as_deref(),
+            );
+            is_local_docker(current(), context(), host.as_deref())
+        })
+    })
+}
+
+/// The command that talks to the engine for this run.
+pub fn program() -> &'static str {
+    current().program()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect() {
+        let none = Some(OsStr::new("/___NO_SUCH_DIR___"));
+        assert_eq!(detect(None, None, none, false), Engine::Docker);
+        assert_eq!(
+            detect(Some("podman"), None, none, false),
+            Engine::Podman { rootless: true }
+        );
+        assert_eq!(
+            detect(Some("podman"), None, none, true),
+            Engine::Podman { rootless: false }
+        );
+        assert_eq!(
+            detect(
+                None,
+                Some("unix:///run/user/1000/podman/podman.sock"),
+                none,
+                false
+            ),
+            Engine::Podman { rootless: true }
+        );
+        assert_eq!(
+            detect(
+                Some("docker"),
+                Some("unix:///run/podman/podman.sock"),
+                none,
+                false
+            ),
+            Engine::Docker
+        );
+    }
+
+    #[test]
+    fn test_engine() {
+        let p = Engine::Podman { rootless: true };
+        assert_eq!((p.program(), p.default_context()), ("podman", "default"));
+        assert_eq!(
+            (Engine::Docker.program(), Engine::Docker.default_context()),
+            ("docker", "desktop-linux")
+        );
+        assert_eq!(detect_context(p), "default");
+    }
+
+    #[test]
+    fn test_settings() {
+        assert_eq!(remote_host(None, Some("unix:///var/run/docker.sock")), None);
+        assert_eq!(
+            remote_host(None, Some("ssh://me@build")),
+            Some("ssh://me@build".into())
+        );
+        assert_eq!(
+            remote_host(Some("tcp://10.0.0.2:2375"), Some("ssh://me@build")),
+            Some("tcp://10.0.0.2:2375".into())
+        );
+        let settings = |host: Option<&str>| Settings {
+            host: host.map(String::from),
+            context: "default".into(),
+        };
+        assert_eq!(
+            serde_jsonc::to_string(&settings(None)).unwrap(),
+            r#"{"context":"default"}"#
+        );
+        assert_eq!(
+            serde_jsonc::to_string(&settings(Some("ssh://me@build"))).unwrap(),
+            r#"{"host":"ssh://me@build","context":"default"}"#
+        );
+    }
+
+    #[test]
+    fn test_host() {
+        let rootless = Engine::Podman { rootless: true };
+        let run = Some(OsStr::new("/run/user/1000"));
+        assert_eq!(
+            host(rootless, None, None, run),
+            Some("unix:///run/user/1000/podman/podman.sock".into())
+        );
+        assert_eq!(
+            host(rootless, None, Some("unix:///tmp/podman.sock"), run),
+            Some("unix:///tmp/podman.sock".into())
+        );
+        assert_eq!(host(rootless, None, None, None), None);
+        assert_eq!(
+            host(Engine::Podman { rootless: false }, None, None, run),
+            None
+        );
+        assert_eq!(
+            host(
+                Engine::Docker,
+                None,
+                Some("unix:///var/run/docker.sock"),
+                run
+            ),
+            None
+        );
+        assert_eq!(
+            host(Engine::Docker, Some("ssh://me@build"), None, run),
+            Some("ssh://me@build".into())
+        );
+    }
+
+    #[test]
+    fn test_pick_context() {
+        let detect = || "desktop-linux".to_string();
+        assert_eq!(pick_context(None, None, detect), "desktop-linux");
+        assert_eq!(
+            pick_context(None, Some("orbstack".into()), detect),
+            "orbstack"
+        );
+        assert_eq!(
+            pick_context(Some("colima".into()), Some("orbstack".into()), detect),
+            "colima"
+        );
+        assert_eq!(
+            pick_context(Some(String::new()), None, detect),
+            "desktop-linux"
+        );
+    }
+
+    #[test]
+    fn test_is_local_docker() {
+        assert!(is_local_docker(Engine::Docker, "default", None));
+        assert!(!is_local_docker(Engine::Docker, "desktop-linux", None));
+        assert!(!is_local_docker(
+            Engine::Docker,
+            "default",
+            Some("ssh://me@build")
+        ));
+        assert!(!is_local_docker(
+            Engine::Podman { rootless: true },
+            "default",
+            None
+        ));
+    }
+
+    #[test]
+    fn test_parse_context() {
+        assert_eq!(parse_context("colima\n"), Some("colima".into()));
+        assert_eq!(parse_context(""), None);
+    }
+}
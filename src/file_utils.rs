@@ -1,10 +1,11 @@
-use std::collections::BTreeSet;
 use std::env;
-use std::ffi::OsStr;
+use std::ffi::{OsStr, OsString};
 use std::fs;
-use std::iter::{empty, once, Iterator};
+use std::io::{self, Read};
+use std::iter::once;
+use std::os::unix::ffi::OsStringExt;
+use std::os::unix::fs::PermissionsExt;
 use std::path::{Component, Path, PathBuf};
-use std::boxed::Box;
 
 /// Normalize a string into a fully-qualified path that has no . or .. in it.
 pub fn normalize(input: &OsStr) -> PathBuf {
@@ -39,72 +40,145 @@ pub fn has_dir(pth: &Path, dir: &str) -> bool {
     pth.join(dir).is_dir()
 }
 
-pub struct FindOptions<'a> {
-    pub dir: &'a str,
-    pub stop: Option<&'a str>,
+/// Search this directory, and its subdirs down to depth levels below it,
+/// for file_name.  Subdirs named in ignore aren't searched.  Shallower
+/// matches come first, then in name order.
+pub fn files_matching(
+    dir: &Path,
+    file_name: &str,
+    depth: usize,
+    ignore: &[String],
+) -> Vec<PathBuf> {
+    if !dir.is_dir() {
+        return vec![];
+    }
+    let mut res: Vec<PathBuf> = once(dir.join(file_name)).filter(|p| p.is_file()).collect();
+    let mut level = vec![dir.to_path_buf()];
+    for _ in 0..depth {
+        let mut subs: Vec<PathBuf> = level
+            .iter()
+            .flat_map(|d| fs::read_dir(d).into_iter().flatten())
+            .filter_map(|f| {
+                let p = f.expect("Bad path").path();
+                let ignored = p
+                    .file_name()
+                    .is_some_and(|n| ignore.iter().any(|i| n == i.as_str()));
+                (p.is_dir() && !ignored).then_some(p)
+            })
+            .collect();
+        subs.sort();
+        res.extend(
+            subs.iter()
+                .map(|d| d.join(file_name))
+                .filter(|p| p.is_file()),
+        );
+        level = subs;
+    }
+    res
 }
 
-/// Look for the given directory, starting at pth, searching each parent
-/// directory. If desired, return None when stop directory found.
-pub fn find_dir_up(pth: &Path, opts: FindOptions) -> Option<PathBuf> {
-    let mut seen: BTreeSet<String> = BTreeSet::new();
-    let mut root = pth.to_path_buf();
-
-    for _comp in pth.components().rev() {
-        let s = root.display().to_string();
-        // Prevent loops, including the root.
-        if seen.contains(&s) {
-            break;
-        }
-        seen.insert(s);
+/// Find an executable called name in the given PATH.
+pub fn which(name: &str, path: Option<&OsStr>) -> Option<PathBuf> {
+    env::split_paths(path?).map(|dir| dir.join(name)).find(|p| {
+        p.metadata()
+            .is_ok_and(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+    })
+}
 
-        let dc = root.join(opts.dir);
-        if dc.is_dir() {
-            return Some(dc);
-        }
+/// Decode the contents of a text file, which is UTF-8 unless it has a
+/// UTF-16 byte order mark, or looks like UTF-16 without one (every other
+/// byte zero, as ASCII text is).  A UTF-8 byte order mark is dropped.
+pub fn decode(bytes: &[u8]) -> io::Result<String> {
+    let utf16 = |rest: &[u8], from: fn([u8; 2]) -> u16| {
+        let units: Vec<u16> = rest.chunks_exact(2).map(|c| from([c[0], c[1]])).collect();
+        String::from_utf16(&units).map_err(|er| io::Error::new(io::ErrorKind::InvalidData, er))
+    };
+    match bytes {
+        [0xef, 0xbb, 0xbf, rest @ ..] => decode(rest),
+        [0xff, 0xfe, rest @ ..] => utf16(rest, u16::from_le_bytes),
+        [0xfe, 0xff, rest @ ..] => utf16(rest, u16::from_be_bytes),
+        [a, 0, ..] if *a != 0 => utf16(bytes, u16::from_le_bytes),
+        [0, b, ..] if *b != 0 => utf16(bytes, u16::from_be_bytes),
+        _ => String::from_utf8(bytes.to_vec())
+            .map_err(|er| io::Error::new(io::ErrorKind::InvalidData, er)),
+    }
+}
+
+/// Read a text file, in any of the encodings that decode knows.
+pub fn read_text(pth: &Path) -> io::Result<String> {
+    decode(&fs::read(pth)?)
+}
 
-        match opts.stop {
-            // Found .git before .devcontainer, which means we are unlikely to
-            // be in a devcontainer directory.
-            Some(stop) => {
-                if has_dir(&root, stop) {
-                    break;
-                }
+/// Split a list of file names, one per line, or NUL-separated (as from
+/// `git ls-files -z` or `find -print0`) if there are any NULs.  Blank
+/// names are skipped.
+pub fn split_file_list(bytes: &[u8]) -> Vec<OsString> {
+    let sep = if bytes.contains(&0) { 0 } else { b'\n' };
+    bytes
+        .split(|&b| b == sep)
+        .map(|name| {
+            if sep == b'\n' {
+                name.strip_suffix(b"\r").unwrap_or(name)
+            } else {
+                name
             }
-            None => {}
-        }
+        })
+        .filter(|name| !name.is_empty())
+        .map(|name| OsString::from_vec(name.to_vec()))
+        .collect()
+}
 
-        if !root.pop() {
-            break;
+/// Read a list of file names from src, or from stdin if src is `-`.
+pub fn read_file_list(src: &str) -> io::Result<Vec<OsString>> {
+    let bytes = if src == "-" {
+        let mut buf = Vec::new();
+        io::stdin().read_to_end(&mut buf)?;
+        buf
+    } else {
+        fs::read(src)?
+    };
+    Ok(split_file_list(&bytes))
+}
+
+/// The Levenshtein distance between a and b, in chars.
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut cur = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let cost = usize::from(ca != *cb);
+            cur.push((prev[j] + cost).min(prev[j + 1] + 1).min(cur[j] + 1));
         }
+        prev = cur;
     }
-    None
+    prev[b.len()]
 }
 
-/// Search this directory, and all subdirs (but just one level!) for file_name.
-pub fn files_matching<'a>(dir: &'a Path, file_name: &'a str) -> Box<dyn Iterator<Item = PathBuf> + 'a> {
-    if dir.is_dir() {
-        let subs = fs::read_dir(dir)
-            .expect("Error reading directory")
-            .filter_map(|f| {
-                let p = f.expect("Bad path").path();
-                p.is_dir().then_some(p)
-            });
-        let res = once(dir.into())
-            .chain(subs)
-            .filter_map(move |f| {
-                let p = f.join(file_name);
-                p.is_file().then_some(p)
-            });
-        Box::new(res)
-    } else {
-        Box::new(empty::<PathBuf>())
+/// The name of a file next to pth (which doesn't exist) that is probably
+/// what was meant: the closest one that is within a third of the name's
+/// length in edits.  None if nothing is that close, or the directory can't
+/// be read.
+pub fn suggest(pth: &Path) -> Option<String> {
+    let name = pth.file_name()?.to_str()?;
+    let most = name.chars().count() / 3;
+    if most == 0 {
+        return None;
     }
+    fs::read_dir(pth.parent()?)
+        .ok()?
+        .flatten()
+        .filter_map(|e| e.file_name().into_string().ok())
+        .map(|other| (edit_distance(name, &other), other))
+        .filter(|(d, _)| *d <= most)
+        .min()
+        .map(|(_, other)| other)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::discovery::{DevcontainerDir, Strategy, find_root};
 
     #[test]
     fn test_normalize() {
@@ -121,52 +195,102 @@ mod tests {
     }
 
     #[test]
-    fn test_files_matching() {
-        let cur = normalize(OsStr::new(std::file!()));
-        let dc = find_dir_up(
-            &cur,
-            FindOptions {
-                dir: ".devcontainer",
-                stop: None,
-            },
-        )
-        .expect("No devcontainer");
-
-        let res: Vec<PathBuf> = files_matching(&dc, "devcontainer.json").collect();
-        assert_eq!(res.len(), 1);
+    fn test_which() {
+        let path = env::var_os("PATH");
+        assert!(which("sh", path.as_deref()).is_some());
+        assert_eq!(which("___NO_SUCH_COMMAND___", path.as_deref()), None);
+        assert_eq!(which("sh", None), None);
+        // Not executable.
+        let cur = env::current_dir().unwrap();
+        assert_eq!(which("Cargo.toml", Some(cur.as_os_str())), None);
     }
 
     #[test]
-    fn test_not_dir() {
-        let cur = normalize(OsStr::new(std::file!()));
-        let ret: Vec<PathBuf> = files_matching(&cur, "NO_SUCH_FILE").collect();
-        let expected: Vec<PathBuf> = vec![];
-        assert_eq!(ret, expected);
-    }    
+    fn test_decode() {
+        assert_eq!(decode(b"{}").unwrap(), "{}");
+        assert_eq!(decode(b"\xef\xbb\xbf{}").unwrap(), "{}");
+        assert_eq!(decode(b"\xff\xfe{\0}\0").unwrap(), "{}");
+        assert_eq!(decode(b"\xfe\xff\0{\0}").unwrap(), "{}");
+        assert_eq!(decode(b"{\0}\0").unwrap(), "{}");
+        assert_eq!(decode(b"\0{\0}").unwrap(), "{}");
+        assert_eq!(decode(b"").unwrap(), "");
+        assert!(decode(b"\xff{}").is_err());
+    }
+
+    #[test]
+    fn test_split_file_list() {
+        assert_eq!(
+            split_file_list(b"a.rs\r\nsrc/b c.rs\n\n"),
+            ["a.rs", "src/b c.rs"]
+        );
+        assert_eq!(
+            split_file_list(b"a.rs\0new\nline.rs\0"),
+            ["a.rs", "new\nline.rs"]
+        );
+        assert!(split_file_list(b"").is_empty());
+        assert_eq!(split_file_list(b"\xff\0")[0].as_encoded_bytes(), b"\xff");
+    }
+
+    #[test]
+    fn test_suggest() {
+        assert_eq!(edit_distance("main.rs", "main.rs"), 0);
+        assert_eq!(edit_distance("mian.rs", "main.rs"), 2);
+        assert_eq!(edit_distance("", "abc"), 3);
+        assert_eq!(edit_distance("Cargo.tml", "Cargo.toml"), 1);
+        let cur = env::current_dir().unwrap();
+        assert_eq!(suggest(&cur.join("src/mian.rs")), Some("main.rs".into()));
+        assert_eq!(suggest(&cur.join("Cargo.tml")), Some("Cargo.toml".into()));
+        assert_eq!(suggest(&cur.join("src/___NO_SUCH_FILE___")), None);
+        assert_eq!(suggest(&cur.join("___NO_SUCH_DIR___/main.rs")), None);
+    }
 
     #[test]
     fn test_no_container() {
         let cur = normalize(OsStr::new(std::file!()));
-        let dc = find_dir_up(
-            &cur,
-            FindOptions {
-                dir: "___BAD_DIR_DOESNT_EXIT_____HOPEFULLY...",
-                stop: None,
-            },
-        );
+        let strategies: Vec<Box<dyn Strategy>> = vec![Box::new(DevcontainerDir::new(
+            "___BAD_DIR_DOESNT_EXIT_____HOPEFULLY...",
+        ))];
+        let dc = find_root(&cur, &strategies, None);
         assert_eq!(dc, None);
     }
 
     #[test]
     fn test_stop_dir() {
         let cur = normalize(OsStr::new(std::file!()));
-        let dc = find_dir_up(
-            &cur,
-            FindOptions {
-                dir: "___BAD_DIR_DOESNT_EXIT_____HOPEFULLY...",
-                stop: Some(".git"),
-            },
-        );
+        let strategies: Vec<Box<dyn Strategy>> = vec![Box::new(DevcontainerDir::new(
+            "___BAD_DIR_DOESNT_EXIT_____HOPEFULLY...",
+        ))];
+        let dc = find_root(&cur, &strategies, Some(".git"));
         assert_eq!(dc, None);
-    }    
+    }
+
+    #[test]
+    fn test_files_matching() {
+        let dc = env::current_dir().unwrap().join(".devcontainer");
+        let res = files_matching(&dc, "devcontainer.json", 1, &[]);
+        assert_eq!(res.len(), 1);
+
+        // main.rs is one level down, in src.
+        let cur = env::current_dir().unwrap();
+        assert_eq!(
+            files_matching(&cur, "main.rs", 0, &[]),
+            Vec::<PathBuf>::new()
+        );
+        assert_eq!(
+            files_matching(&cur, "main.rs", 1, &[]),
+            vec![cur.join("src/main.rs")]
+        );
+        assert_eq!(
+            files_matching(&cur, "main.rs", 1, &["src".into()]),
+            Vec::<PathBuf>::new()
+        );
+    }
+
+    #[test]
+    fn test_not_dir() {
+        let cur = normalize(OsStr::new(std::file!()));
+        let ret = files_matching(&cur, "NO_SUCH_FILE", 1, &[]);
+        let expected: Vec<PathBuf> = vec![];
+        assert_eq!(ret, expected);
+    }
 }
@@ -0,0 +1,354 @@
+use phf::{phf_map, phf_set};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use crate::code_subcommands::{self, Value};
+use crate::config;
+use crate::i18n::t;
+use crate::toml;
+
+/// How many parameters each of code's flags takes, as of when cope was
+/// built.  `cope update-flags` can learn newer ones.
+pub static PARAM_SIZE: phf::Map<&'static str, usize> = phf_map! {
+    "--add-mcp" => 1,
+    "--add" => 1,
+    "--category" => 1,
+    "--diff" => 2,
+    "--disable-extension" => 1,
+    "--enable-proposed-api" => 1,
+    "--extensions-dir" => 1,
+    "--inspect-brk-extensions" => 1,
+    "--inspect-extensions" => 1,
+    "--install-extension" => 1,
+    "--locale" => 1,
+    "--locate-shell-integration-path" => 1,
+    "--log" => 1,
+    "--merge" => 4,
+    "--profile" => 1,
+    "--remote" => 1,
+    "--remove" => 1,
+    "--sync" => 1,
+    "--uninstall-extension" => 1,
+    "--user-data-dir" => 1,
+    "-a" => 1,
+    "-d" => 2,
+    "-m" => 4,
+};
+
+/// All params after one of these are not file names, as far as I can tell.
+static TERMINAL_PARAM: phf::Set<&'static str> = phf_set!["--",];
+
+/// Flags whose parameters cope looks at itself, rather than by count.
+const HANDLED: &[&str] = &["--goto", "-g"];
+
+/// What `cope update-flags` learned from `code --help`.
+#[derive(Deserialize, Serialize, Debug, Default, Clone, PartialEq)]
+pub struct Flags {
+    /// How many parameters each flag takes.
+    #[serde(default)]
+    pub params: BTreeMap<String, usize>,
+
+    /// Each subcommand, with its flags that take a value.
+    #[serde(default)]
+    pub subcommands: BTreeMap<String, BTreeMap<String, Value>>,
+}
+
+/// Where the learned flags are kept.
+pub fn file() -> PathBuf {
+    config::cache_dir().join("flags.json")
+}
+
+/// The user's own additions to the flag tables, from flags.toml in the
+/// config directory, for flags of a newer code (or a fork of it) that cope
+/// doesn't know yet.  These win over everything else.
+#[derive(Deserialize, Debug, Default, Clone, PartialEq)]
+pub struct UserFlags {
+    /// Subcommands after which nothing is a file name.
+    #[serde(default)]
+    pub terminal: Vec<String>,
+
+    /// How many parameters each flag takes; 0 for none.
+    #[serde(default)]
+    pub params: BTreeMap<String, usize>,
+}
+
+/// Where the user's flags are kept.
+pub fn user_file() -> PathBuf {
+    config::config_dir().join("flags.toml")
+}
+
+/// Read the user's flags.  A missing file means there are none; one that
+/// can't be parsed is an error.
+pub fn read_user(file: &Path) -> Result<UserFlags, String> {
+    let Ok(text) = fs::read_to_string(file) else {
+        return Ok(UserFlags::default());
+    };
+    toml::to_json(&text)
+        .and_then(|v| serde_jsonc::from_value(v).map_err(|er| er.to_string()))
+        .map_err(|er| format!("Error parsing flags {file:?} {er}"))
+}
+
+/// The user's flags for this run.  A file that can't be parsed is reported,
+/// and treated as empty, rather than stopping every run.
+fn user() -> &'static UserFlags {
+    static FLAGS: OnceLock<UserFlags> = OnceLock::new();
+    FLAGS.get_or_init(|| {
+        read_user(&user_file()).unwrap_or_else(|er| {
+            eprintln!("{}", t!("bad-flags", error = er));
+            UserFlags::default()
+        })
+    })
+}
+
+/// Read the learned flags.  A missing or broken file means there are none.
+pub fn read(file: &Path) -> Flags {
+    fs::read_to_string(file)
+        .ok()
+        .and_then(|s| serde_jsonc::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+/// The learned flags for this run.
+fn learned() -> &'static Flags {
+    static FLAGS: OnceLock<Flags> = OnceLock::new();
+    FLAGS.get_or_init(|| read(&file()))
+}
+
+/// How many parameters flag takes, if it takes any.
+pub fn param_size(flag: &str) -> Option<usize> {
+    user()
+        .params
+        .get(flag)
+        .or_else(|| learned().params.get(flag))
+        .copied()
+        .or_else(|| PARAM_SIZE.get(flag).copied())
+        .filter(|n| *n > 0)
+}
+
+/// Has the user said, in flags.toml, that nothing after arg is a file name?
+/// That is the one place to add to the terminal subcommands.
+fn user_terminal(arg: &str) -> bool {
+    user().terminal.iter().any(|t| t == arg)
+}
+
+/// Is nothing after arg a file name?
+pub fn is_terminal(arg: &str) -> bool {
+    TERMINAL_PARAM.contains(arg) || user_terminal(arg)
+}
+
+/// The flags that take a value for code's subcommand name, if it is one:
+/// the learned ones first, then the ones cope knows.  Subcommands that the
+/// user has made terminal aren't looked at.
+pub fn subcommand(name: &str) -> Option<Vec<(&'static str, Value)>> {
+    if user_terminal(name) {
+        return None;
+    }
+    let learned = learned().subcommands.get(name);
+    let known = code_subcommands::flags(name);
+    if learned.is_none() && known.is_none() {
+        return None;
+    }
+    let mut res: Vec<(&'static str, Value)> = learned
+        .into_iter()
+        .flatten()
+        .map(|(f, v)| (f.as_str(), *v))
+        .collect();
+    res.extend(known.into_iter().flatten().copied());
+    Some(res)
+}
+
+/// The flag names and parameter placeholders at the start of a line of
+/// help, such as `-d --diff <file> <file>` or `-h, --help`.
+fn signature(line: &str) -> (Vec<&str>, Vec<&str>) {
+    let mut names = Vec::new();
+    let mut placeholders = Vec::new();
+    let mut rest = line.trim_start();
+    while rest.starts_with('-') {
+        let end = rest.find([' ', ',', '=']).unwrap_or(rest.len());
+        names.push(&rest[..end]);
+        rest = rest[end..].trim_start_matches([',', '=']).trim_start();
+    }
+    loop {
+        let close = match rest.chars().next() {
+            Some('<') => '>',
+            Some('[') => ']',
+            _ => break,
+        };
+        let Some(end) = rest.find(close) else {
+            break;
+        };
+        if rest[..end].contains('<') || close == '>' {
+            placeholders.push(&rest[1..end]);
+        }
+        rest = rest[end + 1..].trim_start();
+    }
+    (names, placeholders)
+}
+
+/// The flags that take parameters, and how many, from `code --help`, and
+/// the names of the subcommands it lists.
+pub fn parse_help(help: &str) -> (BTreeMap<String, usize>, Vec<String>) {
+    let mut params = BTreeMap::new();
+    let mut subcommands = Vec::new();
+    let mut in_subcommands = false;
+    for line in help.lines() {
+        if !line.starts_with(' ') {
+            in_subcommands = line.trim_end().trim_end_matches(':').ends_with("ommands");
+            continue;
+        }
+        let trimmed = line.trim_start();
+        if trimmed.starts_with('-') {
+            let (names, placeholders) = signature(trimmed);
+            for name in names {
+                if !placeholders.is_empty() && !HANDLED.contains(&name) {
+                    params.insert(name.to_string(), placeholders.len());
+                }
+            }
+        } else if in_subcommands && let Some(name) = trimmed.split_whitespace().next() {
+            subcommands.push(name.to_string());
+        }
+    }
+    (params, subcommands)
+}
+
+/// The flags that take a value, from a subcommand's --help.  Values whose
+/// placeholder names a file, path or directory are paths.
+pub fn parse_subcommand_help(help: &str) -> BTreeMap<String, Value> {
+    let mut res = BTreeMap::new();
+    for line in help.lines().filter(|l| l.starts_with(' ')) {
+        let (names, placeholders) = signature(line);
+        let Some(placeholder) = placeholders.first() else {
+            continue;
+        };
+        let upper = placeholder.to_ascii_uppercase();
+        let kind = if ["FILE", "PATH", "DIR"].iter().any(|w| upper.contains(w)) {
+            Value::Path
+        } else {
+            Value::Plain
+        };
+        for name in names {
+            res.insert(name.to_string(), kind);
+        }
+    }
+    res
+}
+
+/// Learn the flags from the output of `code --help`, and of `code <name>
+/// --help` for each subcommand, from sub_help.
+pub fn learn(help: &str, sub_help: impl Fn(&str) -> Option<String>) -> Flags {
+    let (params, names) = parse_help(help);
+    let subcommands = names
+        .into_iter()
+        .map(|name| {
+            let flags = sub_help(&name)
+                .map(|h| parse_subcommand_help(&h))
+                .unwrap_or_default();
+            (name, flags)
+        })
+        .collect();
+    Flags {
+        params,
+        subcommands,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    const HELP: &str = "Visual Studio Code 1.105.0
+
+Usage: code [options][paths...]
+
+To read from stdin, append '-' (e.g. 'ps aux | grep code | code -')
+
+Options
+  -d --diff <file> <file>                    Compare two files with each other.
+  -m --merge <path1> <path2> <base> <result> Perform a three-way merge.
+  -a --add <folder>                          Add folder(s) to the last active window.
+  -g --goto <file:line[:character]>          Open a file at the path on the specified line.
+  -n --new-window                            Force to open a new window.
+  --install-extension <ext-id | path>        Installs or updates an extension.
+  --enable-proposed-api [<extension-id>]     Enables proposed API features.
+
+Subcommands
+  chat      Pass in a prompt to run in a chat session.
+  tunnel    Make the current machine accessible from vscode.dev.
+";
+
+    const TUNNEL_HELP: &str = "Usage: code tunnel [OPTIONS] [COMMAND]
+
+Options:
+      --install-extension <EXT_ID>
+          Requests that extensions be preloaded and installed on connecting servers
+      --server-data-dir <SERVER_DATA_DIR>
+          Specifies the directory that server data is kept in
+      --name <NAME>
+  -h, --help
+          Print help
+";
+
+    #[test]
+    fn test_parse_help() {
+        let (params, subcommands) = parse_help(HELP);
+        assert_eq!(params["--merge"], 4);
+        assert_eq!(params["-d"], 2);
+        assert_eq!(params["--install-extension"], 1);
+        assert_eq!(params["--enable-proposed-api"], 1);
+        assert!(!params.contains_key("--new-window"));
+        assert!(!params.contains_key("--goto"));
+        assert_eq!(subcommands, ["chat", "tunnel"]);
+    }
+
+    #[test]
+    fn test_parse_subcommand_help() {
+        let flags = parse_subcommand_help(TUNNEL_HELP);
+        assert_eq!(flags["--server-data-dir"], Value::Path);
+        assert_eq!(flags["--name"], Value::Plain);
+        assert!(!flags.contains_key("--help"));
+        let flags = learn(HELP, |name| {
+            (name == "tunnel").then(|| TUNNEL_HELP.to_string())
+        });
+        assert_eq!(
+            flags.subcommands["tunnel"]["--install-extension"],
+            Value::Plain
+        );
+        assert!(flags.subcommands["chat"].is_empty());
+    }
+
+    #[test]
+    fn test_read_user() {
+        let dir = env::temp_dir().join(format!("cope-flags-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("flags.toml");
+        fs::write(
+            &file,
+            "terminal = [\"agent\"]\n\n[params]\n--new-flag = 2\n--log = 0\n",
+        )
+        .unwrap();
+        let user = read_user(&file).unwrap();
+        assert_eq!(user.terminal, ["agent"]);
+        assert_eq!(user.params["--new-flag"], 2);
+        assert_eq!(user.params["--log"], 0);
+        fs::write(&file, "[params]\n--new-flag = \"two\"\n").unwrap();
+        assert!(
+            read_user(&file)
+                .unwrap_err()
+                .starts_with("Error parsing flags")
+        );
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(read_user(&file), Ok(UserFlags::default()));
+    }
+
+    #[test]
+    fn test_read() {
+        assert_eq!(read(Path::new("___NO_SUCH_FILE___")), Flags::default());
+        let flags: Flags =
+            serde_jsonc::from_str(r#"{"subcommands": {"agent": {"--dir": "path"}}}"#).unwrap();
+        assert_eq!(flags.subcommands["agent"]["--dir"], Value::Path);
+    }
+}
@@ -0,0 +1,247 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::command_utils::capture;
+use crate::discovery::{Strategy, has_configs};
+use crate::i18n::t;
+
+const HOUR: u64 = 60 * 60;
+const DAY: u64 = 24 * HOUR;
+const WEEK: u64 = 7 * DAY;
+
+/// Once the total rank of all entries passes this, everything is aged, and
+/// entries that drop too low are forgotten.  Same approach as zoxide.
+const MAX_RANK: f64 = 10000.0;
+
+/// One previously-opened repo root.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct Entry {
+    pub path: PathBuf,
+    pub rank: f64,
+    pub last: u64,
+}
+
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("Time before epoch")
+        .as_secs()
+}
+
+impl Entry {
+    /// Frequency weighted by how recently the entry was used.
+    pub fn score(&self, now: u64) -> f64 {
+        let age = now.saturating_sub(self.last);
+        let factor = if age < HOUR {
+            4.0
+        } else if age < DAY {
+            2.0
+        } else if age < WEEK {
+            0.5
+        } else {
+            0.25
+        };
+        self.rank * factor
+    }
+}
+
+/// Read the database.  Missing or corrupt databases are empty.
+pub fn read_db(db_file: &Path) -> Vec<Entry> {
+    fs::read_to_string(db_file)
+        .ok()
+        .and_then(|json_data| serde_jsonc::from_str(&json_data).ok())
+        .unwrap_or_default()
+}
+
+/// Write the database, ignoring errors, since losing history is harmless.
+pub fn write_db(db_file: &Path, entries: &[Entry]) {
+    if let Some(parent) = db_file.parent() {
+        let _ = fs::create_dir_all(parent);
+    }
+    if let Ok(json_data) = serde_jsonc::to_string_pretty(entries) {
+        let _ = fs::write(db_file, json_data);
+    }
+}
+
+/// Bump the rank of each of the given paths, adding them if needed.
+pub fn add(entries: &mut Vec<Entry>, paths: &[PathBuf], rank: f64, now: u64) {
+    for p in paths {
+        match entries.iter_mut().find(|e| &e.path == p) {
+            Some(e) => {
+                e.rank += rank;
+                e.last = now;
+            }
+            None => entries.push(Entry {
+                path: p.clone(),
+                rank,
+                last: now,
+            }),
+        }
+    }
+
+    let total: f64 = entries.iter().map(|e| e.rank).sum();
+    if total > MAX_RANK {
+        entries.iter_mut().for_each(|e| e.rank *= 0.9);
+        entries.retain(|e| e.rank >= 1.0);
+    }
+}
+
+/// Record that the given repo roots were opened.
+pub fn record(db_file: &Path, paths: &[PathBuf]) {
+    if paths.is_empty() {
+        return;
+    }
+    let mut entries = read_db(db_file);
+    add(&mut entries, paths, 1.0, now());
+    write_db(db_file, &entries);
+}
+
+/// Does the path match all of the keywords?  Like zoxide, the keywords must
+/// appear in order (case-insensitively), and the last one must be in the
+/// last path component.
+pub fn matches(path: &Path, keywords: &[String]) -> bool {
+    let full = path.to_string_lossy().to_lowercase();
+    let mut rest = full.as_str();
+    for k in keywords {
+        let k = k.to_lowercase();
+        match rest.find(&k) {
+            Some(i) => rest = &rest[i + k.len()..],
+            None => return false,
+        }
+    }
+    match (keywords.last(), path.file_name()) {
+        (Some(k), Some(last)) => last
+            .to_string_lossy()
+            .to_lowercase()
+            .contains(&k.to_lowercase()),
+        (Some(_), None) => false,
+        (None, _) => true,
+    }
+}
+
+/// The highest-scoring entry that matches the keywords, skipping any that no
+/// longer exist.
+pub fn best<'a>(entries: &'a [Entry], keywords: &[String], now: u64) -> Option<&'a Entry> {
+    entries
+        .iter()
+        .filter(|e| matches(&e.path, keywords) && e.path.is_dir())
+        .max_by(|a, b| a.score(now).total_cmp(&b.score(now)))
+}
+
+/// Parse the output of `zoxide query --list --score`, which looks like
+/// `  12.5 /some/path` on each line.
+pub fn parse_zoxide(output: &str) -> Vec<(f64, PathBuf)> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let (score, path) = line.trim_start().split_once(' ')?;
+            Some((score.parse().ok()?, PathBuf::from(path.trim_start())))
+        })
+        .collect()
+}
+
+/// Add zoxide's directories to the database, as long as they have a
+/// devcontainer config.  Returns how many were imported.
+pub fn import_zoxide(db_file: &Path, strategies: &[Box<dyn Strategy>]) -> usize {
+    let Some(output) = capture(Command::new("zoxide").args(["query", "--list", "--score"])) else {
+        eprintln!("{}", t!("zoxide-failed"));
+        return 0;
+    };
+
+    let mut entries = read_db(db_file);
+    let t = now();
+    let found: Vec<(f64, PathBuf)> = parse_zoxide(&output)
+        .into_iter()
+        .filter(|(_, p)| has_configs(p, strategies))
+        .collect();
+    for (score, p) in &found {
+        add(&mut entries, std::slice::from_ref(p), *score, t);
+    }
+    write_db(db_file, &entries);
+    found.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    fn kw(words: &[&str]) -> Vec<String> {
+        words.iter().map(|&s| s.into()).collect()
+    }
+
+    #[test]
+    fn test_score() {
+        let e = Entry {
+            path: "/a".into(),
+            rank: 2.0,
+            last: 1000,
+        };
+        assert_eq!(e.score(1000), 8.0);
+        assert_eq!(e.score(1000 + HOUR), 4.0);
+        assert_eq!(e.score(1000 + DAY), 1.0);
+        assert_eq!(e.score(1000 + WEEK), 0.5);
+    }
+
+    #[test]
+    fn test_add() {
+        let mut entries: Vec<Entry> = vec![];
+        add(&mut entries, &["/a".into()], 1.0, 10);
+        add(&mut entries, &["/a".into(), "/b".into()], 1.0, 20);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].rank, 2.0);
+        assert_eq!(entries[0].last, 20);
+
+        add(&mut entries, &["/c".into()], MAX_RANK, 30);
+        // /b aged out.
+        let paths: Vec<&Path> = entries.iter().map(|e| e.path.as_path()).collect();
+        assert_eq!(paths, vec![Path::new("/a"), Path::new("/c")]);
+    }
+
+    #[test]
+    fn test_matches() {
+        let p = Path::new("/home/me/src/Cope");
+        assert!(matches(p, &kw(&["cope"])));
+        assert!(matches(p, &kw(&["src", "co"])));
+        assert!(!matches(p, &kw(&["co", "src"])));
+        assert!(!matches(p, &kw(&["me"])));
+        assert!(matches(p, &kw(&[])));
+    }
+
+    #[test]
+    fn test_best() {
+        let cur = env::current_dir().unwrap();
+        let entries = vec![
+            Entry {
+                path: cur.join("src"),
+                rank: 1.0,
+                last: 0,
+            },
+            Entry {
+                path: cur.join("___NO_SUCH_DIR___"),
+                rank: 100.0,
+                last: 0,
+            },
+            Entry {
+                path: cur.clone(),
+                rank: 5.0,
+                last: 0,
+            },
+        ];
+        assert_eq!(best(&entries, &kw(&[]), 0).unwrap().path, cur);
+        assert_eq!(
+            best(&entries, &kw(&["src"]), 0).unwrap().path,
+            cur.join("src")
+        );
+        assert_eq!(best(&entries, &kw(&["___"]), 0), None);
+    }
+
+    #[test]
+    fn test_parse_zoxide() {
+        let res = parse_zoxide("  12.5 /a b\n 1 /c\nbogus\n");
+        assert_eq!(res, vec![(12.5, "/a b".into()), (1.0, "/c".into())]);
+    }
+}
@@ -0,0 +1,268 @@
+use std::ffi::OsString;
+use std::fs;
+use std::iter::once;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::command_utils::capture;
+use crate::flags;
+
+/// Does arg have any of the glob characters in it?
+pub fn is_pattern(arg: &str) -> bool {
+    arg.contains(['*', '?', '['])
+}
+
+/// Match one path component against one pattern component, with `*`, `?`
+/// and `[...]` classes (`[!...]` or `[^...]` to negate).
+fn match_component(pat: &[char], name: &[char]) -> bool {
+    match pat.first() {
+        None => name.is_empty(),
+        Some('*') => (0..=name.len()).any(|i| match_component(&pat[1..], &name[i..])),
+        Some('?') => !name.is_empty() && match_component(&pat[1..], &name[1..]),
+        Some('[') => {
+            let Some(&c) = name.first() else {
+                return false;
+            };
+            let negate = matches!(pat.get(1), Some('!' | '^'));
+            let start = if negate { 2 } else { 1 };
+            // A ] right after the [ is part of the class.
+            let Some(end) = pat
+                .iter()
+                .skip(start + 1)
+                .position(|&p| p == ']')
+                .map(|e| e + start + 1)
+            else {
+                // No closing ], so the [ is just a character.
+                return c == '[' && match_component(&pat[1..], &name[1..]);
+            };
+            let class = &pat[start..end];
+            let mut found = false;
+            let mut i = 0;
+            while i < class.len() {
+                if i + 2 < class.len() && class[i + 1] == '-' {
+                    found |= class[i] <= c && c <= class[i + 2];
+                    i += 3;
+                } else {
+                    found |= class[i] == c;
+                    i += 1;
+                }
+            }
+            found != negate && match_component(&pat[end + 1..], &name[1..])
+        }
+        Some(&p) => name.first() == Some(&p) && match_component(&pat[1..], &name[1..]),
+    }
+}
+
+/// Match the components of a relative path against those of a pattern,
+/// where `**` matches any number of directories.  As in the shell, names
+/// starting with `.` are only matched by a pattern that starts with `.`.
+fn match_components(pat: &[&str], path: &[&str]) -> bool {
+    match (pat.first(), path.first()) {
+        (None, _) => path.is_empty(),
+        (Some(&"**"), _) => {
+            match_components(&pat[1..], path)
+                || path.first().is_some_and(|p| !p.starts_with('.'))
+                    && match_components(pat, &path[1..])
+        }
+        (Some(_), None) => false,
+        (Some(p), Some(name)) => {
+            (!name.starts_with('.') || p.starts_with('.'))
+                && match_component(
+                    &p.chars().collect::<Vec<_>>(),
+                    &name.chars().collect::<Vec<_>>(),
+                )
+                && match_components(&pat[1..], &path[1..])
+        }
+    }
+}
+
+/// Does the relative path rel match the pattern?
+pub fn matches(pattern: &str, rel: &str) -> bool {
+    let pat: Vec<&str> = pattern.split('/').filter(|c| !c.is_empty()).collect();
+    let path: Vec<&str> = rel.split('/').filter(|c| !c.is_empty()).collect();
+    match_components(&pat, &path)
+}
+
+/// Every file and directory under base, relative to it, down to depth
+/// components (or all the way, for None).  In a git repo, that's what git
+/// knows about or would pick up, leaving out anything .gitignore'd;
+/// elsewhere, everything but .git directories.
+fn candidates(base: &Path, depth: Option<usize>) -> Vec<String> {
+    let shallow = |f: &str| depth.is_none_or(|d| f.split('/').count() <= d);
+    let listed = capture(Command::new("git").arg("-C").arg(base).args([
+        "ls-files",
+        "-z",
+        "--cached",
+        "--others",
+        "--exclude-standard",
+    ]));
+    let mut res: Vec<String> = match listed {
+        Some(out) => out
+            .split('\0')
+            .filter(|f| !f.is_empty())
+            .flat_map(|f| {
+                // Directories can match too, such as with `src/*`.
+                let dirs = f.match_indices('/').map(|(i, _)| f[..i].to_string());
+                dirs.chain(once(f.to_string())).collect::<Vec<_>>()
+            })
+            .filter(|f| shallow(f) && base.join(f).exists())
+            .collect(),
+        None => {
+            let mut files = Vec::new();
+            walk(base, "", depth, &mut files);
+            files
+        }
+    };
+    res.sort();
+    res.dedup();
+    res
+}
+
+/// Add the files and directories under dir, whose path relative to the
+/// base is rel, going at most depth more levels down.
+fn walk(dir: &Path, rel: &str, depth: Option<usize>, files: &mut Vec<String>) {
+    if depth == Some(0) {
+        return;
+    }
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if name == ".git" {
+            continue;
+        }
+        let entry_rel = if rel.is_empty() {
+            name
+        } else {
+            format!("{rel}/{name}")
+        };
+        if entry.file_type().is_ok_and(|t| t.is_dir()) {
+            walk(&entry.path(), &entry_rel, depth.map(|d| d - 1), files);
+        }
+        files.push(entry_rel);
+    }
+}
+
+/// The paths matching pattern, relative to cwd if it is, sorted.  The
+/// components before the first one with a glob character are looked up
+/// as they are.
+pub fn expand(pattern: &str, cwd: &Path) -> Vec<String> {
+    let components: Vec<&str> = pattern.split('/').collect();
+    let literal = components.iter().take_while(|c| !is_pattern(c)).count();
+    let prefix = components[..literal].join("/");
+    let rest = components[literal..].join("/");
+    let base: PathBuf = if prefix.is_empty() && !pattern.starts_with('/') {
+        cwd.into()
+    } else if prefix.is_empty() {
+        "/".into()
+    } else {
+        cwd.join(&prefix)
+    };
+    // Only `**` can match more components than the pattern has.
+    let depth = (!rest.split('/').any(|c| c == "**"))
+        .then(|| rest.split('/').filter(|c| !c.is_empty()).count());
+    candidates(&base, depth)
+        .into_iter()
+        .filter(|rel| matches(&rest, rel))
+        .map(|rel| {
+            if prefix.is_empty() && !pattern.starts_with('/') {
+                rel
+            } else {
+                format!("{prefix}/{rel}")
+            }
+        })
+        .collect()
+}
+
+/// Expand the glob patterns in args, for shells that don't, or patterns
+/// that were quoted.  Flags, anything after `--` or one of code's
+/// subcommands, and patterns that match nothing (or that name a file that
+/// exists) are left alone.
+pub fn expand_args(args: Vec<OsString>, cwd: &Path) -> Vec<OsString> {
+    let mut res = Vec::with_capacity(args.len());
+    let mut it = args.into_iter();
+    while let Some(a) = it.next() {
+        match a.to_str() {
+            Some(s) if flags::is_terminal(s) || flags::subcommand(s).is_some() => {
+                // As in process_args, nothing after these is a file name,
+                // such as the words of a chat prompt.
+                res.push(a);
+                res.extend(it.by_ref());
+            }
+            Some(s) if !s.starts_with('-') && is_pattern(s) && !cwd.join(s).exists() => {
+                let found = expand(s, cwd);
+                if found.is_empty() {
+                    res.push(a);
+                } else {
+                    res.extend(found.into_iter().map(OsString::from));
+                }
+            }
+            _ => res.push(a),
+        }
+    }
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    #[test]
+    fn test_matches() {
+        assert!(matches("*.rs", "main.rs"));
+        assert!(!matches("*.rs", "src/main.rs"));
+        assert!(matches("**/*.rs", "main.rs"));
+        assert!(matches("**/*.rs", "src/a/b/main.rs"));
+        assert!(matches("src/**", "src/a/b"));
+        assert!(matches("ma?n.[a-s]s", "main.rs"));
+        assert!(!matches("main.[!r]s", "main.rs"));
+        assert!(!matches("*", ".git"));
+        assert!(matches(".*", ".gitignore"));
+        assert!(!matches("**/*.rs", ".cargo/x.rs"));
+        assert!(matches("a[", "a["));
+    }
+
+    #[test]
+    fn test_expand() {
+        let cwd = env::current_dir().unwrap();
+        let found = expand("src/*_utils.rs", &cwd);
+        assert!(
+            found.contains(&"src/file_utils.rs".to_string()),
+            "{found:?}"
+        );
+        assert!(
+            found.contains(&"src/string_utils.rs".to_string()),
+            "{found:?}"
+        );
+        assert!(found.iter().all(|f| f.ends_with("_utils.rs")));
+        assert!(
+            !expand("**/*.rs", &cwd)
+                .iter()
+                .any(|f| f.starts_with("target/"))
+        );
+        let args: Vec<OsString> = ["cope", "-n", "___NO_SUCH___*", "Cargo.t?ml", "--", "*"]
+            .iter()
+            .map(OsString::from)
+            .collect();
+        assert_eq!(
+            expand_args(args, &cwd),
+            ["cope", "-n", "___NO_SUCH___*", "Cargo.toml", "--", "*"]
+        );
+        let args: Vec<OsString> = ["cope", "chat", "why is src/*.rs slow?", "src/*.rs"]
+            .iter()
+            .map(OsString::from)
+            .collect();
+        assert_eq!(expand_args(args.clone(), &cwd), args);
+
+        let tmp = env::temp_dir().join(format!("cope-glob-depth-{}", std::process::id()));
+        fs::create_dir_all(tmp.join("a/b")).unwrap();
+        fs::write(tmp.join("x.txt"), "").unwrap();
+        fs::write(tmp.join("a/b/y.txt"), "").unwrap();
+        assert_eq!(candidates(&tmp, Some(1)), ["a", "x.txt"]);
+        assert_eq!(candidates(&tmp, None), ["a", "a/b", "a/b/y.txt", "x.txt"]);
+        assert_eq!(expand("**/*.txt", &tmp), ["a/b/y.txt", "x.txt"]);
+        fs::remove_dir_all(&tmp).unwrap();
+    }
+}
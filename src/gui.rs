@@ -0,0 +1,145 @@
+use nix::unistd::dup2_stderr;
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::io::IsTerminal;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+use crate::config;
+
+/// Was cope started by a file manager's "Open With", rather than from a
+/// terminal?  Then there is nobody to answer a prompt on stderr, or read
+/// what is written there.  Without a desktop to show a dialog on (such as
+/// in a cron job, or over ssh with no terminal), it wasn't.
+pub fn active() -> bool {
+    !std::io::stdin().is_terminal()
+        && !std::io::stderr().is_terminal()
+        && desktop(cfg!(target_os = "macos"), |name| env::var(name).ok())
+}
+
+/// Is there a desktop session?  On Linux, that needs an X or Wayland
+/// display, or a desktop environment.  A Mac has one unless this is an ssh
+/// session.
+pub fn desktop(macos: bool, var: impl Fn(&str) -> Option<String>) -> bool {
+    let set = |name: &str| var(name).is_some_and(|v| !v.is_empty());
+    if macos {
+        !set("SSH_CONNECTION")
+    } else {
+        ["DISPLAY", "WAYLAND_DISPLAY", "XDG_CURRENT_DESKTOP"]
+            .iter()
+            .any(|v| set(v))
+    }
+}
+
+/// Where diagnostics go when there is no terminal.
+pub fn log_file() -> PathBuf {
+    config::cache_dir().join("cope.log")
+}
+
+/// Send everything written to stderr from now on (including by code,
+/// after the exec) to the log file.  If that fails, stderr is left alone.
+pub fn redirect_stderr() {
+    let file_name = log_file();
+    if let Some(parent) = file_name.parent() {
+        let _ = fs::create_dir_all(parent);
+    }
+    if let Ok(f) = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&file_name)
+    {
+        let _ = dup2_stderr(&f);
+    }
+}
+
+/// The command that shows a graphical list of items, and prints the one
+/// picked.
+pub fn chooser_command(macos: bool, prompt: &str, items: &[String]) -> Vec<String> {
+    if macos {
+        let list: Vec<String> = items.iter().map(|i| format!("{i:?}")).collect();
+        vec![
+            "osascript".into(),
+            "-e".into(),
+            format!(
+                "choose from list {{{}}} with prompt {prompt:?}",
+                list.join(", ")
+            ),
+        ]
+    } else {
+        let mut res: Vec<String> = vec![
+            "zenity".into(),
+            "--list".into(),
+            format!("--title={prompt}"),
+            format!("--column={prompt}"),
+            "--hide-header".into(),
+        ];
+        res.extend(items.iter().cloned());
+        res
+    }
+}
+
+/// Which item was picked, from the chooser's output.  osascript prints
+/// "false" when cancelled; zenity prints nothing.
+pub fn parse_choice(output: &str, items: &[String]) -> Option<usize> {
+    let picked = output.trim_end_matches('\n');
+    items.iter().position(|i| i == picked)
+}
+
+/// Ask with a dialog which item is desired.  None if cancelled, or no
+/// dialog could be shown.
+pub fn select(prompt: &str, items: &[String]) -> Option<usize> {
+    let args = chooser_command(cfg!(target_os = "macos"), prompt, items);
+    // Not `capture`: a person is answering, so there is no timeout.
+    let output = Command::new(&args[0])
+        .args(&args[1..])
+        .stdin(Stdio::null())
+        .output()
+        .ok()?;
+    parse_choice(&String::from_utf8_lossy(&output.stdout), items)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chooser_command() {
+        let items: Vec<String> = vec!["Go".into(), "Rust \"nightly\"".into()];
+        assert_eq!(
+            chooser_command(true, "Which?", &items)[2],
+            r#"choose from list {"Go", "Rust \"nightly\""} with prompt "Which?""#
+        );
+        let z = chooser_command(false, "Which?", &items);
+        assert_eq!(z[0], "zenity");
+        assert_eq!(&z[5..], &items[..]);
+    }
+
+    #[test]
+    fn test_desktop() {
+        let vars = |set: &'static [(&'static str, &'static str)]| {
+            move |name: &str| {
+                set.iter()
+                    .find(|(n, _)| *n == name)
+                    .map(|(_, v)| v.to_string())
+            }
+        };
+        assert!(desktop(false, vars(&[("DISPLAY", ":0")])));
+        assert!(desktop(false, vars(&[("WAYLAND_DISPLAY", "wayland-0")])));
+        assert!(desktop(false, vars(&[("XDG_CURRENT_DESKTOP", "GNOME")])));
+        assert!(!desktop(false, vars(&[("DISPLAY", "")])));
+        assert!(!desktop(false, vars(&[])));
+        assert!(desktop(true, vars(&[])));
+        assert!(!desktop(
+            true,
+            vars(&[("SSH_CONNECTION", "10.0.0.1 22 10.0.0.2 22")])
+        ));
+    }
+
+    #[test]
+    fn test_parse_choice() {
+        let items: Vec<String> = vec!["a".into(), "b".into()];
+        assert_eq!(parse_choice("b\n", &items), Some(1));
+        assert_eq!(parse_choice("false\n", &items), None);
+        assert_eq!(parse_choice("", &items), None);
+    }
+}
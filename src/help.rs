@@ -0,0 +1,146 @@
+/// Help topics, shown by `cope help <topic>`, in the order they are listed.
+pub const TOPICS: &[(&str, &str, &str)] = &[
+    ("uris", "How paths become devcontainer URIs", URIS),
+    ("containers", "Working with running containers", CONTAINERS),
+    (
+        "config",
+        "The config file, and devcontainer.json settings",
+        CONFIG,
+    ),
+    (
+        "troubleshooting",
+        "When cope doesn't do what you expect",
+        TROUBLESHOOTING,
+    ),
+];
+
+const URIS: &str = "\
+cope turns each path it is given into a URI that the Dev Containers
+extension opens inside the repo's container:
+
+    vscode-remote://dev-container+<hex>/workspaces/<repo>/<path>
+
+<hex> is the hex-encoded repo root (or, for configs other than
+.devcontainer/devcontainer.json, a JSON description of the root and the
+config file).  Folders get --folder-uri, and files --file-uri.
+
+Paths outside any repo with a devcontainer config are passed to code
+unchanged, as are flags and anything after --.  With code's own
+--remote <authority>, nothing is changed.  A relative --file-uri= or
+--folder-uri= (such as --folder-uri=.) is treated as the path it
+names.  After chat, serve-web and tunnel, only the paths given to their
+flags (--add-file, --server-data-dir and the like) are changed, to
+absolute paths.
+
+Examples:
+    cope .                 # open this repo in its container
+    cope src/main.rs       # open one file in the container
+    cope --goto src/main.rs:42:7  # and go to line 42, column 7
+    COPE_VERBOSE=1 cope .  # show the URI and the code command line
+";
+
+const CONTAINERS: &str = "\
+Once code has started a repo's container, cope can reach it without
+opening the editor:
+
+    cope status            # which config, folder and compose project
+    cope ps                # the repo's containers
+    cope logs -f           # follow the container's logs
+    cope shell             # a login shell in the workspace folder
+    cope exec -- make test # run a command in the matching directory
+    cope forward 3000      # forward a container port to localhost
+    cope browse            # open a forwarded port in the browser
+    cope down              # stop the containers
+    cope reverse /workspaces/cope/src/main.rs  # the host path for it
+
+Any other running container can be opened, too:
+
+    cope attach redis /data # open /data in the redis container
+    cope k8s dev/web-1:app  # open a container in a Kubernetes pod
+
+Containers are found by the labels the Dev Containers extension puts on
+them, so a container started by the devcontainer CLI works too.
+";
+
+const CONFIG: &str = "\
+cope's own settings are in ~/.config/cope/config.json (JSON with
+comments), or the file named by COPE_CONFIG.  cope setup writes one.
+
+    {
+      \"projects\": [\"~/src\"],                 // for cope projects
+      \"discovery\": [\"devcontainer-dir\"],     // where to look for configs
+      \"aliases\": {\"w\": [\"--new-window\"]},    // cope @w
+      \"timeout\": 5,                          // seconds to wait for docker
+      \"offline\": false
+    }
+
+COPE_CODE_ARGS goes before the arguments of every code command line, as
+in COPE_CODE_ARGS=\"--disable-gpu --profile Work\".
+
+A repo's devcontainer.json can steer cope, too:
+
+    \"customizations\": {
+      \"cope\": {\"default\": true, \"displayName\": \"Go\", \"openMode\": \"folder\"}
+    }
+
+A repo's .devcontainer/cope.json adds args for code whenever the repo is
+opened, from a short list of safe flags (--profile, --disable-extension,
+--log and the like):
+
+    {\"args\": [\"--profile\", \"Backend\"]}
+
+cope doctor checks that the config file parses.
+";
+
+const TROUBLESHOOTING: &str = "\
+Start with cope doctor, which checks for code, docker and the config file.
+
+Then:
+    COPE_VERBOSE=1 cope .     # print the URI and the exact code command
+    COPE_TRACE=/tmp/t cope .  # time each step, as OTLP/JSON
+    cope status               # which config cope picked, and its container
+    cope info                 # everything cope works out from that config
+    cope which                # which editor binary cope will run
+    cope validate             # check devcontainer.json against the schema
+
+Common problems:
+- code opens the folder on the host: no devcontainer config was found
+  above the path before a .git directory.  Check \"discovery\".
+- A dialog, rather than a prompt, asks which container: cope has no
+  terminal, and is writing its messages to ~/.cache/cope/cope.log.
+- Nothing happens for a long time: docker isn't answering.  Use
+  --cope-timeout=SECONDS or --cope-offline.
+- A newer code's flag takes a parameter that cope opens as a file: run
+  cope update-flags, which learns code's flags from code --help.
+";
+
+/// The text of a topic, or None if there is no such topic.
+pub fn topic(name: &str) -> Option<&'static str> {
+    TOPICS
+        .iter()
+        .find(|(n, _, _)| *n == name)
+        .map(|(_, _, text)| *text)
+}
+
+/// The list of topics, for `cope help` on its own.
+pub fn index() -> String {
+    let mut res = String::from("Usage: cope help <topic>\n\nTopics:\n");
+    for (name, summary, _) in TOPICS {
+        res.push_str(&format!("  {name:<17}{summary}\n"));
+    }
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_topic() {
+        assert!(topic("uris").unwrap().contains("--folder-uri"));
+        assert_eq!(topic("nope"), None);
+        for (name, _, _) in TOPICS {
+            assert!(index().contains(name));
+        }
+    }
+}
@@ -0,0 +1,233 @@
+use serde::Deserialize;
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use std::thread;
+
+use crate::command_utils::capture;
+use crate::config;
+use crate::engine::{self, Engine};
+use crate::i18n::t;
+
+/// `hostRequirements` in devcontainer.json: the least the container needs.
+#[derive(Deserialize, Debug, Default, Clone, PartialEq)]
+pub struct HostRequirements {
+    pub cpus: Option<u64>,
+    pub memory: Option<String>,
+    pub storage: Option<String>,
+}
+
+impl HostRequirements {
+    pub fn is_empty(&self) -> bool {
+        self.cpus.is_none() && self.memory.is_none() && self.storage.is_none()
+    }
+}
+
+/// What the container engine can give a container.  Anything that couldn't
+/// be found out is None, and isn't checked.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Resources {
+    pub cpus: Option<u64>,
+    pub memory: Option<u64>,
+    pub storage: Option<u64>,
+}
+
+/// Parse a size like `8gb` or `512mb`, as hostRequirements writes them,
+/// into bytes.  Units are powers of 1024, and a bare number is bytes.
+pub fn parse_size(s: &str) -> Option<u64> {
+    let s = s.trim().to_ascii_lowercase();
+    let digits = s
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(s.len());
+    let (num, unit) = s.split_at(digits);
+    let scale: u64 = match unit.trim().trim_end_matches('b') {
+        "" => 1,
+        "k" => 1 << 10,
+        "m" => 1 << 20,
+        "g" => 1 << 30,
+        "t" => 1 << 40,
+        _ => return None,
+    };
+    Some((num.parse::<f64>().ok()? * scale as f64) as u64)
+}
+
+/// A size in bytes, for people.
+fn format_size(bytes: u64) -> String {
+    format!("{:.1} GB", bytes as f64 / (1u64 << 30) as f64)
+}
+
+/// Warnings for each requirement that the resources fall short of.
+pub fn shortfalls(req: &HostRequirements, have: &Resources) -> Vec<String> {
+    let mut res = Vec::new();
+    if let (Some(need), Some(have)) = (req.cpus, have.cpus)
+        && have < need
+    {
+        res.push(t!("host-cpus", need = need, have = have));
+    }
+    for (key, need, have) in [
+        ("host-memory", req.memory.as_deref(), have.memory),
+        ("host-storage", req.storage.as_deref(), have.storage),
+    ] {
+        if let (Some(need), Some(have)) = (need.and_then(parse_size), have)
+            && have < need
+        {
+            res.push(t!(key, need = format_size(need), have = format_size(have)));
+        }
+    }
+    res
+}
+
+/// Parse the output of `info_args`: CPUs, memory in bytes, and the
+/// directory that holds the engine's images and containers.
+pub fn parse_info(info: &str) -> Option<(u64, u64, String)> {
+    let mut words = info.split_whitespace();
+    let cpus = words.next()?.parse().ok()?;
+    let memory = words.next()?.parse().ok()?;
+    Some((cpus, memory, words.collect::<Vec<_>>().join(" ")))
+}
+
+/// The args that ask the engine for its CPUs, memory and storage directory.
+fn info_args(engine: Engine) -> [&'static str; 3] {
+    match engine {
+        Engine::Docker => [
+            "info",
+            "--format",
+            "{{.NCPU}} {{.MemTotal}} {{.DockerRootDir}}",
+        ],
+        Engine::Podman { .. } => [
+            "info",
+            "--format",
+            "{{.Host.CPUs}} {{.Host.MemTotal}} {{.Store.GraphRoot}}",
+        ],
+    }
+}
+
+/// Does the engine keep its images and containers on this machine's own
+/// filesystems, so that the free space there is what the container gets?
+/// Not on a Mac, where every engine runs in a VM, nor for Docker Desktop's
+/// VM (or any other context) on Linux, nor for an engine on another
+/// machine.
+pub fn storage_on_host(engine: Engine, local_docker: bool, remote: bool, macos: bool) -> bool {
+    !macos
+        && !remote
+        && match engine {
+            Engine::Docker => local_docker,
+            Engine::Podman { .. } => true,
+        }
+}
+
+/// Free space on the filesystem holding pth.
+fn free_space(pth: &Path) -> Option<u64> {
+    let st = nix::sys::statvfs::statvfs(pth).ok()?;
+    Some(st.blocks_available() as u64 * st.fragment_size() as u64)
+}
+
+/// Total memory, from /proc/meminfo.
+fn local_memory() -> Option<u64> {
+    let meminfo = fs::read_to_string("/proc/meminfo").ok()?;
+    let line = meminfo.lines().find(|l| l.starts_with("MemTotal:"))?;
+    let kb = line.trim_start_matches("MemTotal:").trim_end_matches("kB");
+    parse_size(kb).map(|kb| kb * 1024)
+}
+
+/// What the container engine has to offer.  If it can't be asked (such as
+/// when offline), the local machine's CPUs, memory and disk are used; a
+/// container on this machine can't have more.  Storage is only checked when
+/// the engine keeps it on this machine; a VM's disk can't be seen from
+/// here.
+pub fn available() -> Resources {
+    let engine = engine::current();
+    let remote = engine::remote_host(
+        config::load().docker_host.as_deref(),
+        env::var("DOCKER_HOST").ok().as_deref(),
+    )
+    .is_some();
+    let on_host = storage_on_host(
+        engine,
+        engine::local_docker(),
+        remote,
+        cfg!(target_os = "macos"),
+    );
+    let info = capture(Command::new(engine.program()).args(info_args(engine)));
+    match info.as_deref().and_then(parse_info) {
+        Some((cpus, memory, root)) => Resources {
+            cpus: Some(cpus),
+            memory: Some(memory),
+            storage: free_space(Path::new(&root)).filter(|_| on_host),
+        },
+        None => Resources {
+            cpus: thread::available_parallelism().ok().map(|n| n.get() as u64),
+            memory: local_memory(),
+            storage: free_space(Path::new("/")).filter(|_| on_host),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("8gb"), Some(8 << 30));
+        assert_eq!(parse_size("512MB"), Some(512 << 20));
+        assert_eq!(parse_size("1.5 tb"), Some(3 << 39));
+        assert_eq!(parse_size("100"), Some(100));
+        assert_eq!(parse_size("lots"), None);
+        assert_eq!(parse_size("4 parsecs"), None);
+    }
+
+    #[test]
+    fn test_shortfalls() {
+        let req: HostRequirements =
+            serde_jsonc::from_str(r#"{"cpus": 8, "memory": "16gb", "storage": "32gb"}"#).unwrap();
+        let plenty = Resources {
+            cpus: Some(16),
+            memory: Some(64 << 30),
+            storage: Some(1 << 40),
+        };
+        assert!(shortfalls(&req, &plenty).is_empty());
+        let small = Resources {
+            cpus: Some(2),
+            memory: Some(4 << 30),
+            storage: None,
+        };
+        assert_eq!(shortfalls(&req, &small).len(), 2);
+        assert!(shortfalls(&HostRequirements::default(), &small).is_empty());
+    }
+
+    #[test]
+    fn test_storage_on_host() {
+        assert!(storage_on_host(Engine::Docker, true, false, false));
+        assert!(!storage_on_host(Engine::Docker, false, false, false));
+        assert!(!storage_on_host(Engine::Docker, true, false, true));
+        assert!(storage_on_host(
+            Engine::Podman { rootless: true },
+            false,
+            false,
+            false
+        ));
+        assert!(!storage_on_host(
+            Engine::Podman { rootless: true },
+            false,
+            true,
+            false
+        ));
+        assert!(!storage_on_host(
+            Engine::Podman { rootless: false },
+            false,
+            false,
+            true
+        ));
+    }
+
+    #[test]
+    fn test_parse_info() {
+        assert_eq!(
+            parse_info("8 16663535616 /var/lib/docker"),
+            Some((8, 16663535616, "/var/lib/docker".into()))
+        );
+        assert_eq!(parse_info(""), None);
+    }
+}
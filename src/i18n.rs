@@ -0,0 +1,391 @@
+use phf::phf_map;
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::sync::OnceLock;
+
+use crate::config;
+
+/// Messages are looked up by key, and may contain `{name}` placeholders.
+/// English is the fallback for any key a locale doesn't have.
+static EN: phf::Map<&'static str, &'static str> = phf_map! {
+    "choose-container" => "Which container?",
+    "choose-project" => "Which project?",
+    "choose-tunnel" => "Which tunnel?",
+    "no-tunnels" => "No tunnels found.  Use --cope-tunnel=NAME, or start one with `code tunnel`.",
+    "unknown-flag" => "Unknown cope flag {flag} ignored",
+    "files-from-failed" => "Unable to read file names from {path}: {error}",
+    "bad-timeout" => "Invalid timeout {value} ignored; expected seconds",
+    "no-devcontainer" => "No devcontainer found for {path}",
+    "did-you-mean" => "{path} doesn't exist, so code will make it.  Did you mean {suggestion}?",
+    "bad-code-args" => "COPE_CODE_ARGS has an unclosed quote, so it was ignored: {value}",
+    "not-on-host" => "{path} isn't in the workspace folder or a bind mount, so it has no host path",
+    "no-running-container" => "No running container for {path}.  Open it with cope first.",
+    "add-other-window" => "The folder given to --add, {folder}, isn't where the window it is added to is; code can't mix folders from different containers, or from a container and this machine, in one window",
+    "mixed-containers" => "The files for {flag} are not all in one container, so they are passed to code unchanged",
+    "no-container" => "No container found for {path}",
+    "no-such-container" => "No running container called {name}",
+    "bad-config" => "{error}; using the default settings",
+    "config-unchanged" => "{error}; fix it first, so {file} is left unchanged",
+    "config-backup" => "{file} has comments or settings that cope can't keep when it rewrites the file, so the old one is saved as {backup}",
+    "bad-flags" => "{error}; ignoring the file",
+    "bad-project" => "{error}; ignoring the file",
+    "project-arg-ignored" => "Ignoring {arg} in {file}, which a repo isn't allowed to ask for",
+    "bad-devcontainer" => "Skipping {file}, which can't be read: {error}",
+    "validate-ok" => "{file}: OK",
+    "validate-json5" => "(this is JSON5 syntax, such as a trailing comma or single quotes, that cope reads but the Dev Containers extension may not)",
+    "validate-type" => "{path} should be {expected}",
+    "validate-one-of" => "{path} should be one of {values}",
+    "validate-unknown" => "{path} isn't a devcontainer.json property",
+    "validate-no-source" => "There is no image, build.dockerfile or dockerComposeFile to make the container from",
+    "validate-no-service" => "dockerComposeFile needs a service",
+    "host-cpus" => "The container wants {need} CPUs, but the container engine only has {have}",
+    "host-memory" => "The container wants {need} of memory, but the container engine only has {have}",
+    "host-storage" => "The container wants {need} of storage, but only {have} is free",
+    "no-such-pod" => "kubectl can't find a pod container for {name}",
+    "no-project-roots" => "No project roots configured.  Add a \"projects\" array to {file}",
+    "no-projects" => "No projects with a {dir} directory found in {roots}",
+    "no-frecency-match" => "No previously-opened project matches {keywords}",
+    "serve-web" => "Desktop VS Code isn't installed; serving it with `code serve-web`.  Open {url}",
+    "notify-launch-failed" => "Unable to start {command}: {error}",
+    "launch-failed" => "Failed launching {command}: {error}",
+    "editor-not-found" => "{command} isn't on the PATH, or anywhere VS Code usually installs it.  In VS Code, run \"Shell Command: Install 'code' command in PATH\" from the command palette, or set COPE_CODE_BIN to the editor binary.",
+    "notify-copied" => "Copied the repo to {path}; opening it",
+    "notify-copy-failed" => "Unable to copy {path}",
+    "notify-ready" => "The container for {path} is ready",
+    "doctor-engine" => "container engine",
+    "doctor-engine-rootless" => "podman (rootless, socket {socket})",
+    "doctor-code" => "code on PATH",
+    "doctor-code-missing" => "Install the `code` command from VS Code's command palette",
+    "doctor-docker" => "docker reachable",
+    "doctor-docker-missing" => "`docker version` failed",
+    "doctor-docker-version" => "server {version}",
+    "doctor-offline" => "Not checked, since cope is offline",
+    "offline-registry" => "cope is offline, so checking for newer versions is disabled",
+    "doctor-config" => "config file",
+    "doctor-discovery" => "discovery strategies",
+    "doctor-discovery-none" => "None enabled, so no devcontainers will be found",
+    "unknown-strategy" => "Unknown discovery strategy {name} ignored",
+    "unknown-alias" => "Unknown alias @{name}.  See `cope alias`.",
+    "unknown-help-topic" => "Unknown help topic {name}",
+    "up-to-date" => "Everything in {file} is up to date",
+    "updated-flags" => "Learned {flags} flags and {subcommands} subcommands, in {file}",
+    "update-flags-failed" => "Unable to run {command} --help",
+    "choose-upgrades" => "Which upgrades? (space to select)",
+    "updated-config" => "Updated {count} reference(s) in {file}",
+    "setup-overwrite" => "Overwrite {file}?",
+    "setup-projects" => "Directories holding your projects (comma-separated)",
+    "setup-shell" => "Add `{line}` to {file}?",
+    "setup-completion" => "Add completion for cope, `{line}`, to {file}?",
+    "usage" => "Usage: {usage}",
+    "no-container-chosen" => "No container chosen",
+    "copying" => "Copying {from} to {to}",
+    "copy-failed" => "Unable to copy {from} to {to}",
+    "copy-cleanup" => "When you are done, remove the copy (`git worktree remove` for git repos)",
+    "no-launches" => "No launches recorded yet",
+    "stats-off" => "Usage statistics are off.  Set \"stats\": true in {file}",
+    "zoxide-imported" => "Imported {count} directories from zoxide",
+    "zoxide-failed" => "Unable to run `zoxide query`",
+    "forwarding" => "Forwarding localhost:{host_port} to container port {port}",
+    "no-ports" => "No forwarded or published ports found; specify one",
+    "plan-matches" => "Plan matches {file}",
+    "wrote" => "Wrote {file}",
+    "updated" => "Updated {file}",
+    "removed" => "Removed {file}",
+    "already-set-up" => "{file} already set up",
+    "read-failed" => "Unable to read {file}: {error}",
+    "write-failed" => "Unable to write {file}: {error}",
+    "update-failed" => "Unable to update {file}: {error}",
+    "remove-failed" => "Unable to remove {file}: {error}",
+    "status-repo" => "Repo",
+    "status-config" => "Config",
+    "status-folder" => "Folder",
+    "status-users" => "Users",
+    "status-compose" => "Compose project",
+    "status-service" => "Service",
+    "setup-no-editors" => "No editors found on PATH",
+    "setup-found" => "Found {name} at {path}",
+    "setup-context" => "{engine} context: {context}",
+    "setup-host" => "{engine} host: {host}",
+    "setup-no-engine" => "{engine} not available",
+    "setup-shell-name" => "Shell: {shell}",
+    "shim-updated-rc" => "Updated {file}; start a new shell to use it",
+    "shim-add-to-path" => "Put {dir} at the front of your PATH",
+    "shim-shim" => "Shim",
+    "shim-not-installed" => "not installed",
+    "shim-on-path" => "On PATH",
+    "shim-first" => "yes, first",
+    "shim-not-first" => "no",
+    "shim-real-code" => "Real code",
+    "shim-not-found" => "not found",
+    "shim-unknown" => "Unknown shim command {command}; use install, remove or status",
+};
+
+static DE: phf::Map<&'static str, &'static str> = phf_map! {
+    "choose-container" => "Welcher Container?",
+    "choose-project" => "Welches Projekt?",
+    "choose-tunnel" => "Welcher Tunnel?",
+    "no-tunnels" => "Keine Tunnel gefunden.  --cope-tunnel=NAME verwenden oder mit `code tunnel` einen starten.",
+    "unknown-flag" => "Unbekannte cope-Option {flag} ignoriert",
+    "files-from-failed" => "Dateinamen aus {path} können nicht gelesen werden: {error}",
+    "bad-timeout" => "Ungültiges Zeitlimit {value} ignoriert; Sekunden erwartet",
+    "no-devcontainer" => "Kein Devcontainer für {path} gefunden",
+    "did-you-mean" => "{path} existiert nicht, daher legt code die Datei an.  Meinten Sie {suggestion}?",
+    "bad-code-args" => "COPE_CODE_ARGS enthält ein nicht geschlossenes Anführungszeichen und wurde ignoriert: {value}",
+    "not-on-host" => "{path} liegt weder im Arbeitsordner noch in einem Bind-Mount und hat daher keinen Pfad auf dem Host",
+    "no-running-container" => "Kein laufender Container für {path}.  Zuerst mit cope öffnen.",
+    "add-other-window" => "Der Ordner für --add, {folder}, liegt nicht dort, wo das Fenster ist, dem er hinzugefügt wird; code kann Ordner aus verschiedenen Containern oder aus einem Container und diesem Rechner nicht in einem Fenster mischen",
+    "mixed-containers" => "Die Dateien für {flag} liegen nicht alle in einem Container und werden daher unverändert an code übergeben",
+    "no-container" => "Kein Container für {path} gefunden",
+    "no-such-container" => "Kein laufender Container namens {name}",
+    "bad-config" => "{error}; es werden die Standardeinstellungen verwendet",
+    "config-unchanged" => "{error}; bitte zuerst beheben, {file} bleibt daher unverändert",
+    "config-backup" => "{file} enthält Kommentare oder Einstellungen, die cope beim Neuschreiben nicht behalten kann, daher ist die alte Datei als {backup} gesichert",
+    "bad-flags" => "{error}; die Datei wird ignoriert",
+    "bad-project" => "{error}; die Datei wird ignoriert",
+    "project-arg-ignored" => "{arg} in {file} wird ignoriert, da ein Repository das nicht verlangen darf",
+    "bad-devcontainer" => "{file} wird übersprungen, da es nicht gelesen werden kann: {error}",
+    "validate-ok" => "{file}: OK",
+    "validate-json5" => "(das ist JSON5-Syntax, etwa ein abschließendes Komma oder einfache Anführungszeichen, die cope liest, die Dev-Containers-Erweiterung aber vielleicht nicht)",
+    "validate-type" => "{path} sollte {expected} sein",
+    "validate-one-of" => "{path} sollte eines von {values} sein",
+    "validate-unknown" => "{path} ist keine devcontainer.json-Eigenschaft",
+    "validate-no-source" => "Es gibt kein image, build.dockerfile oder dockerComposeFile, aus dem der Container erstellt wird",
+    "validate-no-service" => "dockerComposeFile braucht einen service",
+    "host-cpus" => "Der Container möchte {need} CPUs, die Container-Engine hat aber nur {have}",
+    "host-memory" => "Der Container möchte {need} Arbeitsspeicher, die Container-Engine hat aber nur {have}",
+    "host-storage" => "Der Container möchte {need} Speicherplatz, frei sind aber nur {have}",
+    "no-such-pod" => "kubectl findet keinen Pod-Container für {name}",
+    "no-project-roots" => "Keine Projektverzeichnisse konfiguriert.  Ein \"projects\"-Array in {file} eintragen",
+    "no-projects" => "Keine Projekte mit einem {dir}-Verzeichnis in {roots} gefunden",
+    "no-frecency-match" => "Kein bereits geöffnetes Projekt passt zu {keywords}",
+    "serve-web" => "Desktop-VS-Code ist nicht installiert; es wird mit `code serve-web` bereitgestellt.  {url} öffnen",
+    "notify-launch-failed" => "{command} konnte nicht gestartet werden: {error}",
+    "launch-failed" => "Fehler beim Starten von {command}: {error}",
+    "editor-not-found" => "{command} ist weder im PATH noch dort, wo VS Code üblicherweise installiert wird.  In VS Code in der Befehlspalette \"Shellbefehl: Befehl 'code' in PATH installieren\" ausführen oder COPE_CODE_BIN auf das Editor-Programm setzen.",
+    "notify-copied" => "Repo nach {path} kopiert; wird geöffnet",
+    "notify-copy-failed" => "{path} konnte nicht kopiert werden",
+    "notify-ready" => "Der Container für {path} ist bereit",
+    "doctor-engine" => "Container-Engine",
+    "doctor-engine-rootless" => "podman (rootless, Socket {socket})",
+    "doctor-code" => "code im PATH",
+    "doctor-code-missing" => "Den Befehl `code` über die Befehlspalette von VS Code installieren",
+    "doctor-docker" => "docker erreichbar",
+    "doctor-docker-missing" => "`docker version` fehlgeschlagen",
+    "doctor-docker-version" => "Server {version}",
+    "doctor-offline" => "Nicht geprüft, da cope offline ist",
+    "offline-registry" => "cope ist offline, daher ist die Suche nach neueren Versionen deaktiviert",
+    "doctor-config" => "Konfigurationsdatei",
+    "doctor-discovery" => "Suchstrategien",
+    "doctor-discovery-none" => "Keine aktiviert, daher werden keine Devcontainer gefunden",
+    "unknown-strategy" => "Unbekannte Suchstrategie {name} ignoriert",
+    "unknown-alias" => "Unbekannter Alias @{name}.  Siehe `cope alias`.",
+    "unknown-help-topic" => "Unbekanntes Hilfethema {name}",
+    "up-to-date" => "Alles in {file} ist aktuell",
+    "updated-flags" => "{flags} Optionen und {subcommands} Unterbefehle gelernt, in {file}",
+    "update-flags-failed" => "{command} --help kann nicht ausgeführt werden",
+    "choose-upgrades" => "Welche Aktualisierungen? (Leertaste zum Auswählen)",
+    "updated-config" => "{count} Referenz(en) in {file} aktualisiert",
+    "setup-overwrite" => "{file} überschreiben?",
+    "setup-projects" => "Verzeichnisse mit Projekten (durch Kommas getrennt)",
+    "setup-shell" => "`{line}` zu {file} hinzufügen?",
+    "setup-completion" => "Vervollständigung für cope, `{line}`, zu {file} hinzufügen?",
+    "usage" => "Aufruf: {usage}",
+    "no-container-chosen" => "Kein Container ausgewählt",
+    "copying" => "Kopiere {from} nach {to}",
+    "copy-failed" => "{from} konnte nicht nach {to} kopiert werden",
+    "copy-cleanup" => "Die Kopie danach entfernen (`git worktree remove` bei Git-Repos)",
+    "no-launches" => "Noch keine Starts aufgezeichnet",
+    "stats-off" => "Die Nutzungsstatistik ist aus.  \"stats\": true in {file} setzen",
+    "zoxide-imported" => "{count} Verzeichnisse aus zoxide importiert",
+    "zoxide-failed" => "`zoxide query` konnte nicht ausgeführt werden",
+    "forwarding" => "Leite localhost:{host_port} an Container-Port {port} weiter",
+    "no-ports" => "Keine weitergeleiteten oder veröffentlichten Ports gefunden; bitte einen angeben",
+    "plan-matches" => "Plan stimmt mit {file} überein",
+    "wrote" => "{file} geschrieben",
+    "updated" => "{file} aktualisiert",
+    "removed" => "{file} entfernt",
+    "already-set-up" => "{file} ist bereits eingerichtet",
+    "read-failed" => "{file} konnte nicht gelesen werden: {error}",
+    "write-failed" => "{file} konnte nicht geschrieben werden: {error}",
+    "update-failed" => "{file} konnte nicht aktualisiert werden: {error}",
+    "remove-failed" => "{file} konnte nicht entfernt werden: {error}",
+    "status-repo" => "Repo",
+    "status-config" => "Konfiguration",
+    "status-folder" => "Ordner",
+    "status-users" => "Benutzer",
+    "status-compose" => "Compose-Projekt",
+    "status-service" => "Dienst",
+    "setup-no-editors" => "Keine Editoren im PATH gefunden",
+    "setup-found" => "{name} gefunden: {path}",
+    "setup-context" => "{engine}-Kontext: {context}",
+    "setup-host" => "{engine}-Host: {host}",
+    "setup-no-engine" => "{engine} nicht verfügbar",
+    "setup-shell-name" => "Shell: {shell}",
+    "shim-updated-rc" => "{file} aktualisiert; zum Verwenden eine neue Shell starten",
+    "shim-add-to-path" => "{dir} an den Anfang des PATH setzen",
+    "shim-shim" => "Shim",
+    "shim-not-installed" => "nicht installiert",
+    "shim-on-path" => "Im PATH",
+    "shim-first" => "ja, zuerst",
+    "shim-not-first" => "nein",
+    "shim-real-code" => "Echtes code",
+    "shim-not-found" => "nicht gefunden",
+    "shim-unknown" => "Unbekannter Shim-Befehl {command}; install, remove oder status verwenden",
+};
+
+/// The language part of a POSIX locale name, e.g. "de" from "de_DE.UTF-8".
+/// "C" and "POSIX" mean no translation.
+pub fn language(locale: &str) -> Option<String> {
+    let lang = locale
+        .split(['_', '.', '@'])
+        .next()
+        .unwrap_or_default()
+        .to_lowercase();
+    match lang.as_str() {
+        "" | "c" | "posix" => None,
+        _ => Some(lang),
+    }
+}
+
+/// The user's language, using the usual precedence of locale variables.
+pub fn detect(var: impl Fn(&str) -> Option<String>) -> Option<String> {
+    ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .iter()
+        .filter_map(|v| var(v))
+        .find(|v| !v.is_empty())
+        .and_then(|v| language(&v))
+}
+
+fn builtin(lang: &str) -> Option<&'static phf::Map<&'static str, &'static str>> {
+    match lang {
+        "en" => Some(&EN),
+        "de" => Some(&DE),
+        _ => None,
+    }
+}
+
+struct Catalog {
+    lang: Option<String>,
+    /// Translations from `~/.config/cope/locales/<lang>.json`, which win over
+    /// the built-in ones, so teams can add or fix a language locally.
+    overrides: BTreeMap<String, String>,
+}
+
+fn catalog() -> &'static Catalog {
+    static CATALOG: OnceLock<Catalog> = OnceLock::new();
+    CATALOG.get_or_init(|| {
+        let lang = detect(|v| env::var(v).ok());
+        let overrides = lang
+            .as_ref()
+            .and_then(|l| {
+                let file_name = config::config_dir()
+                    .join("locales")
+                    .join(format!("{l}.json"));
+                fs::read_to_string(file_name).ok()
+            })
+            .and_then(|json_data| serde_jsonc::from_str(&json_data).ok())
+            .unwrap_or_default();
+        Catalog { lang, overrides }
+    })
+}
+
+/// Replace each `{name}` in template with its value.
+pub fn interpolate(template: &str, args: &[(&str, String)]) -> String {
+    args.iter()
+        .fold(template.to_string(), |acc, (name, value)| {
+            acc.replace(&format!("{{{name}}}"), value)
+        })
+}
+
+/// Find the message for key in the given language, falling back to
+/// English, then to the key itself.
+pub fn lookup(lang: Option<&str>, overrides: &BTreeMap<String, String>, key: &str) -> String {
+    if let Some(s) = overrides.get(key) {
+        return s.clone();
+    }
+    lang.and_then(builtin)
+        .and_then(|m| m.get(key))
+        .or_else(|| EN.get(key))
+        .map_or_else(|| key.to_string(), |s| s.to_string())
+}
+
+/// Translate key into the user's language, filling in placeholders.
+pub fn tr(key: &str, args: &[(&str, String)]) -> String {
+    let c = catalog();
+    interpolate(&lookup(c.lang.as_deref(), &c.overrides, key), args)
+}
+
+/// `t!("key")` or `t!("key", name = value, ...)`, where each value is
+/// anything that implements Display.
+macro_rules! t {
+    ($key:expr) => {
+        $crate::i18n::tr($key, &[])
+    };
+    ($key:expr, $($name:ident = $val:expr),+ $(,)?) => {
+        $crate::i18n::tr($key, &[$((stringify!($name), $val.to_string())),+])
+    };
+}
+pub(crate) use t;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_language() {
+        assert_eq!(language("de_DE.UTF-8"), Some("de".into()));
+        assert_eq!(language("fr"), Some("fr".into()));
+        assert_eq!(language("sr@latin"), Some("sr".into()));
+        assert_eq!(language("C.UTF-8"), None);
+        assert_eq!(language("POSIX"), None);
+    }
+
+    #[test]
+    fn test_detect() {
+        let vars = |v: &str| match v {
+            "LC_ALL" => Some(String::new()),
+            "LANG" => Some("de_AT.UTF-8".into()),
+            _ => None,
+        };
+        assert_eq!(detect(vars), Some("de".into()));
+        assert_eq!(detect(|_| None), None);
+    }
+
+    #[test]
+    fn test_lookup() {
+        let none = BTreeMap::new();
+        assert_eq!(
+            lookup(Some("de"), &none, "choose-project"),
+            "Welches Projekt?"
+        );
+        assert_eq!(
+            lookup(Some("xx"), &none, "choose-project"),
+            "Which project?"
+        );
+        assert_eq!(
+            lookup(None, &none, "___no_such_key___"),
+            "___no_such_key___"
+        );
+        let overrides =
+            BTreeMap::from([("choose-project".to_string(), "Quel projet ?".to_string())]);
+        assert_eq!(
+            lookup(Some("fr"), &overrides, "choose-project"),
+            "Quel projet ?"
+        );
+    }
+
+    #[test]
+    fn test_catalogs_match() {
+        for key in DE.keys() {
+            assert!(EN.contains_key(key), "{key} missing from English");
+        }
+    }
+
+    #[test]
+    fn test_t() {
+        assert_eq!(interpolate("a {x} b {x}", &[("x", "1".into())]), "a 1 b 1");
+        let s = t!("no-container", path = "/r");
+        assert!(s.contains("/r"), "{s}");
+    }
+}
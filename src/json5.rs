@@ -0,0 +1,131 @@
+/// Rewrite the JSON5 features that people use in devcontainer.json (single
+/// quotes, unquoted keys, trailing commas, comments and leading `+` signs)
+/// as plain JSON.  This isn't a validating parser; anything it doesn't
+/// understand is copied through for the JSON parser to complain about.
+pub fn to_json(input: &str) -> String {
+    let mut out = String::with_capacity(input.len());
+    let mut chars = input.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => {
+                out.push(c);
+                while let Some(s) = chars.next() {
+                    out.push(s);
+                    match s {
+                        '\\' => out.extend(chars.next()),
+                        '"' => break,
+                        _ => {}
+                    }
+                }
+            }
+            '\'' => {
+                out.push('"');
+                while let Some(s) = chars.next() {
+                    match s {
+                        '\\' => match chars.next() {
+                            Some('\'') => out.push('\''),
+                            Some(e) => {
+                                out.push('\\');
+                                out.push(e);
+                            }
+                            None => {}
+                        },
+                        '"' => out.push_str("\\\""),
+                        '\'' => break,
+                        _ => out.push(s),
+                    }
+                }
+                out.push('"');
+            }
+            '/' if chars.peek() == Some(&'/') => {
+                for s in chars.by_ref() {
+                    if s == '\n' {
+                        out.push(s);
+                        break;
+                    }
+                }
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut prev = ' ';
+                for s in chars.by_ref() {
+                    if prev == '*' && s == '/' {
+                        break;
+                    }
+                    prev = s;
+                }
+            }
+            '}' | ']' => {
+                // Trailing commas.
+                let kept = out.trim_end().len();
+                out.truncate(kept);
+                if out.ends_with(',') {
+                    out.pop();
+                }
+                out.push(c);
+            }
+            '+' if chars
+                .peek()
+                .is_some_and(|n| n.is_ascii_digit() || *n == '.') => {}
+            c if c.is_ascii_digit() => {
+                out.push(c);
+                let mut prev = c;
+                while let Some(&n) = chars.peek() {
+                    let exponent = (n == '+' || n == '-') && (prev == 'e' || prev == 'E');
+                    if !(n.is_ascii_alphanumeric() || n == '.' || exponent) {
+                        break;
+                    }
+                    out.push(n);
+                    prev = n;
+                    chars.next();
+                }
+            }
+            c if c.is_alphabetic() || c == '_' || c == '$' => {
+                let mut word = String::from(c);
+                while let Some(&n) = chars.peek() {
+                    if !(n.is_alphanumeric() || n == '_' || n == '$') {
+                        break;
+                    }
+                    word.push(n);
+                    chars.next();
+                }
+                match word.as_str() {
+                    "true" | "false" | "null" => out.push_str(&word),
+                    _ => {
+                        out.push('"');
+                        out.push_str(&word);
+                        out.push('"');
+                    }
+                }
+            }
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_json() {
+        let input = r#"{
+            // The name.
+            name: 'Go "fast"',
+            image: 'it\'s/here',
+            forwardPorts: [3000, +8080, 1e+3,],
+            /* A block
+               comment */
+            "workspaceFolder": "/w//x",
+            remoteUser: null,
+        }"#;
+        let v: serde_jsonc::Value = serde_jsonc::from_str(&to_json(input)).unwrap();
+        assert_eq!(v["name"], "Go \"fast\"");
+        assert_eq!(v["image"], "it's/here");
+        assert_eq!(v["forwardPorts"][1], 8080);
+        assert_eq!(v["forwardPorts"].as_array().unwrap().len(), 3);
+        assert_eq!(v["workspaceFolder"], "/w//x");
+        assert!(v["remoteUser"].is_null());
+    }
+}
@@ -0,0 +1,126 @@
+use std::process::Command;
+
+use crate::command_utils::capture;
+use crate::string_utils::encode_uri_component;
+
+/// A container in a Kubernetes pod, as the Kubernetes extension names it
+/// in the URI when it attaches.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Target {
+    pub context: String,
+    pub namespace: String,
+    pub pod: String,
+    pub container: String,
+    pub image: String,
+    pub working_dir: Option<String>,
+}
+
+impl Target {
+    /// The authority part of the URI.  Each value is encoded, as the
+    /// Kubernetes extension does, since images such as
+    /// `ghcr.io/org/app:1.2` have characters that would end it early.
+    pub fn authority(&self) -> String {
+        format!(
+            "k8s-container+context={}+podname={}+namespace={}+name={}+image={}",
+            encode_uri_component(&self.context),
+            encode_uri_component(&self.pod),
+            encode_uri_component(&self.namespace),
+            encode_uri_component(&self.container),
+            encode_uri_component(&self.image)
+        )
+    }
+
+    /// The argument that opens path in the container.  Paths in the pod
+    /// aren't checked, so every path is opened as a folder.
+    pub fn uri(&self, path: &str) -> String {
+        format!("--folder-uri=vscode-remote://{}{path}", self.authority())
+    }
+}
+
+/// Split `[namespace/]pod[:container]`.
+pub fn parse_spec(spec: &str) -> (Option<&str>, &str, Option<&str>) {
+    let (namespace, rest) = match spec.split_once('/') {
+        Some((n, r)) => (Some(n), r),
+        None => (None, spec),
+    };
+    match rest.split_once(':') {
+        Some((pod, container)) => (namespace, pod, Some(container)),
+        None => (namespace, rest, None),
+    }
+}
+
+/// The target, out of the output of `kubectl get pod -o json`.  Without a
+/// container name, the pod's first container is used.
+pub fn parse_pod(json_data: &str, context: &str, container: Option<&str>) -> Option<Target> {
+    let v: serde_jsonc::Value = serde_jsonc::from_str(json_data).ok()?;
+    let c = v["spec"]["containers"]
+        .as_array()?
+        .iter()
+        .find(|c| container.is_none_or(|want| c["name"].as_str() == Some(want)))?;
+    Some(Target {
+        context: context.into(),
+        namespace: v["metadata"]["namespace"].as_str()?.into(),
+        pod: v["metadata"]["name"].as_str()?.into(),
+        container: c["name"].as_str()?.into(),
+        image: c["image"].as_str()?.into(),
+        working_dir: c["workingDir"].as_str().map(String::from),
+    })
+}
+
+/// Ask kubectl about the pod, in the current context.
+pub fn find(spec: &str) -> Option<Target> {
+    let (namespace, pod, container) = parse_spec(spec);
+    let context = capture(Command::new("kubectl").args(["config", "current-context"]))?;
+    let mut cmd = Command::new("kubectl");
+    cmd.args(["get", "pod", pod, "-o", "json"]);
+    if let Some(n) = namespace {
+        cmd.args(["-n", n]);
+    }
+    parse_pod(&capture(&mut cmd)?, &context, container)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const POD: &str = r#"{
+        "metadata": {"name": "web-1", "namespace": "dev"},
+        "spec": {"containers": [
+            {"name": "app", "image": "node:22", "workingDir": "/app"},
+            {"name": "proxy", "image": "nginx"}
+        ]}
+    }"#;
+
+    #[test]
+    fn test_parse_spec() {
+        assert_eq!(parse_spec("web-1"), (None, "web-1", None));
+        assert_eq!(
+            parse_spec("dev/web-1:proxy"),
+            (Some("dev"), "web-1", Some("proxy"))
+        );
+    }
+
+    #[test]
+    fn test_parse_pod() {
+        let t = parse_pod(POD, "minikube", None).unwrap();
+        assert_eq!(t.container, "app");
+        assert_eq!(t.working_dir.as_deref(), Some("/app"));
+        assert_eq!(
+            t.uri("/app/src"),
+            "--folder-uri=vscode-remote://k8s-container+context=minikube+podname=web-1+namespace=dev+name=app+image=node%3A22/app/src"
+        );
+        let t = parse_pod(POD, "minikube", Some("proxy")).unwrap();
+        assert_eq!((t.image.as_str(), t.working_dir), ("nginx", None));
+        assert_eq!(parse_pod(POD, "minikube", Some("nope")), None);
+        let t = Target {
+            context: "arn:aws:eks:us-east-1:1:cluster/prod".into(),
+            image: "ghcr.io/org/app:1.2".into(),
+            ..parse_pod(POD, "minikube", None).unwrap()
+        };
+        assert_eq!(
+            t.uri("/app"),
+            "--folder-uri=vscode-remote://k8s-container+context=arn%3Aaws%3Aeks%3Aus-east-1%3A1%3Acluster%2Fprod\
+             +podname=web-1+namespace=dev+name=app+image=ghcr.io%2Forg%2Fapp%3A1.2/app"
+        );
+    }
+}
@@ -0,0 +1,201 @@
+use std::ffi::{OsStr, OsString};
+use std::path::Path;
+
+use crate::compose::Compose;
+use crate::docker::{container_filters, find_container};
+use crate::engine;
+
+/// Does this flag of the docker (or compose) command behind cmd take a
+/// value in the next argument?  Only the flags of `ps`, `logs` and `stop`
+/// matter here.
+fn takes_value(cmd: &str, flag: &str) -> bool {
+    let flags: &[&str] = match cmd {
+        "status" | "ps" => &["--filter", "-f", "--format", "--last", "-n", "--status"],
+        "logs" => &["--tail", "-n", "--since", "--until", "--index"],
+        "down" => &["--time", "--timeout", "-t", "--signal", "-s"],
+        _ => &[],
+    };
+    flags.contains(&flag)
+}
+
+/// Split the arguments of a lifecycle subcommand into the path, which is the
+/// first argument that isn't a flag or a flag's value, and the arguments for
+/// docker.
+pub fn split_path<'a>(cmd: &str, args: &'a [OsString]) -> (Option<&'a OsStr>, Vec<String>) {
+    let mut path = None;
+    let mut extra: Vec<String> = Vec::new();
+    let mut it = args.iter();
+    while let Some(a) = it.next() {
+        let s = a.to_string_lossy();
+        if s.starts_with('-') {
+            extra.push(s.to_string());
+            if takes_value(cmd, &s)
+                && let Some(value) = it.next()
+            {
+                extra.push(value.to_string_lossy().into());
+            }
+        } else if path.is_none() {
+            path = Some(a.as_os_str());
+        } else {
+            extra.push(s.to_string());
+        }
+    }
+    (path, extra)
+}
+
+/// The docker command line for one of the lifecycle subcommands (`status`,
+/// `ps`, `logs`, `down`), aimed at the containers for this repo and config.
+/// Compose-based configs operate on the whole compose project.  `down` stops
+/// the containers either way, without removing them.  Returns None if a
+/// container is needed but can't be found.
+pub fn docker_args(
+    cmd: &str,
+    root: &Path,
+    config_file: &Path,
+    compose: Option<&Compose>,
+    extra: &[String],
+) -> Option<Vec<String>> {
+    let mut res: Vec<String> = vec![engine::program().into()];
+    if let Some(c) = compose {
+        res.extend(c.args());
+        res.push(
+            match cmd {
+                "status" => "ps",
+                "down" => "stop",
+                _ => cmd,
+            }
+            .into(),
+        );
+        res.extend(extra.iter().cloned());
+        return Some(res);
+    }
+
+    match cmd {
+        "status" => {
+            res.extend(["ps".into(), "-a".into()]);
+            res.extend(container_filters(root, config_file));
+            res.extend([
+                "--format".into(),
+                "table {{.ID}}\t{{.Status}}\t{{.Names}}".into(),
+            ]);
+        }
+        "ps" => {
+            res.extend(["ps".into(), "-a".into()]);
+            res.extend(container_filters(root, config_file));
+        }
+        "logs" | "down" => {
+            let id = find_container(root, config_file, true)?;
+            res.push(if cmd == "logs" { "logs" } else { "stop" }.into());
+            res.extend(extra.iter().cloned());
+            res.push(id);
+            return Some(res);
+        }
+        _ => panic!("Unknown lifecycle command {cmd}"),
+    }
+    res.extend(extra.iter().cloned());
+    Some(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compose::StringOrList;
+
+    #[test]
+    fn test_status() {
+        let a = docker_args(
+            "status",
+            Path::new("/r"),
+            Path::new("/r/.devcontainer/devcontainer.json"),
+            None,
+            &[],
+        )
+        .unwrap();
+        assert_eq!(a[..3], ["docker", "ps", "-a"]);
+        assert_eq!(a[4], "label=devcontainer.local_folder=/r");
+    }
+
+    #[test]
+    fn test_split_path() {
+        let args = |a: &[&str]| -> Vec<OsString> { a.iter().map(OsString::from).collect() };
+        let a = args(&["--tail", "10"]);
+        assert_eq!(
+            split_path("logs", &a),
+            (None, vec!["--tail".into(), "10".into()])
+        );
+        let a = args(&["-f", "src", "-n", "5"]);
+        assert_eq!(
+            split_path("logs", &a),
+            (
+                Some(OsStr::new("src")),
+                vec!["-f".into(), "-n".into(), "5".into()]
+            )
+        );
+        let a = args(&["-f", "status=exited", "."]);
+        assert_eq!(
+            split_path("ps", &a),
+            (
+                Some(OsStr::new(".")),
+                vec!["-f".into(), "status=exited".into()]
+            )
+        );
+        let a = args(&["--tail=10", "src", "more"]);
+        assert_eq!(
+            split_path("logs", &a),
+            (
+                Some(OsStr::new("src")),
+                vec!["--tail=10".into(), "more".into()]
+            )
+        );
+    }
+
+    #[test]
+    fn test_down() {
+        let c = Compose::new(
+            Path::new("/r/.devcontainer/devcontainer.json"),
+            &StringOrList::One("compose.yml".into()),
+            None,
+            Some("proj".into()),
+        );
+        let a = docker_args(
+            "down",
+            Path::new("/r"),
+            Path::new("/r/c.json"),
+            Some(&c),
+            &[],
+        )
+        .unwrap();
+        assert_eq!(a.last().unwrap(), "stop");
+    }
+
+    #[test]
+    fn test_compose() {
+        let c = Compose::new(
+            Path::new("/r/.devcontainer/devcontainer.json"),
+            &StringOrList::One("compose.yml".into()),
+            Some("app".into()),
+            Some("proj".into()),
+        );
+        let a = docker_args(
+            "logs",
+            Path::new("/r"),
+            Path::new("/r/.devcontainer/devcontainer.json"),
+            Some(&c),
+            &["-f".into()],
+        )
+        .unwrap();
+        assert_eq!(
+            a,
+            vec![
+                "docker",
+                "compose",
+                "-p",
+                "proj",
+                "-f",
+                "/r/.devcontainer/compose.yml",
+                "logs",
+                "-f"
+            ]
+        );
+    }
+}
@@ -1,65 +1,216 @@
+mod aliases;
+mod code_subcommands;
+mod codespaces;
+mod command_utils;
+mod complete;
+mod compose;
+mod config;
+mod discovery;
+mod docker;
+mod doctor;
+mod editor;
+mod engine;
 mod file_utils;
+mod flags;
+mod frecency;
+mod glob;
+mod gui;
+mod help;
+mod host;
+mod i18n;
+mod json5;
+mod k8s;
+mod lifecycle;
+mod mounts;
+mod new_container;
+mod notify;
+mod options;
+mod packaging;
+mod ports;
+mod projects;
+mod record;
+mod registry;
+mod schema;
+mod setup;
+mod shim;
+mod stats;
 mod string_utils;
+mod toml;
+mod trace;
+mod tunnel;
+mod update;
+mod variables;
 
-use dialoguer::Select;
+use dialoguer::{MultiSelect, Select};
 use nix::unistd::execvp;
-use phf::{phf_map, phf_set};
-use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
 use std::env;
 use std::ffi::{CStr, CString, OsStr, OsString};
 use std::fs;
+use std::io::IsTerminal;
+use std::iter::once;
+use std::os::unix::ffi::OsStrExt;
 use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 
-use crate::file_utils::FindOptions;
-pub use crate::file_utils::{files_matching, find_dir_up, normalize};
+use crate::compose::{Compose, StringOrList};
+pub use crate::config::expand_home;
+use crate::config::{SshProject, TunnelProject};
+use crate::discovery::Strategy;
+use crate::editor::UriVariant;
+pub use crate::file_utils::normalize;
+use crate::host::HostRequirements;
+use crate::i18n::t;
+use crate::mounts::{Mount, MountSpec};
+use crate::options::CopeOptions;
+use crate::ports::PortSpec;
+use crate::string_utils::{aligned, encode_uri_path};
 pub use crate::string_utils::{debug_arg, debug_args, hex, to_cstring};
+use crate::trace::Span;
 
 const DEVCONTAINER_DIR: &str = ".devcontainer";
 const CONFIG_FILE: &str = "devcontainer.json";
 
 const CODE: &CStr = c"code";
-static PARAM_SIZE: phf::Map<&'static str, usize> = phf_map! {
-    "--add-mcp" => 1,
-    "--add" => 1,
-    "--category" => 1,
-    "--diff" => 2,
-    "--disable-extension" => 1,
-    "--enable-proposed-api" => 1,
-    "--extensions-dir" => 1,
-    "--goto" => 1,
-    "--inspect-brk-extensions" => 1,
-    "--inspect-extensions" => 1,
-    "--install-extension" => 1,
-    "--locale" => 1,
-    "--locate-shell-integration-path" => 1,
-    "--log" => 1,
-    "--merge" => 4,
-    "--profile" => 1,
-    "--remove" => 1,
-    "--sync" => 1,
-    "--uninstall-extension" => 1,
-    "--user-data-dir" => 1,
-    "-a" => 1,
-    "-d" => 2,
-    "-g" => 1,
-    "-m" => 4,
-};
-
-/// All params after one of these are not file names, as far as I can tell.
-static TERMINAL_PARAM: phf::Set<&'static str> = phf_set! [
-    "--",
-    "chat",
-    "serve-web",
-    "tunnel"
-];
 
 #[derive(Deserialize, Debug)]
 struct DevContainer {
     name: Option<String>,
 
+    image: Option<String>,
+
+    build: Option<Build>,
+
+    /// The older spelling of build.dockerfile.
+    #[serde(rename = "dockerFile")]
+    docker_file: Option<String>,
+
     #[serde(rename = "workspaceFolder")]
     workspace_folder: Option<String>,
+
+    #[serde(rename = "workspaceMount")]
+    workspace_mount: Option<String>,
+
+    #[serde(default)]
+    mounts: Vec<MountSpec>,
+
+    #[serde(rename = "remoteUser")]
+    remote_user: Option<String>,
+
+    #[serde(rename = "containerUser")]
+    container_user: Option<String>,
+
+    #[serde(rename = "dockerComposeFile")]
+    docker_compose_file: Option<StringOrList>,
+
+    service: Option<String>,
+
+    #[serde(rename = "forwardPorts", default)]
+    forward_ports: Vec<PortSpec>,
+
+    #[serde(rename = "hostRequirements", default)]
+    host_requirements: HostRequirements,
+
+    #[serde(default)]
+    features: BTreeMap<String, serde_jsonc::Value>,
+
+    #[serde(default)]
+    customizations: Customizations,
+}
+
+#[derive(Deserialize, Debug, Default)]
+struct Build {
+    dockerfile: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+struct Customizations {
+    #[serde(default)]
+    cope: CopeCustomizations,
+}
+
+/// `customizations.cope` in devcontainer.json, which lets repo authors tell
+/// cope how to treat the config.
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+struct CopeCustomizations {
+    /// Pick this config without asking when the repo has several.
+    #[serde(default)]
+    default: bool,
+
+    /// Shown in the chooser instead of `name`.
+    display_name: Option<String>,
+
+    /// "folder" opens the container's workspace folder for any path in the
+    /// repo, rather than the path itself.
+    open_mode: Option<OpenMode>,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "camelCase")]
+enum OpenMode {
+    Path,
+    Folder,
+}
+
+impl DevContainer {
+    /// What the container is made from, briefly: the image, the Dockerfile,
+    /// or the compose files and service.
+    fn summary(&self) -> Option<String> {
+        if let Some(image) = &self.image {
+            return Some(image.clone());
+        }
+        let dockerfile = self.build.as_ref().and_then(|b| b.dockerfile.as_ref());
+        if let Some(dockerfile) = dockerfile.or(self.docker_file.as_ref()) {
+            return Some(format!("build {dockerfile}"));
+        }
+        let files = self.docker_compose_file.as_ref()?.to_vec().join(", ");
+        Some(match &self.service {
+            Some(service) => format!("compose {files}, service {service}"),
+            None => format!("compose {files}"),
+        })
+    }
+
+    /// The user that VS Code runs as inside the container.  remoteUser
+    /// defaults to containerUser, which defaults to the image's user.
+    fn user(&self) -> Option<&str> {
+        self.remote_user
+            .as_deref()
+            .or(self.container_user.as_deref())
+    }
+
+    /// What to call this config in the chooser.  Configs without a name get
+    /// one made from their subfolder of `.devcontainer` and the image or
+    /// compose service they use, so that several anonymous configs can be
+    /// told apart.
+    fn label(&self, config_file: &Path) -> String {
+        if let Some(name) = self
+            .customizations
+            .cope
+            .display_name
+            .as_ref()
+            .or(self.name.as_ref())
+        {
+            return name.clone();
+        }
+        let sub = config_file
+            .parent()
+            .filter(|d| d.parent().and_then(Path::file_name) == Some(OsStr::new(DEVCONTAINER_DIR)))
+            .and_then(Path::file_name)
+            .map(|d| d.to_string_lossy());
+        let key = self
+            .image
+            .clone()
+            .or_else(|| self.service.as_ref().map(|s| format!("service {s}")));
+        match (sub, key) {
+            (Some(sub), Some(key)) => format!("{sub}: {key}"),
+            (Some(sub), None) => sub.into_owned(),
+            (None, Some(key)) => key,
+            (None, None) => "<no name>".to_string(),
+        }
+    }
 }
 
 struct JsonResults {
@@ -67,45 +218,287 @@ struct JsonResults {
     pub dev_container: DevContainer,
 }
 
+#[derive(Clone)]
 struct DirProperties {
     pub hex: String,
     pub folder: String,
+    pub config_file: PathBuf,
+    pub user: Option<String>,
+    pub remote_user: Option<String>,
+    pub container_user: Option<String>,
+    pub compose: Option<Compose>,
+    pub forward_ports: Vec<u16>,
+    pub open_mode: OpenMode,
+    pub workspace_mount: Option<Mount>,
+    pub mounts: Vec<Mount>,
+    pub host_requirements: HostRequirements,
+    pub summary: Option<String>,
+    pub features: Vec<String>,
+    /// Args for code from the repo's .devcontainer/cope.json.
+    pub code_args: Vec<String>,
+}
+
+impl DirProperties {
+    /// remoteUser and containerUser as declared, for diagnosing permission
+    /// problems.  Unset ones fall back to the next, and then to the image's
+    /// user.
+    fn users(&self) -> String {
+        let unset = |u: &Option<String>| u.clone().unwrap_or_else(|| "(unset)".into());
+        format!(
+            "remoteUser {}, containerUser {}",
+            unset(&self.remote_user),
+            unset(&self.container_user)
+        )
+    }
+
+    /// Where the host path pth (inside root) is in the container.  A
+    /// workspaceMount says where the repo really is; otherwise it is in
+    /// the workspace folder.
+    fn container_path(&self, root: &Path, pth: &Path) -> Option<String> {
+        self.workspace_mount
+            .as_ref()
+            .and_then(|m| m.container_path(pth))
+            .or_else(|| container_path(root, &self.folder, pth))
+    }
+
+    /// Where the container path pth is on the host, for the repo at root:
+    /// the reverse of container_path, and of the bind mounts.
+    fn host_path(&self, root: &Path, pth: &Path) -> Option<PathBuf> {
+        self.workspace_mount
+            .as_ref()
+            .and_then(|m| m.host_path(pth))
+            .or_else(|| {
+                pth.strip_prefix(&self.folder)
+                    .ok()
+                    .map(|rel| root.join(rel))
+            })
+            .or_else(|| self.mounts.iter().find_map(|m| m.host_path(pth)))
+    }
+}
+
+/// The host root and container path of a dev-container URI, such as
+/// `vscode-remote://dev-container+<hex>/workspaces/repo/a.rs`, with or
+/// without --file-uri= or --folder-uri= in front.
+fn parse_devcontainer_uri(arg: &str) -> Option<(PathBuf, String)> {
+    let uri = arg
+        .strip_prefix("--file-uri=")
+        .or_else(|| arg.strip_prefix("--folder-uri="))
+        .unwrap_or(arg);
+    let rest = uri.strip_prefix("vscode-remote://dev-container+")?;
+    let (authority, path) = rest.split_at(rest.find('/').unwrap_or(rest.len()));
+    let id = String::from_utf8(string_utils::unhex(authority.split('@').next()?)?).ok()?;
+    let host = if id.starts_with('{') {
+        let v: serde_jsonc::Value = serde_jsonc::from_str(&id).ok()?;
+        v["hostPath"].as_str()?.to_string()
+    } else {
+        id
+    };
+    Some((PathBuf::from(host), string_utils::decode_uri_path(path)))
+}
+
+/// `cope reverse <container path or URI>`: print where a path in the
+/// current repo's container (or in the container a dev-container URI
+/// names) is on the host.  Relative paths are from the workspace folder.
+fn reverse(args: &[OsString]) {
+    let Some(arg) = args.first().map(|a| a.to_string_lossy()) else {
+        eprintln!(
+            "{}",
+            t!("usage", usage = "cope reverse <container path or URI>")
+        );
+        std::process::exit(1);
+    };
+    let (start, container) = parse_devcontainer_uri(&arg)
+        .unwrap_or_else(|| (normalize(OsStr::new(".")), arg.to_string()));
+    let mut resolver = Resolver::from_config();
+    let Some((root, props)) = resolver.resolve(&start) else {
+        eprintln!("{}", t!("no-devcontainer", path = start.display()));
+        std::process::exit(1);
+    };
+    let container = Path::new(&props.folder).join(container);
+    match props.host_path(&root, &container) {
+        Some(pth) => println!("{}", pth.display()),
+        None => {
+            eprintln!("{}", t!("not-on-host", path = container.display()));
+            std::process::exit(1);
+        }
+    }
 }
 
-fn read_json(file_name: PathBuf) -> JsonResults {
-    let json_data = fs::read_to_string(file_name.clone())
-        .unwrap_or_else(|er| panic!("Error reading file {file_name:?} {er}"));
-    let dev_container = serde_jsonc::from_str(&json_data)
-        .unwrap_or_else(|er| panic!("Error parsing JSON {file_name:?} {er}"));
-    JsonResults {
-        file_name: file_name,
-        dev_container,
+/// Read a config, as JSON with comments, or failing that, as JSON5.  One
+/// that can't be read or parsed is skipped with a warning, so that a broken
+/// experimental config doesn't stop the others from opening.
+fn read_json(file_name: PathBuf) -> Option<JsonResults> {
+    let _span = Span::enter(format!("parse {}", file_name.display()));
+    let parsed = file_utils::read_text(&file_name)
+        .map_err(|er| er.to_string())
+        .and_then(|json_data| parse_config(&json_data));
+    match parsed {
+        Ok(dev_container) => Some(JsonResults {
+            file_name,
+            dev_container,
+        }),
+        Err(er) => {
+            eprintln!(
+                "{}",
+                t!("bad-devcontainer", file = file_name.display(), error = er)
+            );
+            None
+        }
     }
 }
 
-/// Ask on stderr which of the given items is desired
-fn choose<'a>(matches: &'a Vec<JsonResults>, root: &Path) -> &'a JsonResults {
+/// Parse the text of a config as JSON with comments, or failing that, as
+/// JSON5.  The error is the one from the first try.
+fn parse_config<T: DeserializeOwned>(json_data: &str) -> Result<T, String> {
+    serde_jsonc::from_str(json_data).or_else(|er| {
+        serde_jsonc::from_str(&json5::to_json(json_data))
+            .map_err(|_| describe_error(&er, json_data))
+    })
+}
+
+/// The parser's message, followed by the text around where it went wrong,
+/// with a caret under the column.
+fn describe_error(er: &serde_jsonc::Error, json_data: &str) -> String {
+    let Some(line) = er
+        .line()
+        .checked_sub(1)
+        .and_then(|n| json_data.lines().nth(n))
+    else {
+        return er.to_string();
+    };
+    let chars: Vec<char> = line.chars().collect();
+    let col = er.column().saturating_sub(1).min(chars.len());
+    let start = col.saturating_sub(40);
+    let end = (col + 40).min(chars.len());
+    let snippet: String = chars[start..end].iter().collect();
+    format!(
+        "{er}\n    {}\n    {}^",
+        snippet.trim_end(),
+        " ".repeat(col - start)
+    )
+}
+
+/// Ask on stderr which of the given items is desired, or with a dialog if
+/// there is no terminal.
+fn choose<'a>(matches: &'a [JsonResults], root: &Path) -> &'a JsonResults {
     // See https://github.com/console-rs/console/pull/173 for testing
-    let items = matches.iter().map(|m| {
-        format!(
-            "{} ({:?})",
-            m.dev_container
-                .name
-                .clone()
-                .unwrap_or("<no name>".to_string()),
-            m.file_name.strip_prefix(root).expect("Relative to root")
-        )
-    });
+    let items: Vec<String> = matches
+        .iter()
+        .map(|m| {
+            let name = m.dev_container.label(&m.file_name);
+            let mut label = format!(
+                "{name} ({:?})",
+                // Generated configs live outside the repo.
+                m.file_name.strip_prefix(root).unwrap_or(&m.file_name)
+            );
+            if let Some(user) = m.dev_container.user() {
+                label = format!("{label} as {user}");
+            }
+            // Unnamed configs already have the image in their name.
+            match m
+                .dev_container
+                .summary()
+                .filter(|s| !name.contains(s.as_str()))
+            {
+                Some(summary) => format!("{label} [{summary}]"),
+                None => label,
+            }
+        })
+        .collect();
 
+    if gui::active() {
+        let Some(selection) = gui::select(&t!("choose-container"), &items) else {
+            eprintln!("{}", t!("no-container-chosen"));
+            std::process::exit(1);
+        };
+        return &matches[selection];
+    }
     let selection = Select::new()
-        .with_prompt("Which container?")
-        .items(items)
+        .with_prompt(t!("choose-container"))
+        .items(&items)
         .default(0)
         .interact()
         .expect("Selection failed");
     &matches[selection]
 }
 
+/// The JSON that the Dev Containers extension hex-encodes into the URI.  It
+/// is incredibly picky: all of the weird bits need to be there, in this
+/// order (the order of the fields here), with no additional whitespace.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ContainerId<'a> {
+    host_path: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    local_docker: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    settings: Option<&'a engine::Settings>,
+    config_file: ConfigFileUri<'a>,
+}
+
+/// A host path, the way VS Code's `URI.file` takes it apart.  Paths on this
+/// machine are Unix paths, and come out as they went in.  Windows paths
+/// (`C:\src\x`, or `\\server\share\x` on a network share), which show up
+/// through WSL interop, get the lower-case drive letter and backslashes
+/// that VS Code on Windows puts in fsPath, and forward slashes in path.
+#[derive(Debug, PartialEq)]
+struct HostFile {
+    fs_path: String,
+    authority: String,
+    path: String,
+}
+
+impl HostFile {
+    fn new(p: &str) -> HostFile {
+        let bytes = p.as_bytes();
+        let drive = bytes.len() >= 3
+            && bytes[0].is_ascii_alphabetic()
+            && bytes[1] == b':'
+            && (bytes[2] == b'\\' || bytes[2] == b'/');
+        if drive {
+            let rest = &p[2..];
+            let letter = p[..1].to_ascii_lowercase();
+            return HostFile {
+                fs_path: format!("{letter}:{}", rest.replace('/', "\\")),
+                authority: String::new(),
+                path: format!("/{letter}:{}", rest.replace('\\', "/")),
+            };
+        }
+        if let Some(unc) = p.strip_prefix("\\\\") {
+            let (server, share) = unc.split_once('\\').unwrap_or((unc, ""));
+            return HostFile {
+                fs_path: p.into(),
+                authority: server.to_ascii_lowercase(),
+                path: format!("/{}", share.replace('\\', "/")),
+            };
+        }
+        HostFile {
+            fs_path: p.into(),
+            authority: String::new(),
+            path: p.into(),
+        }
+    }
+
+    /// The whole URI, as `URI.toString` writes it.
+    fn external(&self) -> String {
+        format!("file://{}{}", self.authority, encode_uri_path(&self.path))
+    }
+}
+
+/// A VS Code `URI` object, as it is serialized.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ConfigFileUri<'a> {
+    #[serde(rename = "$mid")]
+    mid: u8,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    fs_path: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    external: Option<String>,
+    path: &'a str,
+    scheme: &'static str,
+}
+
 /// Convert the given config file into the internal format that the Dev
 /// Containers extension expects.  This is an undocumented interface, so I
 /// expect it to be brittle. To find examples for this, open the Developer
@@ -113,50 +506,99 @@ fn choose<'a>(matches: &'a Vec<JsonResults>, root: &Path) -> &'a JsonResults {
 /// console, and enter: `window.vscode.context.configuration().workspace.uri`.
 /// Fiddle around with the results to find value of the _formatted field, then
 /// hex decode.
-pub fn container_id(root: &Path, chosen: &Path) -> String {
+pub fn container_id(root: &Path, chosen: &Path, variant: UriVariant) -> String {
     // Maintain compatibility with the URI that the `devcontainer` CLI uses,
-    // if we are just opening the default config.
-    if chosen.eq(&root.join(DEVCONTAINER_DIR).join(CONFIG_FILE)) {
-        root.to_string_lossy().into()
-    } else {
-        // This string is incredibly picky.  It just looks like JSON but it
-        // apparently isn't.  All of the weird bits need to be there, in this
-        // order, with no additional whitespace.
-        format!(
-            r#"{{"hostPath":{root:?},"localDocker":false,"settings":{{"context":"desktop-linux"}},"configFile":{{"$mid":1,"fsPath":{chosen:?},"external":"file://{}","path":{chosen:?},"scheme":"file"}}}}"#,
-            chosen.to_string_lossy()
-        )
+    // if we are just opening a config in one of the spec's default places.
+    if chosen.eq(&root.join(DEVCONTAINER_DIR).join(CONFIG_FILE))
+        || chosen.eq(&root.join(".devcontainer.json"))
+    {
+        return HostFile::new(&root.to_string_lossy()).fs_path;
     }
+    let root = HostFile::new(&root.to_string_lossy());
+    let chosen = HostFile::new(&chosen.to_string_lossy());
+    let id = if variant == UriVariant::OpenRemote {
+        // The Open Remote extension only looks at the paths.
+        ContainerId {
+            host_path: &root.fs_path,
+            local_docker: None,
+            settings: None,
+            config_file: ConfigFileUri {
+                mid: 1,
+                fs_path: None,
+                external: None,
+                path: &chosen.path,
+                scheme: "file",
+            },
+        }
+    } else {
+        ContainerId {
+            host_path: &root.fs_path,
+            local_docker: Some(engine::local_docker()),
+            settings: Some(engine::settings()),
+            config_file: ConfigFileUri {
+                mid: 1,
+                fs_path: Some(&chosen.fs_path),
+                external: Some(chosen.external()),
+                path: &chosen.path,
+                scheme: "file",
+            },
+        }
+    };
+    serde_jsonc::to_string(&id).expect("Serializing container id")
 }
 
-/// Compute the hex bits for the devcontainer URI, as well as the name of the 
+/// Compute the hex bits for the devcontainer URI, as well as the name of the
 /// project folder *inside* the container.
-fn dir_properties(root: &Path) -> Option<DirProperties> {
-    let matches: Vec<JsonResults> = files_matching(root, CONFIG_FILE).map(read_json).collect();
+fn dir_properties(root: &Path, configs: Vec<PathBuf>) -> Option<DirProperties> {
+    let matches: Vec<JsonResults> = configs.into_iter().filter_map(read_json).collect();
 
     let chosen = match matches.len() {
         0 => {
-            // No devcontainer.json found in .devcontainer/
+            // No configs found by any strategy.
             return None;
         }
         1 => {
             // Only one.  The most common case.
             &matches[0]
         }
-        _ => choose(&matches, &root),
+        _ => match matches
+            .iter()
+            .find(|m| m.dev_container.customizations.cope.default)
+        {
+            Some(m) => m,
+            None => choose(&matches, root),
+        },
     };
 
-    // Remove .devcontainer
-    let mut root = root.to_path_buf();
-    root.pop();
-    let id = container_id(&root, &chosen.file_name);
-    debug_arg(env::var("COPE_VERBOSE").is_ok(), &id);
+    let verbose = env::var("COPE_VERBOSE").is_ok();
+    let id = container_id(root, &chosen.file_name, editor::current_variant());
+    debug_arg(verbose, &id);
     let hex = hex(id.as_bytes());
 
-    let folder = chosen
-        .dev_container
+    let vars = variables::Context {
+        local_workspace_folder: root,
+        config_file: Some(&chosen.file_name),
+        container_workspace_folder: None,
+        local_env: |name| env::var(name).ok(),
+    };
+    // Compose configs without a workspaceFolder get wherever the service
+    // mounts the repo.
+    let compose_folder = || {
+        let spec = chosen.dev_container.docker_compose_file.as_ref()?;
+        let service = chosen.dev_container.service.as_deref()?;
+        compose::workspace_folder(
+            root,
+            &compose::compose_files(&chosen.file_name, spec),
+            service,
+        )
+    };
+    let project = config::read_project(root);
+    let folder = project
         .workspace_folder
-        .clone()
+        .as_deref()
+        .or(chosen.dev_container.workspace_folder.as_deref())
+        .map(|f| variables::substitute(f, &vars))
+        .or_else(compose_folder)
         .unwrap_or_else(|| {
             format!(
                 "/workspaces/{}",
@@ -166,160 +608,2348 @@ fn dir_properties(root: &Path) -> Option<DirProperties> {
                     .to_string_lossy()
             )
         });
-    Some(DirProperties { hex, folder })
+    let props = DirProperties {
+        hex,
+        folder,
+        config_file: chosen.file_name.clone(),
+        user: chosen
+            .dev_container
+            .user()
+            .map(|u| variables::substitute(u, &vars)),
+        remote_user: chosen
+            .dev_container
+            .remote_user
+            .as_deref()
+            .map(|u| variables::substitute(u, &vars)),
+        container_user: chosen
+            .dev_container
+            .container_user
+            .as_deref()
+            .map(|u| variables::substitute(u, &vars)),
+        compose: chosen
+            .dev_container
+            .docker_compose_file
+            .as_ref()
+            .map(|spec| {
+                Compose::new(
+                    &chosen.file_name,
+                    spec,
+                    chosen.dev_container.service.clone(),
+                    env::var("COMPOSE_PROJECT_NAME").ok(),
+                )
+            }),
+        forward_ports: chosen
+            .dev_container
+            .forward_ports
+            .iter()
+            .filter_map(PortSpec::port)
+            .collect(),
+        open_mode: chosen
+            .dev_container
+            .customizations
+            .cope
+            .open_mode
+            .unwrap_or(OpenMode::Path),
+        // The override wins over workspaceMount, too, since it says where
+        // the repo really is.
+        workspace_mount: chosen
+            .dev_container
+            .workspace_mount
+            .as_deref()
+            .filter(|_| project.workspace_folder.is_none())
+            .and_then(|m| mounts::parse(&variables::substitute(m, &vars))),
+        mounts: chosen
+            .dev_container
+            .mounts
+            .iter()
+            .filter_map(|m| m.to_mount(|s| variables::substitute(s, &vars)))
+            .collect(),
+        host_requirements: chosen.dev_container.host_requirements.clone(),
+        summary: chosen.dev_container.summary(),
+        features: chosen.dev_container.features.keys().cloned().collect(),
+        code_args: project.args,
+    };
+    if verbose {
+        eprintln!("\n{}", props.users());
+    }
+    Some(props)
+}
+
+/// Finds the devcontainer config for paths, using the discovery
+/// strategies in order.
+struct Resolver {
+    strategies: Vec<Box<dyn Strategy>>,
+
+    // Cache so we don't call `choose` twice for the same repo root.
+    // The perf is unlikely to matter in practice, but the UX of having to
+    // answer the same question twice is bad.
+    cache: BTreeMap<PathBuf, Option<DirProperties>>,
+
+    /// The WSL distribution cope is running in, if any.  Paths without a
+    /// devcontainer are opened in it, since the editor is on the Windows
+    /// side.
+    wsl: Option<String>,
+
+    /// Repos to open in a codespace, with the codespace's name (or
+    /// "auto"), from the config file.
+    codespaces: BTreeMap<PathBuf, String>,
+
+    /// Look for a codespace for every repo, for `--cope-codespace`.
+    find_codespaces: bool,
+
+    // The codespaces that were found, so `gh` is only asked once per repo.
+    found_codespaces: BTreeMap<PathBuf, Option<(String, String)>>,
+
+    /// Repos to open through a tunnel, from the config file.
+    tunnel_projects: BTreeMap<PathBuf, TunnelProject>,
+
+    /// Repos whose devcontainers run on an SSH host, from the config file.
+    ssh_projects: BTreeMap<PathBuf, SshProject>,
+
+    /// cope is running in a devcontainer, such as in its integrated
+    /// terminal.
+    in_container: bool,
+}
+
+impl Resolver {
+    fn new(strategies: Vec<Box<dyn Strategy>>) -> Resolver {
+        Resolver {
+            strategies,
+            cache: BTreeMap::new(),
+            wsl: None,
+            codespaces: BTreeMap::new(),
+            find_codespaces: false,
+            found_codespaces: BTreeMap::new(),
+            tunnel_projects: BTreeMap::new(),
+            ssh_projects: BTreeMap::new(),
+            in_container: false,
+        }
+    }
+
+    /// Use the strategies from the config file, or the defaults.
+    fn from_config() -> Resolver {
+        let cfg = config::load();
+        let names = cfg.discovery.unwrap_or_else(discovery::default_names);
+        let dir = discovery::DevcontainerDir {
+            dir: DEVCONTAINER_DIR.into(),
+            depth: cfg.discovery_depth.unwrap_or(discovery::DEFAULT_DEPTH),
+            ignore: cfg.discovery_ignore,
+        };
+        let mut res = Resolver::new(discovery::from_names(&names, &dir));
+        res.in_container = in_devcontainer(env::var("REMOTE_CONTAINERS").ok().as_deref());
+        // wsl+ URIs only mean something to VS Code running on Windows; one
+        // installed in the distribution opens the paths itself.
+        res.wsl = wsl_distro(
+            env::var("WSL_DISTRO_NAME").ok(),
+            Path::new("/proc/sys/fs/binfmt_misc/WSLInterop"),
+        )
+        .filter(|_| {
+            editor::locate(
+                editor::bin(),
+                env::var_os("PATH").as_deref(),
+                env::var_os("HOME"),
+            )
+            .is_some_and(|bin| editor::is_windows_side(&bin))
+        });
+        res.codespaces = cfg
+            .codespaces
+            .into_iter()
+            .map(|(repo, name)| (config::expand_home(&repo, env::var_os("HOME")), name))
+            .collect();
+        res.tunnel_projects = cfg
+            .tunnel_projects
+            .into_iter()
+            .map(|(repo, p)| (config::expand_home(&repo, env::var_os("HOME")), p))
+            .collect();
+        res.ssh_projects = cfg
+            .ssh_projects
+            .into_iter()
+            .map(|(repo, p)| (config::expand_home(&repo, env::var_os("HOME")), p))
+            .collect();
+        res
+    }
+
+    /// Find the repo root that holds a devcontainer for this path, along
+    /// with the properties of the chosen config.
+    fn resolve(&mut self, pth: &Path) -> Option<(PathBuf, &DirProperties)> {
+        let _span = Span::enter("discover");
+        let (root, configs) = discovery::find_root(pth, &self.strategies, Some(".git"))?;
+        let cached = self
+            .cache
+            .entry(root.clone())
+            .or_insert_with(|| dir_properties(&root, configs));
+
+        let props = cached.as_ref()?;
+        Some((root, props))
+    }
+
+    /// The git repo holding pth, the codespace it should be opened in, if
+    /// it has one, and the name of the repo's checkout there.
+    fn codespace(&mut self, pth: &Path) -> Option<(PathBuf, String, String)> {
+        if self.codespaces.is_empty() && !self.find_codespaces {
+            return None;
+        }
+        let root = codespaces::git_root(pth)?;
+        if let Some(found) = self.found_codespaces.get(&root) {
+            return found.clone().map(|(name, repo)| (root, name, repo));
+        }
+        let found = match self.codespaces.get(&root).map(String::as_str) {
+            Some(codespaces::AUTO) => codespaces::detect(&root),
+            Some(name) => Some(name.into()),
+            None if self.find_codespaces => codespaces::detect(&root),
+            None => None,
+        };
+        let found = found.map(|name| {
+            (
+                name,
+                codespaces::repo_name(codespaces::origin_slug(&root).as_deref(), &root),
+            )
+        });
+        self.found_codespaces.insert(root.clone(), found.clone());
+        found.map(|(name, repo)| (root, name, repo))
+    }
+
+    /// For a path outside any repo, a devcontainer of one of the repos
+    /// named in this run that has it in one of its `mounts`, along with
+    /// where it is in the container.  process_args resolves every repo first,
+    /// so the order of the arguments doesn't matter.  If several repos mount
+    /// the path, the first by path wins.
+    fn mounted(&self, pth: &Path) -> Option<(&DirProperties, String)> {
+        self.cache.values().flatten().find_map(|props| {
+            let inside = props.mounts.iter().find_map(|m| m.container_path(pth))?;
+            Some((props, inside))
+        })
+    }
+
+    /// The repos that were opened in a devcontainer, with their configs.
+    fn opened(&self) -> impl Iterator<Item = (&PathBuf, &DirProperties)> {
+        self.cache
+            .iter()
+            .filter_map(|(root, props)| Some((root, props.as_ref()?)))
+    }
+
+    /// The repo roots that were opened in a devcontainer.
+    fn roots(&self) -> Vec<PathBuf> {
+        self.cache
+            .iter()
+            .filter(|(_, props)| props.is_some())
+            .map(|(root, _)| root.clone())
+            .collect()
+    }
+}
+
+impl Default for Resolver {
+    fn default() -> Resolver {
+        Resolver::new(discovery::from_names(
+            &discovery::default_names(),
+            &discovery::DevcontainerDir::new(DEVCONTAINER_DIR),
+        ))
+    }
+}
+
+/// The repo in projects that holds pth, if any.  The innermost one wins.
+fn innermost<'a, T>(
+    projects: &'a BTreeMap<PathBuf, T>,
+    pth: &Path,
+) -> Option<(&'a PathBuf, &'a T)> {
+    projects
+        .iter()
+        .filter(|(root, _)| pth.starts_with(root))
+        .max_by_key(|(root, _)| root.components().count())
+}
+
+/// The argument that opens rel, inside a repo at remote_root on an SSH
+/// host, in the devcontainer that runs there.  This is the chained
+/// authority of the extension's "Dev Container on SSH host".
+pub fn ssh_devcontainer_uri(
+    host: &str,
+    remote_root: &str,
+    folder: &str,
+    rel: &Path,
+    is_dir: bool,
+) -> String {
+    let mut pth = PathBuf::from(folder);
+    if !rel.as_os_str().is_empty() {
+        pth.push(rel);
+    }
+    format!(
+        "--{}-uri=vscode-remote://dev-container+{}@ssh-remote+{host}{}",
+        if is_dir { "folder" } else { "file" },
+        hex(remote_root.as_bytes()),
+        encode_uri_path(&pth.to_string_lossy())
+    )
+}
+
+/// Where the host path pth (inside root) lives in a container whose
+/// workspace folder is `folder`.  None if pth isn't inside root.
+fn container_path(root: &Path, folder: &str, pth: &Path) -> Option<String> {
+    let rel = pth.strip_prefix(root).ok()?;
+    if rel.as_os_str().is_empty() {
+        Some(folder.to_string())
+    } else {
+        Some(format!(
+            "{}/{}",
+            folder.trim_end_matches('/'),
+            rel.display()
+        ))
+    }
 }
 
 /// If this is a file in a directory that has a devcontainer, convert it to
 /// a vscode-remote: URI.  If not, just convert to a CString.
-fn to_devcontainer_uri(
-    arg: &OsStr,
-    dir: &str,
-    cache: &mut BTreeMap<PathBuf, Option<DirProperties>>,
-) -> CString {
+fn to_devcontainer_uri(arg: &OsStr, resolver: &mut Resolver) -> CString {
     let pth = normalize(arg);
-    if let Some(mut root) = find_dir_up(
-        &pth,
-        FindOptions {
-            dir,
-            stop: Some(".git"),
-        },
-    ) {
-        let cached = cache
-            .entry(root.clone())
-            .or_insert_with(|| dir_properties(&root));
-
-        if let Some(props) = cached {
-            root.pop();
-            return CString::new(format!(
-                "--{}-uri=vscode-remote://dev-container+{}{}/{}",
-                if pth.is_dir() { "folder" } else { "file" },
-                props.hex,
-                props.folder,
-                pth.strip_prefix(root).expect("stripping prefix").display()
-            ))
+    if let Some((root, project)) = innermost(&resolver.ssh_projects, &pth) {
+        let (root, project) = (root.clone(), project.clone());
+        let remote_root = project.path.unwrap_or_else(|| root.display().to_string());
+        // A local checkout with a config knows the workspace folder.
+        let folder = match resolver.resolve(&pth) {
+            Some((r, props)) if r == root => props.folder.clone(),
+            _ => format!(
+                "/workspaces/{}",
+                remote_root.rsplit('/').next().unwrap_or_default()
+            ),
+        };
+        let rel = pth.strip_prefix(&root).expect("stripping prefix");
+        return CString::new(ssh_devcontainer_uri(
+            &project.host,
+            &remote_root,
+            &folder,
+            rel,
+            pth.is_dir(),
+        ))
+        .expect("Bad CString from format");
+    }
+    if let Some((root, project)) = innermost(&resolver.tunnel_projects, &pth) {
+        let remote_root = project
+            .path
+            .clone()
+            .unwrap_or_else(|| root.display().to_string());
+        let rel = pth.strip_prefix(root).expect("stripping prefix");
+        return CString::new(tunnel::uri(
+            &project.tunnel,
+            &remote_root,
+            rel,
+            pth.is_dir(),
+        ))
+        .expect("Bad CString from format");
+    }
+    if let Some((root, name, repo_name)) = resolver.codespace(&pth) {
+        let rel = pth.strip_prefix(&root).expect("stripping prefix");
+        return CString::new(codespaces::uri(&name, &repo_name, rel, pth.is_dir()))
             .expect("Bad CString from format");
+    }
+    let in_container = resolver.in_container;
+    if let Some((root, props)) = resolver.resolve(&pth) {
+        // Already in this repo's container, where code opens paths itself.
+        if in_container && root == Path::new(&props.folder) {
+            return to_cstring(arg.into());
         }
+        let pth = match props.open_mode {
+            OpenMode::Folder => root.clone(),
+            OpenMode::Path => pth,
+        };
+        let inside = props
+            .container_path(&root, &pth)
+            .expect("Path inside the repo");
+        return CString::new(format!(
+            "--{}-uri=vscode-remote://dev-container+{}{}",
+            if pth.is_dir() { "folder" } else { "file" },
+            props.hex,
+            inside
+        ))
+        .expect("Bad CString from format");
     }
 
-    // No .devcontainer/ in the parents, or no devcontainer.json in
-    // .devcontainer
+    // No devcontainer configs in the parents.
+    if let Some((props, inside)) = resolver.mounted(&pth) {
+        return CString::new(format!(
+            "--{}-uri=vscode-remote://dev-container+{}{inside}",
+            if pth.is_dir() { "folder" } else { "file" },
+            props.hex
+        ))
+        .expect("Bad CString from format");
+    }
+    if let Some(distro) = &resolver.wsl {
+        return CString::new(wsl_uri(distro, &pth)).expect("Bad CString from format");
+    }
     to_cstring(arg.into())
 }
 
+/// The Dev Containers extension sets REMOTE_CONTAINERS in the containers
+/// it starts.
+fn in_devcontainer(remote_containers: Option<&str>) -> bool {
+    remote_containers == Some("true")
+}
+
+/// Split the `:line` or `:line:column` that --goto allows off the end of a
+/// file name.  The position is empty if there isn't one.
+fn split_position(arg: &str) -> (&str, &str) {
+    let mut end = arg.len();
+    for _ in 0..2 {
+        match arg[..end].rsplit_once(':') {
+            Some((file, n)) if !n.is_empty() && n.bytes().all(|c| c.is_ascii_digit()) => {
+                end = file.len()
+            }
+            _ => break,
+        }
+    }
+    arg.split_at(end)
+}
+
+/// to_devcontainer_uri for a --goto target, keeping its position on the
+/// end of a file URI.  A file that isn't rewritten is passed through as it
+/// was, position and all.
+fn goto_uri(arg: &str, resolver: &mut Resolver) -> CString {
+    let (file, position) = split_position(arg);
+    let uri = to_devcontainer_uri(OsStr::new(file), resolver);
+    if uri.as_bytes() == file.as_bytes() {
+        return to_cstring(arg.into());
+    }
+    if uri.as_bytes().starts_with(b"--file-uri=") {
+        let mut bytes = uri.into_bytes();
+        bytes.extend_from_slice(position.as_bytes());
+        return CString::new(bytes).expect("Bad CString from format");
+    }
+    // Opening the folder, so there's no file to go to.
+    uri
+}
+
+/// The files given to --diff or --merge, as file URIs, if they are all in
+/// the same container.  code shows them in a single window, so files in
+/// different containers, or outside of any, are passed through unchanged,
+/// with a warning if any of them were in a container.
+fn same_container_uris(
+    flag: &str,
+    files: &[OsString],
+    resolver: &mut Resolver,
+) -> Option<Vec<CString>> {
+    let in_container = resolver.in_container;
+    let mut found: Vec<Option<(String, CString)>> = Vec::with_capacity(files.len());
+    for f in files {
+        let pth = normalize(f);
+        let Some((root, props)) = resolver.resolve(&pth) else {
+            found.push(None);
+            continue;
+        };
+        if in_container && root == Path::new(&props.folder) {
+            return None;
+        }
+        let inside = props
+            .container_path(&root, &pth)
+            .expect("Path inside the repo");
+        let uri = CString::new(format!(
+            "--file-uri=vscode-remote://dev-container+{}{inside}",
+            props.hex
+        ))
+        .expect("Bad CString from format");
+        found.push(Some((props.hex.clone(), uri)));
+    }
+    let first = found[0].as_ref().map(|(hex, _)| hex);
+    if first.is_some()
+        && found
+            .iter()
+            .all(|f| f.as_ref().map(|(hex, _)| hex) == first)
+    {
+        return Some(
+            found
+                .into_iter()
+                .map(|f| f.expect("All in the container").1)
+                .collect(),
+        );
+    }
+    if found.iter().any(Option::is_some) {
+        eprintln!("{}", t!("mixed-containers", flag = flag));
+    }
+    None
+}
+
+/// The folder given to --add, as a folder URI, if it is in a container.
+/// Folders outside of any are passed through unchanged.
+fn add_uri(folder: &OsStr, resolver: &mut Resolver) -> Option<CString> {
+    let in_container = resolver.in_container;
+    let pth = normalize(folder);
+    let (root, props) = resolver.resolve(&pth)?;
+    if in_container && root == Path::new(&props.folder) {
+        return None;
+    }
+    let inside = props
+        .container_path(&root, &pth)
+        .expect("Path inside the repo");
+    Some(
+        CString::new(format!(
+            "--folder-uri=vscode-remote://dev-container+{}{inside}",
+            props.hex
+        ))
+        .expect("Bad CString from format"),
+    )
+}
+
+/// The parameters that follow flag, from flags::param_size.  If there aren't
+/// enough, `code` will complain for us, so no need to check.
+fn flag_params(
+    flag: &str,
+    it: &mut impl Iterator<Item = OsString>,
+    resolver: &mut Resolver,
+) -> Vec<CString> {
+    let sz = flags::param_size(flag).unwrap_or(0);
+    let params: Vec<OsString> = it.take(sz).collect();
+    if matches!(flag, "--diff" | "-d" | "--merge" | "-m")
+        && params.len() == sz
+        && let Some(uris) = same_container_uris(flag, &params, resolver)
+    {
+        return uris;
+    }
+    if matches!(flag, "--add" | "-a")
+        && let [folder] = params.as_slice()
+        && let Some(uri) = add_uri(folder, resolver)
+    {
+        return vec![uri];
+    }
+    params.into_iter().map(to_cstring).collect()
+}
+
+/// The WSL distribution, from WSL_DISTRO_NAME, which is only trusted if
+/// the interop file shows that this really is WSL.
+fn wsl_distro(name: Option<String>, interop: &Path) -> Option<String> {
+    name.filter(|n| !n.is_empty() && interop.exists())
+}
+
+/// The argument that opens pth in a WSL distribution from Windows.  This
+/// includes `/mnt/c/...` paths, which are opened through the distribution
+/// too.
+pub fn wsl_uri(distro: &str, pth: &Path) -> String {
+    format!(
+        "--{}-uri=vscode-remote://wsl+{distro}{}",
+        if pth.is_dir() { "folder" } else { "file" },
+        encode_uri_path(&pth.to_string_lossy())
+    )
+}
+
+/// A --file-uri= or --folder-uri= that the user gave with a relative path
+/// (such as `--folder-uri=.`) or a `file:` URI, which code would take
+/// literally.  The path is made absolute, and opened in its container if it
+/// has one; otherwise it becomes an absolute `file://` URI.  Other URIs are
+/// None, and passed through.
+fn user_uri(arg: &str, resolver: &mut Resolver) -> Option<CString> {
+    let (flag, value) = arg.split_once('=')?;
+    if flag != "--file-uri" && flag != "--folder-uri" {
+        return None;
+    }
+    let path = match value
+        .strip_prefix("file://")
+        .or_else(|| value.strip_prefix("file:"))
+    {
+        Some(p) => string_utils::decode_uri_path(p),
+        None if uri_scheme(value).is_none() => value.to_string(),
+        None => return None,
+    };
+    let pth = normalize(OsStr::new(&path));
+    let rewritten = to_devcontainer_uri(pth.as_os_str(), resolver);
+    let uri = match rewritten.to_str().ok().and_then(|r| r.split_once('=')) {
+        // Keep the kind of URI the user asked for.
+        Some((_, uri)) if rewritten.as_bytes() != pth.as_os_str().as_bytes() => uri.to_string(),
+        _ => format!("file://{}", encode_uri_path(&pth.to_string_lossy())),
+    };
+    Some(CString::new(format!("{flag}={uri}")).expect("Bad CString from format"))
+}
+
+/// code makes a new file for a path that doesn't exist, which is what was
+/// wanted, unless it was a typo.  If a file next to it has nearly the same
+/// name, say so.
+fn warn_typo(arg: &str) {
+    let pth = Path::new(arg);
+    if pth.exists() {
+        return;
+    }
+    if let Some(name) = file_utils::suggest(&normalize(pth.as_os_str())) {
+        eprintln!(
+            "{}",
+            t!(
+                "did-you-mean",
+                path = arg,
+                suggestion = pth.with_file_name(name).display()
+            )
+        );
+    }
+}
+
+/// Does args have code's own --remote (or --remote=) before any `--`?
+fn has_remote(args: &[OsString]) -> bool {
+    args.iter()
+        .take_while(|a| *a != "--")
+        .any(|a| a == "--remote" || a.to_str().is_some_and(|s| s.starts_with("--remote=")))
+}
+
 /// For each arg, if it might be a file name, see if the file name needs to be
 /// converted to a URI.  Otherwise pass the arg through.
-fn process_args(args: impl ExactSizeIterator<Item = OsString>) -> Vec<CString> {
+/// The resolver remembers every repo root that was seen.
+fn process_args(
+    args: impl ExactSizeIterator<Item = OsString>,
+    resolver: &mut Resolver,
+) -> Vec<CString> {
     let mut args: Vec<OsString> = args.collect();
     if args.len() == 1 {
-        // This is the default in the code CLI, but we need a chance to 
+        // This is the default in the code CLI, but we need a chance to
         // permute it into a file URI.
         args.push(OsString::from("."));
     }
     let mut result: Vec<CString> = Vec::with_capacity(args.len());
     let mut it = args.into_iter();
+    // After --goto, every file name can end in :line or :line:column.
+    let mut goto = false;
 
     result.push(CODE.to_owned());
     it.next().expect("Always expect 'cope' as the 0th param");
+    if has_remote(it.as_slice()) {
+        // The user already picked where code opens things, so the paths
+        // are theirs to name.
+        result.extend(it.map(to_cstring));
+        return result;
+    }
+
+    resolve_repos(it.as_slice(), resolver);
 
-    // Cache so we don't call `choose` twice for the same directory.
-    // The perf is unlikely to matter in practice, but the UX of having to 
-    // answer the same question twice is bad.
-    let mut cache: BTreeMap<PathBuf, Option<DirProperties>> = BTreeMap::new();
     while let Some(a) = it.next() {
         if let Some(b) = a.clone().to_str() {
-            if let Some(sz) = PARAM_SIZE.get(b) {
+            if flags::param_size(b).is_some() {
+                result.push(to_cstring(a.clone()));
+                result.extend(flag_params(b, &mut it, resolver));
+            } else if let Some(sub_flags) = flags::subcommand(b) {
+                // Subcommands have flags of their own, some of which are
+                // paths, such as chat's --add-file.
                 result.push(to_cstring(a));
-                // If we don't have enough parameters, `code` will complain
-                // for us, so no need to check that we have enough.
-                result.extend(it.by_ref().take(*sz).map(to_cstring));
-            } else if TERMINAL_PARAM.contains(b) {
+                let cwd = env::current_dir().unwrap_or_default();
+                let rest = code_subcommands::rewrite(it, &sub_flags, &cwd);
+                result.extend(rest.into_iter().map(to_cstring));
+                break;
+            } else if flags::is_terminal(b) {
                 // Nothing after a terminal can be processed as a URI. If it's
                 // a filename, when the "--" is passed to code, the --file-uri
                 // param after it gets treated as a literal filename.
                 result.push(to_cstring(a));
                 result.extend(it.map(to_cstring));
                 break;
+            } else if b == "-" {
+                // code reads stdin into an untitled editor.  That doesn't
+                // need a folder, so no implicit "." is wanted, either.
+                result.push(to_cstring(a));
+            } else if b == "--goto" || b == "-g" {
+                goto = true;
+                result.push(to_cstring(a));
+            } else if let Some(uri) = user_uri(b, resolver) {
+                result.push(uri);
             } else if b.starts_with("--") {
                 // Other parameters are passed through unmodified,
                 // and they don't have follow-on parameters.
                 result.push(to_cstring(a));
+            } else if b.len() > 2
+                && b.starts_with('-')
+                && b[1..].chars().all(|c| c.is_ascii_alphabetic())
+            {
+                // Coalesced single-letter flags, such as -wa.  As in code's
+                // own parser, only the last one can take parameters.
+                let flags: Vec<String> = b[1..].chars().map(|c| format!("-{c}")).collect();
+                goto |= flags.iter().any(|f| f == "-g");
+                let last = flags[flags.len() - 1].clone();
+                result.extend(
+                    flags
+                        .into_iter()
+                        .map(|f| CString::new(f).expect("Bad CString from format")),
+                );
+                result.extend(flag_params(&last, &mut it, resolver));
             } else if b.starts_with("-") {
-                if (b.len() > 2)
-                    && (b.contains('a') || b.contains('d') || b.contains('g') || b.contains('m'))
-                {
-                    eprintln!(
-                        "cope does not handle coalesced single letter flags with parameters cleanly yet"
-                    )
-                }
                 // Single-letter parameters, skipped
                 result.push(to_cstring(a));
+            } else if uri_scheme(b).is_some() && !Path::new(b).exists() {
+                // Already a URI, such as a vscode:// link or an https://
+                // URL, which code knows what to do with.
+                result.push(to_cstring(a));
+            } else if let Some(uri) = ssh_uri(b).filter(|_| !Path::new(b).exists()) {
+                result.push(CString::new(uri).expect("Bad CString from format"));
+            } else if goto {
+                warn_typo(split_position(b).0);
+                result.push(goto_uri(b, resolver));
             } else {
                 // This must be a filename, since everything else will
                 // have been caught above.
-                result.push(to_devcontainer_uri(
-                    a.as_os_str(),
-                    DEVCONTAINER_DIR,
-                    &mut cache,
-                ));
+                warn_typo(b);
+                result.push(to_devcontainer_uri(a.as_os_str(), resolver));
             }
         } else {
             // Invalid UTF-8, can still be used as a path.  It can't be a
             // valid parameter flag.
-            result.push(to_devcontainer_uri(
-                a.as_os_str(),
-                DEVCONTAINER_DIR,
-                &mut cache,
-            ));
+            result.push(to_devcontainer_uri(a.as_os_str(), resolver));
         }
     }
 
-    // TODO: handle chat, serve-web, and tunnel
-
     debug_args(env::var("COPE_VERBOSE").is_ok(), &result);
     result
 }
 
-fn main() {
-    // Just exec here, rather than doing a fork.  This allows the existing
-    // stdin and stdout to work, along with their existing pty's.
-    match execvp(CODE, &process_args(env::args_os())) {
-        Err(_) => eprintln!("execvp failed launching {:?}", CODE),
+/// Resolve the repos of all of the path arguments up front, so that a path
+/// outside any repo can be found in the mounts of a repo named after it.
+/// Flags and their parameters are skipped, and so is everything after a
+/// subcommand or a terminal.
+fn resolve_repos(args: &[OsString], resolver: &mut Resolver) {
+    let mut it = args.iter();
+    while let Some(a) = it.next() {
+        match a.to_str() {
+            Some(b) if flags::is_terminal(b) || flags::subcommand(b).is_some() => break,
+            Some(b) if b.starts_with('-') => {
+                let last = if b.starts_with("--") {
+                    b.to_string()
+                } else {
+                    format!("-{}", b.chars().last().unwrap_or('-'))
+                };
+                it.by_ref()
+                    .take(flags::param_size(&last).unwrap_or(0))
+                    .for_each(drop);
+            }
+            Some(b) if uri_scheme(b).is_some() && !Path::new(b).exists() => {}
+            _ => {
+                // Paths that open through a tunnel or a codespace don't
+                // use the local devcontainer.
+                let pth = normalize(a);
+                if innermost(&resolver.tunnel_projects, &pth).is_none()
+                    && resolver.codespace(&pth).is_none()
+                {
+                    resolver.resolve(&pth);
+                }
+            }
+        }
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use std::os::unix::ffi::OsStringExt;
-
-    fn convert_args(args: &[&str]) -> Vec<String> {
-        let oa: Vec<OsString> = args.iter().map(|&s| OsString::from(s)).collect();
-        process_args(oa.into_iter())
-            .iter()
-            .map(|s| s.to_str().unwrap().into())
-            .collect()
+/// execvp, except that `code` means the configured editor binary, which
+/// skips the `cope shim`, if there is one, or we would just run ourselves,
+/// and falls back to a flatpak or snap install of the editor.  An editor
+/// binary with spaces in it is a command line.  Only returns if the exec
+/// failed.
+fn exec_real(args: &[CString]) -> nix::Error {
+    match real_args(args) {
+        Ok(wrapped) => {
+            let Err(er) = execvp(&wrapped[0], &wrapped);
+            er
+        }
+        Err(er) => er,
     }
+}
 
-    fn assert_file_uri(arg: &str) {
-        assert!(
-            arg.to_owned()
-                .starts_with("--file-uri=vscode-remote://dev-container+"),
-            "{:?}",
-            arg
-        );
+/// args with the shell-split words of COPE_CODE_ARGS (such as
+/// `--disable-gpu` or `--profile Work`) before the user's own.  code's
+/// subcommands have flags of their own, so they get none.
+fn with_code_args(args: &[CString], extra: Option<&str>) -> Vec<CString> {
+    let Some(extra) = extra.filter(|e| !e.trim().is_empty()) else {
+        return args.to_vec();
+    };
+    if args
+        .get(1)
+        .and_then(|a| a.to_str().ok())
+        .is_some_and(|a| a != "--" && (flags::is_terminal(a) || flags::subcommand(a).is_some()))
+    {
+        return args.to_vec();
     }
+    let Some(words) = string_utils::shell_split(extra) else {
+        eprintln!("{}", t!("bad-code-args", value = extra));
+        return args.to_vec();
+    };
+    let words = words
+        .into_iter()
+        .map(|w| CString::new(w).expect("COPE_CODE_ARGS with NUL"));
+    once(args[0].clone())
+        .chain(words)
+        .chain(args[1..].iter().cloned())
+        .collect()
+}
 
-    fn assert_folder_uri(arg: &str) {
-        assert!(
-            arg.to_owned()
-                .starts_with("--folder-uri=vscode-remote://dev-container+"),
-            "{:?}",
-            arg
-        );
+/// The command line that exec_real runs for args.
+fn real_args(args: &[CString]) -> Result<Vec<CString>, nix::Error> {
+    if args[0].as_c_str() != CODE {
+        return Ok(args.to_vec());
+    }
+    let given = args.len();
+    let args = &with_code_args(args, env::var("COPE_CODE_ARGS").ok().as_deref());
+    let bin = editor::bin();
+    let Some(prefix) = editor::command(
+        bin,
+        env::var_os("PATH").as_deref(),
+        env::var_os("HOME"),
+        Path::new("/"),
+    ) else {
+        return Err(nix::Error::ENOENT);
+    };
+    let wrapped = packaging::wrap(&prefix, args);
+    if prefix.len() > 1 || args.len() != given {
+        debug_args(env::var("COPE_VERBOSE").is_ok(), &wrapped);
     }
+    Ok(wrapped)
+}
 
-    #[test]
-    fn test_convert_path() {
+/// Where a rewritten arg opens: the authority of its URI, such as
+/// `dev-container+<hex>`, or "" for a local path.
+fn authority(arg: &str) -> &str {
+    let Some(uri) = arg
+        .strip_prefix("--file-uri=")
+        .or_else(|| arg.strip_prefix("--folder-uri="))
+    else {
+        return "";
+    };
+    let rest = uri.split_once("://").map_or("", |(_, rest)| rest);
+    rest.split('/').next().unwrap_or_default()
+}
+
+/// Can these args be split into several command lines?  Not if they have
+/// a subcommand, or `--`, after which cope can't tell what is what.
+fn splittable(args: &[CString]) -> bool {
+    let strs: Vec<&str> = args
+        .iter()
+        .map(|a| a.to_str().unwrap_or_default())
+        .collect();
+    !(strs.iter().skip(1).any(|a| *a == "--")
+        || strs.get(1).is_some_and(|a| flags::subcommand(a).is_some()))
+}
+
+/// The args after args[0], a flag and its parameters at a time, each with
+/// where it opens: the authority of its URI, "" for a local path, or None
+/// for a flag that isn't about files.
+fn units(args: &[CString]) -> Vec<(Option<&str>, &[CString])> {
+    let strs: Vec<&str> = args
+        .iter()
+        .map(|a| a.to_str().unwrap_or_default())
+        .collect();
+    let mut res = Vec::new();
+    let mut i = 1;
+    while i < args.len() {
+        let a = strs[i];
+        let sz = flags::param_size(a).unwrap_or(0).min(args.len() - i - 1);
+        let key = match a {
+            "--diff" | "-d" | "--merge" | "-m" | "--add" | "-a" => {
+                Some(strs.get(i + 1).map_or("", |p| authority(p)))
+            }
+            "-" => Some(""),
+            _ if sz > 0 => None,
+            _ if a.starts_with("--file-uri=") || a.starts_with("--folder-uri=") => {
+                Some(authority(a))
+            }
+            _ if a.starts_with('-') => None,
+            _ => Some(""),
+        };
+        res.push((key, &args[i..=i + sz]));
+        i += sz + 1;
+    }
+    res
+}
+
+/// The folders given to --add that won't end up in a window opened in the
+/// same place, since code can't mix folders from different containers (or
+/// from a container and the host) in one window.  With other paths on the
+/// command line, the folder goes to their window; otherwise to the last
+/// active one, which is only known (as window) when cope runs in a VS Code
+/// terminal.
+fn mismatched_adds<'a>(args: &'a [CString], window: Option<&str>) -> Vec<&'a str> {
+    if !splittable(args) {
+        return vec![];
+    }
+    let units = units(args);
+    let is_add =
+        |unit: &[CString]| matches!(unit[0].to_bytes(), b"--add" | b"-a") && unit.len() == 2;
+    let others: Vec<&str> = units
+        .iter()
+        .filter(|(_, unit)| !is_add(unit))
+        .filter_map(|(key, _)| *key)
+        .collect();
+    units
+        .iter()
+        .filter(|(_, unit)| is_add(unit))
+        .filter_map(|(key, unit)| {
+            let key = (*key)?;
+            let elsewhere = if others.is_empty() {
+                window.is_some_and(|w| w != key)
+            } else {
+                others.iter().any(|o| *o != key)
+            };
+            elsewhere.then(|| unit[1].to_str().unwrap_or_default())
+        })
+        .collect()
+}
+
+/// Where the window of the VS Code terminal cope runs in is, if it is in
+/// one on the host.  In a container's terminal, which container isn't
+/// known.
+fn terminal_window(term_program: Option<&str>, in_container: bool) -> Option<&'static str> {
+    (term_program == Some("vscode") && !in_container).then_some("")
+}
+
+/// Split the rewritten args into one command line for each container (and
+/// one for local files) that they open in, since one window can only show
+/// one of them.  Flags that aren't about files go on every command line.
+/// Command lines with subcommands or `--` aren't split.
+fn by_container(args: &[CString]) -> Vec<Vec<CString>> {
+    if !splittable(args) {
+        return vec![args.to_vec()];
+    }
+    let mut shared: Vec<CString> = Vec::new();
+    let mut groups: Vec<(&str, Vec<CString>)> = Vec::new();
+    for (key, unit) in units(args) {
+        match key {
+            None => shared.extend_from_slice(unit),
+            Some(key) => match groups.iter_mut().find(|(k, _)| *k == key) {
+                Some((_, group)) => group.extend_from_slice(unit),
+                None => groups.push((key, unit.to_vec())),
+            },
+        }
+    }
+    if groups.len() < 2 {
+        return vec![args.to_vec()];
+    }
+    groups
+        .into_iter()
+        .map(|(_, targets)| {
+            once(args[0].clone())
+                .chain(shared.iter().cloned())
+                .chain(targets)
+                .collect()
+        })
+        .collect()
+}
+
+/// How much room exec needs for args: each one, its NUL, and its pointer.
+fn args_size(args: &[CString]) -> usize {
+    args.iter()
+        .map(|a| a.as_bytes_with_nul().len() + size_of::<usize>())
+        .sum()
+}
+
+/// How much room there is for the editor's args: ARG_MAX, less what the
+/// environment takes, and some to spare for a flatpak wrapper and
+/// COPE_CODE_ARGS.
+fn arg_limit() -> usize {
+    let max = nix::unistd::sysconf(nix::unistd::SysconfVar::ARG_MAX)
+        .ok()
+        .flatten()
+        .map_or(128 * 1024, |m| m as usize);
+    let environ: usize = env::vars_os()
+        .map(|(k, v)| k.len() + v.len() + 2 + size_of::<usize>())
+        .sum();
+    max.saturating_sub(environ + 16 * 1024)
+}
+
+/// Split a command line that is too big for exec into several that each
+/// fit in limit, with the flags that aren't about files on each one.  The
+/// later ones add to the window the first one opened, so a --new-window
+/// becomes --reuse-window on them.
+fn fit_arg_limit(args: Vec<CString>, limit: usize) -> Vec<Vec<CString>> {
+    if args_size(&args) <= limit || !splittable(&args) {
+        return vec![args];
+    }
+    let mut base: Vec<CString> = vec![args[0].clone()];
+    let mut targets = Vec::new();
+    for (key, unit) in units(&args) {
+        match key {
+            None => base.extend_from_slice(unit),
+            Some(_) => targets.push(unit),
+        }
+    }
+    let mut res: Vec<Vec<CString>> = Vec::new();
+    let mut current = base.clone();
+    for unit in targets {
+        if current.len() > base.len() && args_size(&current) + args_size(unit) > limit {
+            res.push(current);
+            current = base.clone();
+        }
+        current.extend_from_slice(unit);
+    }
+    res.push(current);
+    for later in res.iter_mut().skip(1) {
+        for a in later.iter_mut().skip(1) {
+            if matches!(a.to_bytes(), b"-n" | b"--new-window") {
+                *a = c"-r".to_owned();
+            }
+        }
+    }
+    res
+}
+
+/// When the args were split by container, each command line gets
+/// --new-window, unless it says which window to use already.  Otherwise the
+/// files for one container can land in the window that was just opened
+/// for another, or in some unrelated window that was used last.
+fn new_windows(commands: &mut [Vec<CString>]) {
+    if commands.len() < 2 {
+        return;
+    }
+    for args in commands {
+        let chosen = args[1..].iter().any(|a| {
+            matches!(
+                a.to_bytes(),
+                b"-n" | b"--new-window" | b"-r" | b"--reuse-window"
+            )
+        });
+        if !chosen {
+            args.insert(1, c"-n".to_owned());
+        }
+    }
+}
+
+/// How long `cope wait-ready` waits for the extension to start a container,
+/// which can mean building an image.
+const READY_LIMIT: Duration = Duration::from_secs(30 * 60);
+
+/// For each repo opened in a container that isn't running yet, start a
+/// `cope wait-ready` in the background, which notifies once the Dev
+/// Containers extension has it running.
+fn watch_ready(resolver: &Resolver) {
+    let Ok(cope) = env::current_exe() else {
+        return;
+    };
+    for (root, props) in resolver.opened() {
+        if docker::find_container(root, &props.config_file, false).is_none() {
+            let _ = std::process::Command::new(&cope)
+                .arg("wait-ready")
+                .arg(root)
+                .arg(&props.config_file)
+                .stdin(std::process::Stdio::null())
+                .stdout(std::process::Stdio::null())
+                .stderr(std::process::Stdio::null())
+                .spawn();
+        }
+    }
+}
+
+/// `cope wait-ready <root> <config file>`: started by watch_ready, not by
+/// people.  Notifies when the container for root and its config is
+/// running, or gives up after READY_LIMIT.
+fn wait_ready(args: &[OsString]) {
+    let [root, config_file] = args else {
+        return;
+    };
+    notify::set_enabled(true);
+    let started = Instant::now();
+    while started.elapsed() < READY_LIMIT {
+        if docker::find_container(Path::new(root), Path::new(config_file), false).is_some() {
+            notify::send(
+                "cope",
+                &t!("notify-ready", path = Path::new(root).display()),
+            );
+            return;
+        }
+        std::thread::sleep(Duration::from_secs(2));
+    }
+}
+
+/// Start the editor for each command line but the last, without waiting,
+/// then replace this process with the last one.  With --wait, whatever ran
+/// cope is waiting for every window, so all of them are started, and cope
+/// waits for all of them before exiting with the status of the last.
+fn launch_all(commands: &[Vec<CString>]) {
+    let Some((last, first)) = commands.split_last() else {
+        return;
+    };
+    let verbose = env::var("COPE_VERBOSE").is_ok();
+    let wait = !first.is_empty()
+        && last
+            .iter()
+            .any(|a| a.as_bytes() == b"--wait" || a.as_bytes() == b"-w");
+    let mut children = Vec::new();
+    for args in if wait { commands } else { first } {
+        debug_args(verbose, args);
+        match spawn_editor(args) {
+            Ok(child) => children.push(child),
+            Err(er) => eprintln!(
+                "{}",
+                t!("launch-failed", command = editor::bin(), error = er)
+            ),
+        }
+    }
+    if wait {
+        let statuses: Vec<_> = children.iter_mut().map(|c| c.wait().ok()).collect();
+        let code = statuses
+            .last()
+            .copied()
+            .flatten()
+            .and_then(|s| s.code())
+            .unwrap_or(1);
+        std::process::exit(code);
+    }
+    if !first.is_empty() {
+        debug_args(verbose, last);
+    }
+    launch(last);
+}
+
+/// Start the editor with args, as a child of this process.
+fn spawn_editor(args: &[CString]) -> std::io::Result<std::process::Child> {
+    let wrapped = real_args(args)?;
+    let wrapped: Vec<&OsStr> = wrapped
+        .iter()
+        .map(|a| OsStr::from_bytes(a.as_bytes()))
+        .collect();
+    std::process::Command::new(wrapped[0])
+        .args(&wrapped[1..])
+        .spawn()
+}
+
+/// Replace this process with `code`.  Only returns if the exec failed.
+fn launch(args: &[CString]) {
+    // Just exec here, rather than doing a fork.  This allows the existing
+    // stdin and stdout to work, along with their existing pty's.
+    // A zero-length span, marking when the exec happened.
+    drop(Span::enter("launch"));
+    trace::flush();
+    let er = exec_real(args);
+    if er == nix::Error::ENOENT {
+        eprintln!("{}", t!("editor-not-found", command = editor::bin()));
+    } else {
+        eprintln!(
+            "{}",
+            t!("launch-failed", command = editor::bin(), error = er)
+        );
+    }
+    notify::send(
+        "cope",
+        &t!("notify-launch-failed", command = editor::bin(), error = er),
+    );
+}
+
+/// The argument that opens a path in any running container, the way the
+/// Dev Containers extension's "Attach to Running Container" does.
+pub fn attached_uri(container_name: &str, path: &str, folder: bool) -> String {
+    let id = serde_jsonc::json!({ "containerName": container_name }).to_string();
+    format!(
+        "--{}-uri=vscode-remote://attached-container+{}{}",
+        if folder { "folder" } else { "file" },
+        hex(id.as_bytes()),
+        encode_uri_path(path)
+    )
+}
+
+/// The scheme of an argument that is a URI, such as `file:///tmp/x`.  A
+/// scheme is more than one letter, so `C://x` is still taken for a path.
+fn uri_scheme(arg: &str) -> Option<&str> {
+    let (scheme, _) = arg.split_once("://")?;
+    let mut chars = scheme.chars();
+    (scheme.len() > 1
+        && chars.next()?.is_ascii_alphabetic()
+        && chars.all(|c| c.is_ascii_alphanumeric() || "+.-".contains(c)))
+    .then_some(scheme)
+}
+
+/// An scp-style `[user@]host:/path` argument, as a Remote - SSH URI.  The
+/// remote path can't be checked, so a last component with a `.` in it is
+/// taken to be a file, and anything else a folder.
+pub fn ssh_uri(arg: &str) -> Option<String> {
+    let (host, path) = arg.split_once(':')?;
+    if host.is_empty() || host.contains('/') || !path.starts_with('/') {
+        return None;
+    }
+    let folder = path.ends_with('/') || !path.rsplit('/').next().unwrap_or_default().contains('.');
+    Some(format!(
+        "--{}-uri=vscode-remote://ssh-remote+{host}{}",
+        if folder { "folder" } else { "file" },
+        encode_uri_path(path)
+    ))
+}
+
+/// The folder that serve-web opens: the first argument that isn't a flag or
+/// a flag's value, or the current directory.
+fn serve_web_folder(args: &[OsString]) -> PathBuf {
+    let mut it = args.iter();
+    while let Some(a) = it.next() {
+        let s = a.to_string_lossy();
+        if !s.starts_with('-') {
+            return normalize(a);
+        }
+        it.by_ref()
+            .take(flags::param_size(&s).unwrap_or(0))
+            .for_each(drop);
+    }
+    normalize(OsStr::new("."))
+}
+
+/// Only the standalone `code` CLI is installed, so there is no desktop
+/// editor to open.  Serve the editor over HTTP instead, and say where.
+/// Devcontainers aren't available this way, so the folder is opened on the
+/// host.
+fn serve_web(args: &[OsString]) {
+    let folder = serve_web_folder(&args[1..]);
+    let token = tunnel::token();
+    eprintln!("{}", t!("serve-web", url = tunnel::url(&token, &folder)));
+    exec_args(tunnel::serve_web_args(&token));
+}
+
+/// Convert the args, remember which repos were opened, then launch.
+fn open(args: Vec<OsString>, opts: &CopeOptions) {
+    if let Some(wanted) = &opts.tunnel {
+        let home = env::var_os("HOME").unwrap_or_default();
+        let names = tunnel::discover(Path::new(&home), env::var_os("PATH").as_deref());
+        if let Some(name) = tunnel::pick(wanted, &names) {
+            let rest: Vec<String> = args[1..]
+                .iter()
+                .map(|a| a.to_string_lossy().into())
+                .collect();
+            exec_args(tunnel::remote_args(&name, &rest));
+        }
+        return;
+    }
+    let mut args = if opts.glob || config::load().glob {
+        glob::expand_args(args, &env::current_dir().unwrap_or_default())
+    } else {
+        args
+    };
+    if let Some(src) = &opts.files_from {
+        match file_utils::read_file_list(src) {
+            Ok(files) => {
+                // Before any --, so that they get rewritten.
+                let at = args.iter().position(|a| a == "--").unwrap_or(args.len());
+                args.splice(at..at, files);
+            }
+            Err(er) => {
+                eprintln!("{}", t!("files-from-failed", path = src, error = er));
+                std::process::exit(1);
+            }
+        }
+    }
+    let args = if opts.new_container {
+        match in_new_container(args) {
+            Some(a) => a,
+            None => return,
+        }
+    } else {
+        args
+    };
+    let passthrough = args
+        .get(1)
+        .and_then(|a| a.to_str())
+        .is_some_and(|a| flags::is_terminal(a) || flags::subcommand(a).is_some());
+    // Only plain `code` can be the standalone CLI; cursor, codium and the
+    // like are always desktop editors.
+    if !passthrough
+        && editor::bin() == "code"
+        && tunnel::standalone_cli(env::var_os("PATH").as_deref())
+    {
+        serve_web(&args);
+        return;
+    }
+    let mut resolver = Resolver::from_config();
+    resolver.find_codespaces = opts.codespace;
+    let span = Span::enter("process_args");
+    let started = Instant::now();
+    let code_args = process_args(args.into_iter(), &mut resolver);
+    let resolve_time = started.elapsed();
+    drop(span);
+
+    warn_host_requirements(&resolver);
+    let roots = resolver.roots();
+    frecency::record(&frecency_db(), &roots);
+    if config::load().stats {
+        let mut all = stats::read(&stats_file());
+        stats::add(&mut all, &roots, resolve_time, frecency::now());
+        stats::write(&stats_file(), &all);
+    }
+
+    let window = terminal_window(
+        env::var("TERM_PROGRAM").ok().as_deref(),
+        resolver.in_container,
+    );
+    for folder in mismatched_adds(&code_args, window) {
+        eprintln!("{}", t!("add-other-window", folder = folder));
+    }
+    let mut commands = by_container(&code_args);
+    new_windows(&mut commands);
+    add_project_args(&mut commands, &resolver);
+    let limit = arg_limit();
+    let commands: Vec<Vec<CString>> = commands
+        .into_iter()
+        .flat_map(|c| fit_arg_limit(c, limit))
+        .collect();
+    if notify::enabled() && !command_utils::is_offline() {
+        watch_ready(&resolver);
+    }
+    launch_all(&commands);
+}
+
+/// Put the args from each repo's .devcontainer/cope.json on the command
+/// line that opens its container, and no other, since by_container has
+/// already split them up by container.  Subcommands don't get any.
+fn add_project_args(commands: &mut [Vec<CString>], resolver: &Resolver) {
+    for args in commands {
+        if args
+            .get(1)
+            .and_then(|a| a.to_str().ok())
+            .is_some_and(|a| flags::subcommand(a).is_some())
+        {
+            continue;
+        }
+        let hexes: Vec<String> = units(args)
+            .into_iter()
+            .filter_map(|(key, _)| {
+                key?.strip_prefix("dev-container+")?
+                    .split('@')
+                    .next()
+                    .map(String::from)
+            })
+            .collect();
+        let extra: Vec<CString> = resolver
+            .cache
+            .values()
+            .flatten()
+            .filter(|props| hexes.contains(&props.hex))
+            .flat_map(|props| &props.code_args)
+            .map(|a| CString::new(a.as_str()).expect("Project arg with NUL"))
+            .collect();
+        if !extra.is_empty() {
+            args.splice(1..1, extra);
+            debug_args(env::var("COPE_VERBOSE").is_ok(), args);
+        }
+    }
+}
+
+/// Warn about containers that want more CPUs, memory or storage than the
+/// container engine has, rather than leaving VS Code to fail later.  The
+/// engine is only asked when some config has hostRequirements.
+fn warn_host_requirements(resolver: &Resolver) {
+    let wanting: Vec<&DirProperties> = resolver
+        .cache
+        .values()
+        .flatten()
+        .filter(|props| !props.host_requirements.is_empty())
+        .collect();
+    if wanting.is_empty() {
+        return;
+    }
+    let have = host::available();
+    for props in wanting {
+        for warning in host::shortfalls(&props.host_requirements, &have) {
+            eprintln!("{}: {warning}", props.config_file.display());
+        }
+    }
+}
+
+/// Make a fresh copy of the repo holding the first path argument (default:
+/// the current directory), and point all of the args that are inside the
+/// repo at the copy instead.  Since the copy has a different host path, the
+/// Dev Containers extension gives it its own container.
+fn in_new_container(args: Vec<OsString>) -> Option<Vec<OsString>> {
+    let paths = path_args(&args);
+    let first = args
+        .iter()
+        .zip(&paths)
+        .find(|(_, is_path)| **is_path)
+        .map_or(OsStr::new("."), |(a, _)| a.as_os_str());
+    let strategies = Resolver::from_config().strategies;
+    let Some((root, _)) = discovery::find_root(&normalize(first), &strategies, Some(".git")) else {
+        eprintln!("{}", t!("no-devcontainer", path = format!("{first:?}")));
+        return None;
+    };
+
+    let name = root.file_name().unwrap_or_default().to_string_lossy();
+    let dest = new_container::unique_dir(&config::data_dir().join("containers"), &name);
+    eprintln!(
+        "{}",
+        t!(
+            "copying",
+            from = format!("{root:?}"),
+            to = format!("{dest:?}")
+        )
+    );
+    if !new_container::provision(&root, &dest) {
+        eprintln!(
+            "{}",
+            t!(
+                "copy-failed",
+                from = format!("{root:?}"),
+                to = format!("{dest:?}")
+            )
+        );
+        notify::send("cope", &t!("notify-copy-failed", path = root.display()));
+        return None;
+    }
+    notify::send("cope", &t!("notify-copied", path = dest.display()));
+    eprintln!("{}", t!("copy-cleanup"));
+
+    let mut res: Vec<OsString> = args
+        .into_iter()
+        .zip(paths)
+        .map(|(a, is_path)| {
+            if !is_path {
+                return a;
+            }
+            let pth = normalize(&a);
+            match new_container::remap(&pth, &root, &dest) {
+                Some(p) if pth.exists() => p.into(),
+                _ => a,
+            }
+        })
+        .collect();
+    if res.len() == 1 {
+        // The implicit "." needs to point at the copy, too.
+        res.push(dest.into());
+    }
+    Some(res)
+}
+
+/// Which of args (including args[0]) are paths that code opens: not flags,
+/// nor their parameters, except for the files of --diff, --merge and
+/// --add.  Nothing after a subcommand or a terminal counts.
+fn path_args(args: &[OsString]) -> Vec<bool> {
+    let mut res = vec![false; args.len()];
+    let mut i = 1;
+    while i < args.len() {
+        let a = args[i].to_string_lossy();
+        if flags::is_terminal(&a) || flags::subcommand(&a).is_some() {
+            break;
+        }
+        let sz = flags::param_size(&a).unwrap_or(0);
+        if matches!(
+            a.as_ref(),
+            "--diff" | "-d" | "--merge" | "-m" | "--add" | "-a"
+        ) {
+            res[i + 1..(i + 1 + sz).min(args.len())].fill(true);
+        } else if !a.starts_with('-') {
+            res[i] = true;
+        }
+        i += sz + 1;
+    }
+    res
+}
+
+fn frecency_db() -> PathBuf {
+    config::data_dir().join("frecency.json")
+}
+
+fn stats_file() -> PathBuf {
+    config::data_dir().join("stats.json")
+}
+
+/// `cope stats`: show how often each repo was launched, and how long it
+/// took to work out its config.
+fn show_stats() {
+    let all = stats::read(&stats_file());
+    if all.is_empty() {
+        if config::load().stats {
+            println!("{}", t!("no-launches"));
+        } else {
+            println!(
+                "{}",
+                t!("stats-off", file = config::config_file().display())
+            );
+        }
+        return;
+    }
+    print!("{}", stats::format(&all));
+}
+
+/// `cope which`: show the editor command that would run, after the
+/// flags, environment, config file and invoked name have had their say.
+fn which_editor() {
+    match editor::command(
+        editor::bin(),
+        env::var_os("PATH").as_deref(),
+        env::var_os("HOME"),
+        Path::new("/"),
+    ) {
+        Some(cmd) => {
+            let words: Vec<_> = cmd.iter().map(|w| w.to_string_lossy()).collect();
+            println!("{}", words.join(" "));
+        }
+        None => {
+            eprintln!("{}", t!("editor-not-found", command = editor::bin()));
+            std::process::exit(1);
+        }
+    }
+}
+
+/// `cope attach <container> [path]`: open a path in any running container,
+/// not just one from a devcontainer config.  Relative paths are from the
+/// workspace folder in the image's devcontainer metadata, or else the
+/// container's working directory.
+fn attach(args: &[OsString]) {
+    let Some(container) = args.first().map(|a| a.to_string_lossy()) else {
+        eprintln!(
+            "{}",
+            t!("usage", usage = "cope attach <container-name-or-id> [path]")
+        );
+        std::process::exit(1);
+    };
+    let Some(found) = docker::inspect_container(&container) else {
+        eprintln!("{}", t!("no-such-container", name = container));
+        std::process::exit(1);
+    };
+    let workdir = found.folder();
+    let path = match args.get(1).map(|a| a.to_string_lossy()) {
+        None => workdir,
+        Some(p) if p.starts_with('/') => p.into(),
+        Some(p) => Path::new(&workdir).join(&*p).display().to_string(),
+    };
+    let folder = docker::is_dir(&container, &path);
+    exec_args(vec![
+        CODE.to_string_lossy().into(),
+        attached_uri(&found.name, &path, folder),
+    ]);
+}
+
+/// `cope k8s <[namespace/]pod[:container]> [path]`: open a path in a
+/// container of a Kubernetes pod.  Relative paths are from the container's
+/// working directory.
+fn k8s(args: &[OsString]) {
+    let Some(spec) = args.first().map(|a| a.to_string_lossy()) else {
+        eprintln!(
+            "{}",
+            t!(
+                "usage",
+                usage = "cope k8s <[namespace/]pod[:container]> [path]"
+            )
+        );
+        std::process::exit(1);
+    };
+    let Some(target) = k8s::find(&spec) else {
+        eprintln!("{}", t!("no-such-pod", name = spec));
+        std::process::exit(1);
+    };
+    let workdir = target.working_dir.clone().unwrap_or_else(|| "/".into());
+    let path = match args.get(1).map(|a| a.to_string_lossy()) {
+        None => workdir,
+        Some(p) if p.starts_with('/') => p.into(),
+        Some(p) => Path::new(&workdir).join(&*p).display().to_string(),
+    };
+    exec_args(vec![CODE.to_string_lossy().into(), target.uri(&path)]);
+}
+
+/// `cope update-flags`: learn which of code's flags take parameters, and
+/// what its subcommands are, from `code --help` and `code <subcommand>
+/// --help`, so that cope keeps up with newer versions of code.
+fn update_flags() {
+    let help = |args: &[&str]| -> Option<String> {
+        let args: Vec<CString> = once(CODE.to_owned())
+            .chain(args.iter().map(|a| to_cstring((*a).into())))
+            .collect();
+        let wrapped = real_args(&args).ok()?;
+        let wrapped: Vec<&OsStr> = wrapped
+            .iter()
+            .map(|a| OsStr::from_bytes(a.as_bytes()))
+            .collect();
+        command_utils::capture(std::process::Command::new(wrapped[0]).args(&wrapped[1..]))
+    };
+    let Some(top) = help(&["--help"]) else {
+        eprintln!("{}", t!("update-flags-failed", command = editor::bin()));
+        std::process::exit(1);
+    };
+    let learned = flags::learn(&top, |name| help(&[name, "--help"]));
+    let file = flags::file();
+    if let Some(dir) = file.parent() {
+        fs::create_dir_all(dir).unwrap_or_else(|er| panic!("Error creating {dir:?} {er}"));
+    }
+    let json_data = serde_jsonc::to_string_pretty(&learned).expect("Serializing flags");
+    fs::write(&file, json_data).unwrap_or_else(|er| panic!("Error writing file {file:?} {er}"));
+    println!(
+        "{}",
+        t!(
+            "updated-flags",
+            flags = learned.params.len(),
+            subcommands = learned.subcommands.len(),
+            file = file.display()
+        )
+    );
+}
+
+/// What is wrong with the text of a config.  Unlike parse_config, JSON5
+/// syntax is a problem here, though the schema is still checked if that is
+/// all that is wrong.
+fn strict_problems(json_data: &str) -> Vec<String> {
+    match serde_jsonc::from_str::<serde_jsonc::Value>(json_data) {
+        Ok(v) => schema::check(&v)
+            .iter()
+            .map(schema::Problem::message)
+            .collect(),
+        Err(er) => {
+            let mut problems = vec![describe_error(&er, json_data)];
+            if let Ok(v) = serde_jsonc::from_str::<serde_jsonc::Value>(&json5::to_json(json_data)) {
+                problems.push(t!("validate-json5"));
+                problems.extend(schema::check(&v).iter().map(schema::Problem::message));
+            }
+            problems
+        }
+    }
+}
+
+/// `cope validate [path]`: check the devcontainer.json files of the repo
+/// holding path (default: the current directory), or the one file named,
+/// against the devcontainer.json schema.  Exits with 1 if any have
+/// problems.
+fn validate(args: &[OsString]) {
+    let pth = normalize(args.first().map_or(OsStr::new("."), |a| a.as_os_str()));
+    let configs = if pth.is_file() {
+        vec![pth]
+    } else {
+        let resolver = Resolver::from_config();
+        match discovery::find_root(&pth, &resolver.strategies, Some(".git")) {
+            Some((_, configs)) => configs,
+            None => {
+                eprintln!("{}", t!("no-devcontainer", path = format!("{pth:?}")));
+                std::process::exit(1);
+            }
+        }
+    };
+    let mut ok = true;
+    for config_file in configs {
+        let problems = match file_utils::read_text(&config_file) {
+            Ok(json_data) => strict_problems(&json_data),
+            Err(er) => vec![er.to_string()],
+        };
+        if problems.is_empty() {
+            println!("{}", t!("validate-ok", file = config_file.display()));
+        } else {
+            ok = false;
+            println!("{}:", config_file.display());
+            for p in problems {
+                println!("  {p}");
+            }
+        }
+    }
+    if !ok {
+        std::process::exit(1);
+    }
+}
+
+/// `cope z [--import-zoxide] [keywords...]`: open the best match out of the
+/// repos that have been opened before.
+fn z(argv0: &OsStr, args: &[OsString], opts: &CopeOptions) {
+    let db = frecency_db();
+    let mut keywords: Vec<String> = Vec::new();
+    for a in args {
+        if a == "--import-zoxide" {
+            let count = frecency::import_zoxide(&db, &Resolver::from_config().strategies);
+            eprintln!("{}", t!("zoxide-imported", count = count));
+        } else {
+            keywords.push(a.to_string_lossy().into());
+        }
+    }
+    if keywords.is_empty() && !args.is_empty() {
+        // Only importing.
+        return;
+    }
+
+    match frecency::best(&frecency::read_db(&db), &keywords, frecency::now()) {
+        Some(entry) => open(vec![argv0.into(), entry.path.clone().into()], opts),
+        None => eprintln!(
+            "{}",
+            t!("no-frecency-match", keywords = format!("{keywords:?}"))
+        ),
+    }
+}
+
+/// `cope shell [path | container]`: start an interactive shell in the
+/// running container for the repo holding path (default: the current
+/// directory), as the same user that VS Code uses, or in the named
+/// container.
+fn shell(args: &[OsString]) {
+    // A running container's name, rather than a path: use the user and
+    // folder from the image's devcontainer metadata.
+    if let Some(name) = args.first().filter(|a| !Path::new(a).exists())
+        && let Some(found) = docker::inspect_container(&name.to_string_lossy())
+    {
+        exec_args(docker::shell_args(
+            found.name.trim_start_matches('/'),
+            found.metadata.user(),
+            &found.folder(),
+        ));
+        return;
+    }
+    let pth = normalize(args.first().map_or(OsStr::new("."), |a| a.as_os_str()));
+    if let Some((_, props, id)) = running_container(&pth) {
+        exec_args(docker::shell_args(
+            &id,
+            props.user.as_deref(),
+            &props.folder,
+        ));
+    }
+}
+
+/// `cope exec [--] <command...>`: run a command in the running container for
+/// the current directory's repo, in the matching directory inside the
+/// container.
+fn exec(args: &[OsString]) {
+    let args = match args.first() {
+        Some(a) if a == "--" => &args[1..],
+        _ => args,
+    };
+    if args.is_empty() {
+        eprintln!("{}", t!("usage", usage = "cope exec [--] <command...>"));
+        return;
+    }
+    let cwd = normalize(OsStr::new("."));
+    if let Some((root, props, id)) = running_container(&cwd) {
+        let dir = props
+            .container_path(&root, &cwd)
+            .unwrap_or(props.folder.clone());
+        let cmd: Vec<String> = args.iter().map(|a| a.to_string_lossy().into()).collect();
+        let tty = std::io::stdin().is_terminal() && std::io::stdout().is_terminal();
+        exec_args(docker::exec_args(
+            &id,
+            props.user.as_deref(),
+            &dir,
+            tty,
+            &cmd,
+        ));
+    }
+}
+
+/// `cope forward <port>[:host-port]`: forward a port from the running
+/// container for the current directory's repo to localhost, until
+/// interrupted.
+fn forward(args: &[OsString]) {
+    let Some((port, host_port)) = args
+        .first()
+        .and_then(|a| a.to_str())
+        .and_then(ports::parse_forward)
+    else {
+        eprintln!("{}", t!("usage", usage = "cope forward <port>[:host-port]"));
+        return;
+    };
+    if let Some((_, _, id)) = running_container(&normalize(OsStr::new("."))) {
+        eprintln!("{}", t!("forwarding", host_port = host_port, port = port));
+        exec_args(ports::forward_args(&id, port, host_port));
+    }
+}
+
+/// `cope browse [port] [/path]`: open a service from the running container
+/// for the current directory's repo in the default browser.
+fn browse(args: &[OsString]) {
+    let mut wanted: Option<u16> = None;
+    let mut path = String::from("/");
+    for a in args {
+        let a = a.to_string_lossy();
+        match a.parse() {
+            Ok(p) => wanted = Some(p),
+            Err(_) if a.starts_with('/') => path = a.into(),
+            Err(_) => {
+                eprintln!("{}", t!("usage", usage = "cope browse [port] [/path]"));
+                return;
+            }
+        }
+    }
+
+    let Some((_, props, id)) = running_container(&normalize(OsStr::new("."))) else {
+        return;
+    };
+    let published = ports::parse_docker_port(&docker::published_ports(&id));
+    let Some(port) = ports::browse_port(wanted, &props.forward_ports, &published) else {
+        eprintln!("{}", t!("no-ports"));
+        return;
+    };
+    let opener = if cfg!(target_os = "macos") {
+        "open"
+    } else {
+        "xdg-open"
+    };
+    exec_args(vec![
+        opener.into(),
+        format!("http://localhost:{port}{path}"),
+    ]);
+}
+
+/// Registry lookups aren't allowed offline; say so, rather than claiming
+/// everything is up to date.
+fn registry_offline() -> bool {
+    let offline = command_utils::is_offline();
+    if offline {
+        eprintln!("{}", t!("offline-registry"));
+    }
+    offline
+}
+
+/// The chosen devcontainer.json for the repo holding path (default: the
+/// current directory), and its contents.
+fn chosen_config(path: Option<&OsString>) -> Option<(PathBuf, String)> {
+    let pth = normalize(path.map_or(OsStr::new("."), |a| a.as_os_str()));
+    let mut resolver = Resolver::from_config();
+    let Some((_, props)) = resolver.resolve(&pth) else {
+        eprintln!("{}", t!("no-devcontainer", path = format!("{pth:?}")));
+        return None;
+    };
+    let config_file = props.config_file.clone();
+    match file_utils::read_text(&config_file) {
+        Ok(contents) => Some((config_file, contents)),
+        Err(er) => {
+            eprintln!(
+                "{}",
+                t!("read-failed", file = config_file.display(), error = er)
+            );
+            None
+        }
+    }
+}
+
+/// `cope outdated [--json] [path]`: report newer image tags and feature
+/// versions without changing anything.
+fn outdated(args: &[OsString]) {
+    if registry_offline() {
+        return;
+    }
+    let json = args.iter().any(|a| a == "--json");
+    let path = args.iter().find(|a| *a != "--json");
+    let Some((config_file, contents)) = chosen_config(path) else {
+        return;
+    };
+    let upgrades = update::find_upgrades(&contents, registry::list_tags);
+    if json {
+        println!(
+            "{}",
+            serde_jsonc::to_string_pretty(&upgrades).expect("Serializing upgrades")
+        );
+    } else if upgrades.is_empty() {
+        println!("{}", t!("up-to-date", file = config_file.display()));
+    } else {
+        print!("{}", update::format(&upgrades));
+    }
+}
+
+/// `cope update-config [path]`: offer newer image tags and feature versions
+/// for the devcontainer.json of the repo holding path, and rewrite the file
+/// with the ones picked.
+fn update_config(args: &[OsString]) {
+    if registry_offline() {
+        return;
+    }
+    let Some((config_file, contents)) = chosen_config(args.first()) else {
+        return;
+    };
+    let upgrades = update::find_upgrades(&contents, registry::list_tags);
+    if upgrades.is_empty() {
+        println!("{}", t!("up-to-date", file = config_file.display()));
+        return;
+    }
+
+    let items: Vec<String> = upgrades
+        .iter()
+        .map(|u| format!("{} -> {}", u.from, u.to))
+        .collect();
+    let picked = MultiSelect::new()
+        .with_prompt(t!("choose-upgrades"))
+        .items(&items)
+        .interact_opt()
+        .expect("Selection failed")
+        .unwrap_or_default();
+    if picked.is_empty() {
+        return;
+    }
+    let chosen: Vec<update::Upgrade> = picked.into_iter().map(|i| upgrades[i].clone()).collect();
+    fs::write(&config_file, update::apply(&contents, &chosen))
+        .unwrap_or_else(|er| panic!("Error writing file {config_file:?} {er}"));
+    println!(
+        "{}",
+        t!(
+            "updated-config",
+            file = config_file.display(),
+            count = chosen.len()
+        )
+    );
+}
+
+/// `cope complete <command> <word> <previous word>`: print the completions
+/// of word, one per line.  This is the protocol of bash's `complete -C`.
+/// With `"containerCompletion": true` in the config file, files that only
+/// exist in the running container are offered too.
+fn complete(args: &[OsString]) {
+    let args: Vec<String> = args.iter().map(|a| a.to_string_lossy().into()).collect();
+    let [cmd, word, prev, ..] = args.as_slice() else {
+        eprintln!(
+            "{}",
+            t!(
+                "usage",
+                usage = "cope complete <command> <word> <previous word>"
+            )
+        );
+        return;
+    };
+    let cwd = normalize(OsStr::new("."));
+    let mut files = complete::local_matches(&cwd, word);
+    if config::load().container_completion {
+        files.extend(container_matches(&cwd, word));
+    }
+    let position = if prev == cmd { 1 } else { 2 };
+    for c in complete::candidates(position, word, files) {
+        println!("{c}");
+    }
+}
+
+/// The completions of word in the running container for the repo holding
+/// cwd.  Nothing is printed on failure, and nobody is asked to choose
+/// between configs, since the shell is waiting.
+fn container_matches(cwd: &Path, word: &str) -> Vec<String> {
+    command_utils::set_timeout(command_utils::timeout().min(complete::CONTAINER_TIMEOUT));
+    let strategies = Resolver::from_config().strategies;
+    let Some((root, configs)) = discovery::find_root(cwd, &strategies, Some(".git")) else {
+        return vec![];
+    };
+    if configs.len() != 1 {
+        return vec![];
+    }
+    let Some(props) = dir_properties(&root, configs) else {
+        return vec![];
+    };
+    let Some(id) = docker::find_container(&root, &props.config_file, false) else {
+        return vec![];
+    };
+    let dir = props
+        .container_path(&root, cwd)
+        .unwrap_or(props.folder.clone());
+    let cmd: Vec<String> = vec![
+        "sh".into(),
+        "-c".into(),
+        complete::container_script(),
+        "sh".into(),
+        word.into(),
+    ];
+    let args = docker::exec_args(&id, props.user.as_deref(), &dir, false, &cmd);
+    command_utils::capture(std::process::Command::new(&args[0]).args(&args[1..]))
+        .map(|out| out.lines().map(String::from).collect())
+        .unwrap_or_default()
+}
+
+/// Find the running container for the repo holding pth, complaining if
+/// there isn't one.
+fn running_container(pth: &Path) -> Option<(PathBuf, DirProperties, String)> {
+    let mut resolver = Resolver::from_config();
+    let Some((root, props)) = resolver.resolve(pth) else {
+        eprintln!("{}", t!("no-devcontainer", path = format!("{pth:?}")));
+        return None;
+    };
+    let Some(id) = docker::find_container(&root, &props.config_file, false) else {
+        eprintln!("{}", t!("no-running-container", path = format!("{root:?}")));
+        return None;
+    };
+    Some((root, props.clone(), id))
+}
+
+/// Replace this process with the given command line.  Only returns if the
+/// exec failed.
+fn exec_args(args: Vec<String>) {
+    let args: Vec<CString> = args.into_iter().map(|a| to_cstring(a.into())).collect();
+    debug_args(env::var("COPE_VERBOSE").is_ok(), &args);
+    drop(Span::enter(format!("exec {:?}", args[0])));
+    trace::flush();
+    let er = exec_real(&args);
+    eprintln!(
+        "{}",
+        t!(
+            "launch-failed",
+            command = format!("{:?}", args[0]),
+            error = er
+        )
+    );
+    notify::send(
+        "cope",
+        &t!(
+            "notify-launch-failed",
+            command = args[0].to_string_lossy(),
+            error = er
+        ),
+    );
+}
+
+/// `cope status|ps|logs|down [path] [docker args...]`: manage the
+/// container(s) for the repo holding path (default: the current directory).
+/// The first argument that isn't a flag, or the value of one, is the path.
+fn lifecycle(cmd: &str, args: &[OsString]) {
+    let (path, extra) = lifecycle::split_path(cmd, args);
+    let pth = normalize(path.unwrap_or(OsStr::new(".")));
+    let mut resolver = Resolver::from_config();
+    let Some((root, props)) = resolver.resolve(&pth) else {
+        eprintln!("{}", t!("no-devcontainer", path = format!("{pth:?}")));
+        return;
+    };
+
+    if cmd == "status" {
+        let mut rows = vec![
+            (t!("status-repo"), root.display().to_string()),
+            (t!("status-config"), props.config_file.display().to_string()),
+            (t!("status-folder"), props.folder.clone()),
+            (t!("status-users"), props.users()),
+        ];
+        if let Some(c) = &props.compose {
+            rows.push((t!("status-compose"), c.project.clone()));
+            if let Some(service) = &c.service {
+                rows.push((t!("status-service"), service.clone()));
+            }
+        }
+        print!("{}", aligned(&rows));
+    }
+
+    match lifecycle::docker_args(
+        cmd,
+        &root,
+        &props.config_file,
+        props.compose.as_ref(),
+        &extra,
+    ) {
+        Some(docker_args) => exec_args(docker_args),
+        None => eprintln!("{}", t!("no-container", path = format!("{root:?}"))),
+    }
+}
+
+/// Everything cope knows about the container for root, for `cope info`.
+fn info_report(root: &Path, props: &DirProperties) -> String {
+    let none = || "(none)".to_string();
+    let list = |items: Vec<String>| {
+        if items.is_empty() {
+            none()
+        } else {
+            items.join(", ")
+        }
+    };
+    let mut lines = vec![
+        ("Repo", root.display().to_string()),
+        ("Config", props.config_file.display().to_string()),
+        ("From", props.summary.clone().unwrap_or_else(none)),
+        ("Hex ID", props.hex.clone()),
+        (
+            "Devcontainer ID",
+            variables::devcontainer_id(root, &props.config_file),
+        ),
+        ("Folder", props.folder.clone()),
+        (
+            "URI",
+            format!(
+                "vscode-remote://dev-container+{}{}",
+                props.hex, props.folder
+            ),
+        ),
+        (
+            "Remote user",
+            props.user.clone().unwrap_or_else(|| "(the image's)".into()),
+        ),
+        ("Declared users", props.users()),
+        ("Features", list(props.features.clone())),
+        (
+            "Forwarded ports",
+            list(props.forward_ports.iter().map(u16::to_string).collect()),
+        ),
+        (
+            "Mounts",
+            list(
+                props
+                    .mounts
+                    .iter()
+                    .map(|m| format!("{} -> {}", m.source.display(), m.target))
+                    .collect(),
+            ),
+        ),
+        (
+            "Open mode",
+            match props.open_mode {
+                OpenMode::Path => "path".into(),
+                OpenMode::Folder => "folder".into(),
+            },
+        ),
+    ];
+    if let Some(m) = &props.workspace_mount {
+        lines.push((
+            "Workspace mount",
+            format!("{} -> {}", m.source.display(), m.target),
+        ));
+    }
+    if let Some(c) = &props.compose {
+        lines.push(("Compose project", c.project.clone()));
+        lines.push(("Service", c.service.clone().unwrap_or_else(none)));
+    }
+    aligned(&lines)
+}
+
+/// `cope info [path]`: show the config cope would use for path (default:
+/// the current directory), and everything it works out from it, without
+/// opening anything.
+fn info(args: &[OsString]) {
+    let pth = normalize(args.first().map_or(OsStr::new("."), |a| a.as_os_str()));
+    let mut resolver = Resolver::from_config();
+    let Some((root, props)) = resolver.resolve(&pth) else {
+        eprintln!("{}", t!("no-devcontainer", path = format!("{pth:?}")));
+        std::process::exit(1);
+    };
+    print!("{}", info_report(&root, props));
+}
+
+fn plan_strings(plan: &[CString]) -> Vec<String> {
+    plan.iter().map(|a| a.to_string_lossy().into()).collect()
+}
+
+/// `cope record <file> [args...]`: work out what `cope args...` would run,
+/// and save the inputs and the result to file, without launching anything.
+fn record(argv0: &OsStr, args: &[OsString]) {
+    let Some((file_name, rest)) = args.split_first() else {
+        eprintln!("{}", t!("usage", usage = "cope record <file> [args...]"));
+        return;
+    };
+    let cope_args: Vec<OsString> = once(argv0.into()).chain(rest.iter().cloned()).collect();
+    let rec = record::Recording {
+        cwd: env::current_dir().expect("failed to get current directory"),
+        args: cope_args
+            .iter()
+            .map(|a| a.to_string_lossy().into())
+            .collect(),
+        plan: plan_strings(&process_args(
+            cope_args.into_iter(),
+            &mut Resolver::from_config(),
+        )),
+    };
+    record::write(Path::new(file_name), &rec);
+    println!("{}", rec.plan.join(" "));
+}
+
+/// `cope replay [--check] <file>`: launch a recorded plan.  With --check,
+/// re-run the recorded inputs through the current resolver instead, and
+/// report any differences from the recorded plan.
+fn replay(args: &[OsString]) {
+    let check = args.iter().any(|a| a == "--check");
+    let Some(file_name) = args.iter().find(|a| *a != "--check") else {
+        eprintln!("{}", t!("usage", usage = "cope replay [--check] <file>"));
+        return;
+    };
+    let rec = record::read(Path::new(file_name));
+    if !check {
+        let plan: Vec<CString> = rec.plan.into_iter().map(|a| to_cstring(a.into())).collect();
+        launch(&plan);
+        return;
+    }
+
+    env::set_current_dir(&rec.cwd)
+        .unwrap_or_else(|er| panic!("Unable to change to {:?} {er}", rec.cwd));
+    let inputs = rec.args.iter().map(OsString::from).collect::<Vec<_>>();
+    let actual = plan_strings(&process_args(
+        inputs.into_iter(),
+        &mut Resolver::from_config(),
+    ));
+    let differences = record::diff(&rec.plan, &actual);
+    if differences.is_empty() {
+        println!("{}", t!("plan-matches", file = format!("{file_name:?}")));
+    } else {
+        differences.iter().for_each(|d| println!("{d}"));
+        std::process::exit(1);
+    }
+}
+
+/// `cope alias [add <name> <args...> | remove <name>]`: manage the aliases
+/// in the config file.  With no arguments, list them.
+fn alias(args: &[OsString]) {
+    let args: Vec<String> = args.iter().map(|a| a.to_string_lossy().into()).collect();
+    let config_file = config::config_file();
+    let mut cfg = match config::try_read_config(&config_file) {
+        Ok(cfg) => cfg,
+        Err(er) => {
+            eprintln!(
+                "{}",
+                t!("config-unchanged", error = er, file = config_file.display())
+            );
+            std::process::exit(1);
+        }
+    };
+    match args
+        .iter()
+        .map(String::as_str)
+        .collect::<Vec<_>>()
+        .as_slice()
+    {
+        [] => print!("{}", aliases::format(&cfg.aliases)),
+        ["add", name, rest @ ..] if !rest.is_empty() => {
+            let name = name.trim_start_matches('@').to_string();
+            cfg.aliases
+                .insert(name, rest.iter().map(|&s| s.into()).collect());
+        }
+        ["remove", name] => {
+            if cfg.aliases.remove(name.trim_start_matches('@')).is_none() {
+                eprintln!("{}", t!("unknown-alias", name = name));
+                return;
+            }
+        }
+        _ => {
+            eprintln!(
+                "{}",
+                t!(
+                    "usage",
+                    usage = "cope alias [add <name> <args...> | remove <name>]"
+                )
+            );
+            return;
+        }
+    }
+    if !args.is_empty()
+        && let Err(er) = config::write_config(&config_file, &cfg)
+    {
+        eprintln!(
+            "{}",
+            t!("write-failed", file = config_file.display(), error = er)
+        );
+    }
+}
+
+/// `cope projects [--refresh]`: pick one of the projects found under the
+/// configured roots, and open it.
+fn projects(argv0: &OsStr, args: &[OsString], opts: &CopeOptions) {
+    let home = env::var_os("HOME");
+    let roots: Vec<PathBuf> = config::load()
+        .projects
+        .iter()
+        .map(|r| expand_home(r, home.clone()))
+        .collect();
+    if roots.is_empty() {
+        eprintln!(
+            "{}",
+            t!(
+                "no-project-roots",
+                file = format!("{:?}", config::config_file())
+            )
+        );
+        return;
+    }
+
+    let refresh = args.iter().any(|a| a == "--refresh");
+    let found = projects::cached_scan(
+        &config::cache_dir().join("projects.json"),
+        &roots,
+        &Resolver::from_config().strategies,
+        refresh,
+    );
+    if found.is_empty() {
+        eprintln!(
+            "{}",
+            t!(
+                "no-projects",
+                dir = DEVCONTAINER_DIR,
+                roots = format!("{roots:?}")
+            )
+        );
+        return;
+    }
+    if let Some(project) = projects::choose(&found) {
+        open(vec![argv0.into(), project.into()], opts);
+    }
+}
+
+fn main() {
+    let args = match aliases::expand(
+        env::args_os().collect(),
+        &config::load().aliases,
+        env::var_os("HOME"),
+    ) {
+        Ok(a) => a,
+        Err(name) => {
+            eprintln!("{}", t!("unknown-alias", name = name));
+            std::process::exit(1);
+        }
+    };
+    if gui::active() {
+        gui::redirect_stderr();
+    }
+    let (opts, args) = options::extract(args);
+    let insiders = opts
+        .insiders
+        .then(|| editor::Profile::Insiders.bin().to_string());
+    let invoked_as = editor::for_argv0(&args[0], &config::load().invoked_as);
+    editor::init(
+        opts.editor
+            .as_deref()
+            .map(editor::named)
+            .or(opts.code_bin.clone())
+            .or(insiders)
+            .or(invoked_as),
+    );
+    let cfg = config::load();
+    command_utils::set_offline(opts.offline || cfg.offline);
+    notify::set_enabled(opts.notify || cfg.notify);
+    if let Some(timeout) = opts.timeout.or_else(|| {
+        cfg.timeout
+            .and_then(|t| Duration::try_from_secs_f64(t).ok())
+    }) {
+        command_utils::set_timeout(timeout);
+    }
+
+    // cope's own subcommands.  Anything else is for code.
+    match args.get(1).and_then(|a| a.to_str()) {
+        Some("projects") => projects(&args[0], &args[2..], &opts),
+        Some("z") => z(&args[0], &args[2..], &opts),
+        Some("shell") => shell(&args[2..]),
+        Some("exec") => exec(&args[2..]),
+        Some("forward") => forward(&args[2..]),
+        Some("browse") => browse(&args[2..]),
+        Some("record") => record(&args[0], &args[2..]),
+        Some("replay") => replay(&args[2..]),
+        Some("setup") => setup::run(),
+        Some("alias") => alias(&args[2..]),
+        Some("outdated") => outdated(&args[2..]),
+        Some("update-config") => update_config(&args[2..]),
+        Some("update-flags") => update_flags(),
+        Some("reverse") => reverse(&args[2..]),
+        Some("tunnels") => {
+            let home = env::var_os("HOME").unwrap_or_default();
+            for name in tunnel::discover(Path::new(&home), env::var_os("PATH").as_deref()) {
+                println!("{name}");
+            }
+        }
+        Some("stats") => show_stats(),
+        Some("help") => match args.get(2).map(|a| a.to_string_lossy()) {
+            None => print!("{}", help::index()),
+            Some(name) => match help::topic(&name) {
+                Some(text) => print!("{text}"),
+                None => {
+                    eprint!(
+                        "{}\n\n{}",
+                        t!("unknown-help-topic", name = format!("{name:?}")),
+                        help::index()
+                    );
+                    std::process::exit(1);
+                }
+            },
+        },
+        Some("complete") => complete(&args[2..]),
+        Some("shim") => shim::run(&args[2..]),
+        Some("which") => which_editor(),
+        Some("attach") => attach(&args[2..]),
+        Some("k8s") => k8s(&args[2..]),
+        Some("validate") => validate(&args[2..]),
+        Some("info") => info(&args[2..]),
+        Some("doctor") => print!("{}", doctor::format(&doctor::run())),
+        // Only run by watch_ready, so it isn't in the help or completions.
+        Some("wait-ready") => wait_ready(&args[2..]),
+        Some(cmd @ ("status" | "ps" | "logs" | "down")) => lifecycle(cmd, &args[2..]),
+        _ => open(args, &opts),
+    }
+    trace::flush();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::ffi::OsStringExt;
+
+    fn convert_args(args: &[&str]) -> Vec<String> {
+        let oa: Vec<OsString> = args.iter().map(|&s| OsString::from(s)).collect();
+        process_args(oa.into_iter(), &mut Resolver::default())
+            .iter()
+            .map(|s| s.to_str().unwrap().into())
+            .collect()
+    }
+
+    fn assert_file_uri(arg: &str) {
+        assert!(
+            arg.to_owned()
+                .starts_with("--file-uri=vscode-remote://dev-container+"),
+            "{:?}",
+            arg
+        );
+    }
+
+    fn assert_folder_uri(arg: &str) {
+        assert!(
+            arg.to_owned()
+                .starts_with("--folder-uri=vscode-remote://dev-container+"),
+            "{:?}",
+            arg
+        );
+    }
+
+    #[test]
+    fn test_convert_path() {
         let actual = convert_args(&["cope", "Cargo.toml", "/", "--log", "info", "--", "--log"]);
 
         assert_eq!(&actual[0], "code");
@@ -342,11 +2972,160 @@ mod tests {
 
     #[test]
     fn test_needfile() {
-        let actual = convert_args(&["cope", "-d", "one", "two"]);
-        // Expect eprintln
+        // Only one of them is in a container.  Expect eprintln
+        let actual = convert_args(&["cope", "-d", "one", "/two"]);
         assert_eq!(actual[1], "-d");
         assert_eq!(actual[2], "one");
-        assert_eq!(actual[3], "two");
+        assert_eq!(actual[3], "/two");
+    }
+
+    #[test]
+    fn test_diff() {
+        let actual = convert_args(&["cope", "--diff", "Cargo.toml", "src/main.rs"]);
+        assert_eq!(actual[1], "--diff");
+        assert_file_uri(&actual[2]);
+        assert!(actual[2].ends_with("/Cargo.toml"), "{:?}", actual[2]);
+        assert_file_uri(&actual[3]);
+        assert!(actual[3].ends_with("/src/main.rs"), "{:?}", actual[3]);
+        let actual = convert_args(&["cope", "-nd", "src", "src/main.rs"]);
+        assert_eq!(actual[1..3], ["-n", "-d"]);
+        assert_file_uri(&actual[3]);
+        // Not enough files for a diff.
+        assert_eq!(convert_args(&["cope", "-d", "Cargo.toml"])[2], "Cargo.toml");
+    }
+
+    #[test]
+    fn test_add() {
+        let actual = convert_args(&["cope", "--add", "src", "Cargo.toml"]);
+        assert_eq!(actual[1], "--add");
+        assert_folder_uri(&actual[2]);
+        assert!(actual[2].ends_with("/src"), "{:?}", actual[2]);
+        assert_file_uri(&actual[3]);
+        assert_eq!(convert_args(&["cope", "-a", "/"])[1..], ["-a", "/"]);
+    }
+
+    #[test]
+    fn test_mismatched_adds() {
+        let args =
+            |a: &[&str]| -> Vec<CString> { a.iter().map(|s| CString::new(*s).unwrap()).collect() };
+        let c1 = "--folder-uri=vscode-remote://dev-container+01/w";
+        let f1 = "--file-uri=vscode-remote://dev-container+01/w/a.rs";
+        let f2 = "--file-uri=vscode-remote://dev-container+02/w/b.rs";
+        assert!(mismatched_adds(&args(&["code", "--add", c1, f1]), None).is_empty());
+        assert_eq!(
+            mismatched_adds(&args(&["code", "--add", c1, f2]), None),
+            [c1]
+        );
+        assert_eq!(
+            mismatched_adds(&args(&["code", "-a", c1, "local.txt"]), None),
+            [c1]
+        );
+        assert!(mismatched_adds(&args(&["code", "--add", c1]), None).is_empty());
+        assert_eq!(
+            mismatched_adds(&args(&["code", "--add", c1]), Some("")),
+            [c1]
+        );
+        assert!(mismatched_adds(&args(&["code", "--add", "/src"]), Some("")).is_empty());
+        assert!(mismatched_adds(&args(&["code", "--add", c1, "--", "x"]), Some("")).is_empty());
+        assert_eq!(terminal_window(Some("vscode"), false), Some(""));
+        assert_eq!(terminal_window(Some("vscode"), true), None);
+        assert_eq!(terminal_window(None, false), None);
+    }
+
+    #[test]
+    fn test_path_args() {
+        let os = |a: &[&str]| -> Vec<OsString> { a.iter().map(OsString::from).collect() };
+        assert_eq!(
+            path_args(&os(&[
+                "cope",
+                "--profile",
+                "work",
+                "src",
+                "-d",
+                "a",
+                "b",
+                "--log",
+                "x"
+            ])),
+            [false, false, false, true, false, true, true, false, false]
+        );
+        assert_eq!(
+            path_args(&os(&["cope", "-n", "--", "src"])),
+            [false, false, false, false]
+        );
+        assert_eq!(
+            path_args(&os(&["cope", "--add", "src"])),
+            [false, false, true]
+        );
+    }
+
+    #[test]
+    fn test_serve_web_folder() {
+        let os = |a: &[&str]| -> Vec<OsString> { a.iter().map(OsString::from).collect() };
+        assert_eq!(
+            serve_web_folder(&os(&["--profile", "X", "/src/app"])),
+            PathBuf::from("/src/app")
+        );
+        assert_eq!(
+            serve_web_folder(&os(&["-n", "/src"])),
+            PathBuf::from("/src")
+        );
+        assert_eq!(
+            serve_web_folder(&os(&["--profile", "X"])),
+            normalize(OsStr::new("."))
+        );
+    }
+
+    #[test]
+    fn test_merge() {
+        let actual = convert_args(&["cope", "--merge", "a.rs", "b.rs", "base.rs", "out.rs"]);
+        assert_eq!(actual[1], "--merge");
+        for (arg, name) in actual[2..]
+            .iter()
+            .zip(["/a.rs", "/b.rs", "/base.rs", "/out.rs"])
+        {
+            assert_file_uri(arg);
+            assert!(arg.ends_with(name), "{:?}", arg);
+        }
+        // Expect eprintln
+        let actual = convert_args(&["cope", "-m", "a.rs", "/b.rs", "base.rs", "out.rs"]);
+        assert_eq!(actual[2..], ["a.rs", "/b.rs", "base.rs", "out.rs"]);
+    }
+
+    #[test]
+    fn test_chat() {
+        let actual = convert_args(&["cope", "chat", "-a", "Cargo.toml", "what", "is", "src"]);
+        let cwd = env::current_dir().unwrap();
+        assert_eq!(actual[1..3], ["chat", "-a"]);
+        assert_eq!(actual[3], cwd.join("Cargo.toml").to_string_lossy());
+        assert_eq!(actual[4..], ["what", "is", "src"]);
+    }
+
+    #[test]
+    fn test_serve_web() {
+        let actual = convert_args(&[
+            "cope",
+            "serve-web",
+            "--port",
+            "8080",
+            "--server-data-dir",
+            "src",
+        ]);
+        let cwd = env::current_dir().unwrap();
+        assert_eq!(actual[1..4], ["serve-web", "--port", "8080"]);
+        assert_eq!(actual[4], "--server-data-dir");
+        assert_eq!(actual[5], cwd.join("src").to_string_lossy());
+    }
+
+    #[test]
+    fn test_tunnel() {
+        let actual = convert_args(&["cope", "tunnel", "rename", "src", "--extensions-dir", "ext"]);
+        let cwd = env::current_dir().unwrap();
+        assert_eq!(
+            actual[1..5],
+            ["tunnel", "rename", "src", "--extensions-dir"]
+        );
+        assert_eq!(actual[5], cwd.join("ext").to_string_lossy());
     }
 
     #[test]
@@ -355,10 +3134,272 @@ mod tests {
         assert_eq!(actual[1], "--foo");
     }
 
+    #[test]
+    fn test_goto() {
+        assert_eq!(split_position("src/lib.rs:42:7"), ("src/lib.rs", ":42:7"));
+        assert_eq!(split_position("src/lib.rs:42"), ("src/lib.rs", ":42"));
+        assert_eq!(split_position("a:1:2:3"), ("a:1", ":2:3"));
+        assert_eq!(split_position("src/lib.rs"), ("src/lib.rs", ""));
+        assert_eq!(split_position("notes:"), ("notes:", ""));
+
+        let actual = convert_args(&[
+            "cope",
+            "--goto",
+            "src/main.rs:42:7",
+            "/___NO_SUCH_FILE___:3",
+        ]);
+        assert_eq!(actual[1], "--goto");
+        assert_file_uri(&actual[2]);
+        assert!(actual[2].ends_with("/src/main.rs:42:7"), "{:?}", actual[2]);
+        assert_eq!(actual[3], "/___NO_SUCH_FILE___:3");
+    }
+
+    #[test]
+    fn test_goto_short() {
+        let actual = convert_args(&["cope", "-g", "src/main.rs:10"]);
+        assert_eq!(actual[1], "-g");
+        assert!(actual[2].ends_with("/src/main.rs:10"), "{:?}", actual[2]);
+        assert_file_uri(&actual[2]);
+        let actual = convert_args(&["cope", "-rg", "src/main.rs:10:2"]);
+        assert_eq!(actual[1..3], ["-r", "-g"]);
+        assert!(actual[3].ends_with("/src/main.rs:10:2"), "{:?}", actual[3]);
+    }
+
     #[test]
     fn test_multi_sdash() {
-        let actual = convert_args(&["cope", "-wa", "foo"]);
-        // Expect eprintf
+        let actual = convert_args(&["cope", "-wa", "/foo", "Cargo.toml"]);
+        assert_eq!(actual[1..4], ["-w", "-a", "/foo"]);
+        assert_file_uri(&actual[4]);
+        let actual = convert_args(&["cope", "-nd", "/one", "/two"]);
+        assert_eq!(actual[1..], ["-n", "-d", "/one", "/two"]);
+        let actual = convert_args(&["cope", "-ga", "src"]);
+        assert_eq!(actual[1..3], ["-g", "-a"]);
+        assert_folder_uri(&actual[3]);
+    }
+
+    #[test]
+    fn test_by_container() {
+        let c = |args: &[&str]| -> Vec<CString> {
+            args.iter().map(|a| CString::new(*a).unwrap()).collect()
+        };
+        let a = "--file-uri=vscode-remote://dev-container+61/w/a.rs";
+        let b = "--folder-uri=vscode-remote://dev-container+62/w";
+        let b2 = "--file-uri=vscode-remote://dev-container+62/w/b.rs";
+        assert_eq!(
+            by_container(&c(&["code", "-n", a, "--log", "info", b, "/etc/hosts", b2])),
+            vec![
+                c(&["code", "-n", "--log", "info", a]),
+                c(&["code", "-n", "--log", "info", b, b2]),
+                c(&["code", "-n", "--log", "info", "/etc/hosts"]),
+            ]
+        );
+        assert_eq!(
+            by_container(&c(&["code", a, "-r"])),
+            vec![c(&["code", a, "-r"])]
+        );
+        assert_eq!(
+            by_container(&c(&["code", "--diff", a, a, b])),
+            vec![c(&["code", "--diff", a, a]), c(&["code", b])]
+        );
+        assert_eq!(
+            by_container(&c(&["code", a, "--", b])),
+            vec![c(&["code", a, "--", b])]
+        );
+        assert_eq!(authority(b), "dev-container+62");
+        assert_eq!(authority("src/a.rs"), "");
+    }
+
+    #[test]
+    fn test_fit_arg_limit() {
+        let c = |args: &[&str]| -> Vec<CString> {
+            args.iter().map(|a| CString::new(*a).unwrap()).collect()
+        };
+        let args = c(&["code", "-n", "aaaa", "--log", "info", "bbbb", "cccc"]);
+        assert_eq!(fit_arg_limit(args.clone(), 1 << 20), vec![args.clone()]);
+        let limit = args_size(&c(&["code", "-n", "--log", "info", "aaaa", "bbbb"]));
+        assert_eq!(
+            fit_arg_limit(args, limit),
+            vec![
+                c(&["code", "-n", "--log", "info", "aaaa", "bbbb"]),
+                c(&["code", "-r", "--log", "info", "cccc"]),
+            ]
+        );
+        let dashes = c(&["code", "aaaa", "--", "bbbb"]);
+        assert_eq!(fit_arg_limit(dashes.clone(), 1), vec![dashes]);
+        assert!(arg_limit() > 0);
+    }
+
+    #[test]
+    fn test_add_project_args() {
+        let dir = env::temp_dir().join(format!("cope-test-project-args-{}", std::process::id()));
+        fs::create_dir_all(dir.join(".devcontainer")).unwrap();
+        let config_file = dir.join(".devcontainer/devcontainer.json");
+        fs::write(&config_file, r#"{"image": "x"}"#).unwrap();
+        fs::write(
+            dir.join(".devcontainer/cope.json"),
+            r#"{"args": ["--profile", "Backend"]}"#,
+        )
+        .unwrap();
+        let props = dir_properties(&dir, vec![config_file]).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        let uri = format!(
+            "--folder-uri=vscode-remote://dev-container+{}/workspaces/x",
+            props.hex
+        );
+        let mut resolver = Resolver::default();
+        resolver.cache.insert(dir, Some(props));
+        let c = |args: &[&str]| -> Vec<CString> {
+            args.iter().map(|a| CString::new(*a).unwrap()).collect()
+        };
+        let mut commands = vec![
+            c(&["code", "-n", &uri]),
+            c(&["code", "-n", "/etc/hosts"]),
+            c(&["code", "chat", &uri]),
+        ];
+        add_project_args(&mut commands, &resolver);
+        assert_eq!(
+            commands,
+            vec![
+                c(&["code", "--profile", "Backend", "-n", &uri]),
+                c(&["code", "-n", "/etc/hosts"]),
+                c(&["code", "chat", &uri])
+            ]
+        );
+    }
+
+    #[test]
+    fn test_new_windows() {
+        let c = |args: &[&str]| -> Vec<CString> {
+            args.iter().map(|a| CString::new(*a).unwrap()).collect()
+        };
+        let mut one = vec![c(&["code", "a"])];
+        new_windows(&mut one);
+        assert_eq!(one, vec![c(&["code", "a"])]);
+        let mut two = vec![c(&["code", "a"]), c(&["code", "-r", "b"])];
+        new_windows(&mut two);
+        assert_eq!(two, vec![c(&["code", "-n", "a"]), c(&["code", "-r", "b"])]);
+    }
+
+    #[test]
+    fn test_host_path() {
+        let dir = env::temp_dir().join(format!("cope-test-host-path-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let config_file = dir.join("devcontainer.json");
+        fs::write(
+            &config_file,
+            r#"{"image": "x", "workspaceFolder": "/workspaces/cope",
+                "mounts": ["source=/home/me/.ssh,target=/root/.ssh,type=bind"]}"#,
+        )
+        .unwrap();
+        let mut props = dir_properties(&dir, vec![config_file]).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(
+            props.host_path(&dir, Path::new("/workspaces/cope/src/main.rs")),
+            Some(dir.join("src/main.rs"))
+        );
+        assert_eq!(
+            props.host_path(&dir, Path::new("/root/.ssh/config")),
+            Some("/home/me/.ssh/config".into())
+        );
+        assert_eq!(props.host_path(&dir, Path::new("/etc/hosts")), None);
+        props.workspace_mount = mounts::parse("source=/home/me/src,target=/src,type=bind");
+        assert_eq!(
+            props.host_path(&dir, Path::new("/src/cope/a.rs")),
+            Some("/home/me/src/cope/a.rs".into())
+        );
+
+        let uri = format!(
+            "--file-uri=vscode-remote://dev-container+{}/workspaces/my%20app/a.rs",
+            hex(b"/src/my app")
+        );
+        assert_eq!(
+            parse_devcontainer_uri(&uri),
+            Some(("/src/my app".into(), "/workspaces/my app/a.rs".into()))
+        );
+        let id = hex(br#"{"hostPath":"/src/app","configFile":{"$mid":1}}"#);
+        assert_eq!(
+            parse_devcontainer_uri(&format!(
+                "vscode-remote://dev-container+{id}@ssh-remote+box/w"
+            )),
+            Some(("/src/app".into(), "/w".into()))
+        );
+        assert_eq!(parse_devcontainer_uri("/workspaces/app"), None);
+    }
+
+    #[test]
+    fn test_remote() {
+        assert_eq!(
+            convert_args(&["cope", "--remote", "ssh-remote+box", "Cargo.toml", "src"])[1..],
+            ["--remote", "ssh-remote+box", "Cargo.toml", "src"]
+        );
+        assert_eq!(
+            convert_args(&["cope", "Cargo.toml", "--remote=wsl+Ubuntu"])[1..],
+            ["Cargo.toml", "--remote=wsl+Ubuntu"]
+        );
+        assert_file_uri(&convert_args(&["cope", "Cargo.toml", "--", "--remote"])[1]);
+    }
+
+    #[test]
+    fn test_user_uri() {
+        let actual = convert_args(&["cope", "--folder-uri=.", "--file-uri=file:Cargo.toml"]);
+        assert!(
+            actual[1].starts_with("--folder-uri=vscode-remote://dev-container+"),
+            "{actual:?}"
+        );
+        assert!(
+            actual[2].starts_with("--file-uri=vscode-remote://dev-container+"),
+            "{actual:?}"
+        );
+        assert!(actual[2].ends_with("/Cargo.toml"), "{actual:?}");
+        assert_eq!(
+            convert_args(&["cope", "--file-uri=file:///___NO_SUCH___/../a%20b.txt"])[1],
+            "--file-uri=file:///a%20b.txt"
+        );
+        let remote = "--folder-uri=vscode-remote://ssh-remote+box/srv";
+        assert_eq!(convert_args(&["cope", remote])[1], remote);
+    }
+
+    #[test]
+    fn test_with_code_args() {
+        let args = vec![CODE.to_owned(), c"--folder-uri=x".to_owned()];
+        let strs = |a: Vec<CString>| {
+            a.into_iter()
+                .map(|a| a.into_string().unwrap())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            strs(with_code_args(
+                &args,
+                Some("--disable-gpu --profile 'My Work'")
+            )),
+            [
+                "code",
+                "--disable-gpu",
+                "--profile",
+                "My Work",
+                "--folder-uri=x"
+            ]
+        );
+        assert_eq!(
+            strs(with_code_args(&args, None)),
+            ["code", "--folder-uri=x"]
+        );
+        assert_eq!(
+            strs(with_code_args(&args, Some("'open"))),
+            ["code", "--folder-uri=x"]
+        );
+        let tunnel = vec![CODE.to_owned(), c"tunnel".to_owned()];
+        assert_eq!(
+            strs(with_code_args(&tunnel, Some("--disable-gpu"))),
+            ["code", "tunnel"]
+        );
+    }
+
+    #[test]
+    fn test_stdin() {
+        assert_eq!(convert_args(&["cope", "-"])[1..], ["-"]);
+        let actual = convert_args(&["cope", "-", "Cargo.toml"]);
+        assert_eq!(actual[1], "-");
         assert_file_uri(&actual[2]);
     }
 
@@ -374,7 +3415,7 @@ mod tests {
         let bad = OsString::from_vec(vec![0xff]);
 
         let oa = vec![good, bad];
-        let actual = process_args(oa.into_iter());
+        let actual = process_args(oa.into_iter(), &mut Resolver::default());
         assert_file_uri(actual[1].to_str().unwrap());
     }
 
@@ -383,14 +3424,515 @@ mod tests {
         let id = container_id(
             &PathBuf::from("/foo"),
             &PathBuf::from("/foo/.devcontainer/bar/devcontainer.json"),
+            UriVariant::Microsoft,
         );
         assert_eq!(id.chars().next().unwrap(), '{');
+        let id = container_id(
+            Path::new("/my \"repo\"/é"),
+            Path::new("/my \"repo\"/é/.devcontainer/a b/devcontainer.json"),
+            UriVariant::Microsoft,
+        );
+        assert!(
+            id.starts_with(r#"{"hostPath":"/my \"repo\"/é","localDocker":"#),
+            "{id}"
+        );
+        assert!(
+            id.ends_with(concat!(
+                r#""configFile":{"$mid":1,"fsPath":"/my \"repo\"/é/.devcontainer/a b/devcontainer.json","#,
+                r#""external":"file:///my%20%22repo%22/%C3%A9/.devcontainer/a%20b/devcontainer.json","#,
+                r#""path":"/my \"repo\"/é/.devcontainer/a b/devcontainer.json","scheme":"file"}}"#
+            )),
+            "{id}"
+        );
+        let id = container_id(
+            &PathBuf::from("/foo"),
+            &PathBuf::from("/foo/.devcontainer/bar/devcontainer.json"),
+            UriVariant::OpenRemote,
+        );
+        assert_eq!(
+            id,
+            r#"{"hostPath":"/foo","configFile":{"$mid":1,"path":"/foo/.devcontainer/bar/devcontainer.json","scheme":"file"}}"#
+        );
+        let id = container_id(
+            &PathBuf::from("/foo"),
+            &PathBuf::from("/foo/.devcontainer/devcontainer.json"),
+            UriVariant::OpenRemote,
+        );
+        assert_eq!(id, "/foo");
+        let id = container_id(
+            &PathBuf::from("/foo"),
+            &PathBuf::from("/foo/.devcontainer.json"),
+            UriVariant::Microsoft,
+        );
+        assert_eq!(id, "/foo");
+    }
+
+    #[test]
+    fn test_host_file() {
+        let f = HostFile::new("/home/me/a\\b");
+        assert_eq!(
+            (f.fs_path.as_str(), f.path.as_str()),
+            ("/home/me/a\\b", "/home/me/a\\b")
+        );
+        assert_eq!(f.external(), "file:///home/me/a%5Cb");
+        let f = HostFile::new("C:\\Users\\me\\my repo");
+        assert_eq!(f.fs_path, "c:\\Users\\me\\my repo");
+        assert_eq!(f.path, "/c:/Users/me/my repo");
+        assert_eq!(f.external(), "file:///c%3A/Users/me/my%20repo");
+        let f = HostFile::new("\\\\Server\\share\\repo");
+        assert_eq!(f.fs_path, "\\\\Server\\share\\repo");
+        assert_eq!(f.path, "/share/repo");
+        assert_eq!(f.external(), "file://server/share/repo");
+        assert_eq!(
+            container_id(
+                Path::new("C:\\src"),
+                Path::new("C:\\src\\.devcontainer\\a\\devcontainer.json"),
+                UriVariant::OpenRemote
+            ),
+            r#"{"hostPath":"c:\\src","configFile":{"$mid":1,"path":"/c:/src/.devcontainer/a/devcontainer.json","scheme":"file"}}"#
+        );
+    }
+
+    #[test]
+    fn test_container_path() {
+        let root = Path::new("/r");
+        assert_eq!(
+            container_path(root, "/w", Path::new("/r")),
+            Some("/w".into())
+        );
+        assert_eq!(
+            container_path(root, "/w/", Path::new("/r/src/a.rs")),
+            Some("/w/src/a.rs".into())
+        );
+        assert_eq!(container_path(root, "/w", Path::new("/other")), None);
+    }
+
+    #[test]
+    fn test_user() {
+        let dc: DevContainer =
+            serde_jsonc::from_str(r#"{"containerUser": "root", "remoteUser": "vscode"}"#).unwrap();
+        assert_eq!(dc.user(), Some("vscode"));
+        let dc: DevContainer = serde_jsonc::from_str(r#"{"containerUser": "root"}"#).unwrap();
+        assert_eq!(dc.user(), Some("root"));
+        let dc: DevContainer = serde_jsonc::from_str("{}").unwrap();
+        assert_eq!(dc.user(), None);
+    }
+
+    #[test]
+    fn test_label() {
+        let dc: DevContainer =
+            serde_jsonc::from_str(r#"{"name": "Go", "image": "golang"}"#).unwrap();
+        assert_eq!(
+            dc.label(Path::new("/r/.devcontainer/go/devcontainer.json")),
+            "Go"
+        );
+        let dc: DevContainer = serde_jsonc::from_str(r#"{"image": "golang:1"}"#).unwrap();
+        assert_eq!(
+            dc.label(Path::new("/r/.devcontainer/go/devcontainer.json")),
+            "go: golang:1"
+        );
+        assert_eq!(
+            dc.label(Path::new("/r/.devcontainer/devcontainer.json")),
+            "golang:1"
+        );
+        let dc: DevContainer = serde_jsonc::from_str(r#"{"service": "app"}"#).unwrap();
+        assert_eq!(
+            dc.label(Path::new("/r/.devcontainer/devcontainer.json")),
+            "service app"
+        );
+        let dc: DevContainer = serde_jsonc::from_str("{}").unwrap();
+        assert_eq!(
+            dc.label(Path::new("/r/.devcontainer/ci/devcontainer.json")),
+            "ci"
+        );
+        assert_eq!(dc.label(Path::new("/r/.devcontainer.json")), "<no name>");
+    }
+
+    #[test]
+    fn test_summary() {
+        let dc: DevContainer =
+            serde_jsonc::from_str(r#"{"image": "golang:1", "build": {"dockerfile": "D"}}"#)
+                .unwrap();
+        assert_eq!(dc.summary().as_deref(), Some("golang:1"));
+        let dc: DevContainer =
+            serde_jsonc::from_str(r#"{"build": {"dockerfile": "../Dockerfile"}}"#).unwrap();
+        assert_eq!(dc.summary().as_deref(), Some("build ../Dockerfile"));
+        let dc: DevContainer = serde_jsonc::from_str(r#"{"dockerFile": "Dockerfile"}"#).unwrap();
+        assert_eq!(dc.summary().as_deref(), Some("build Dockerfile"));
+        let dc: DevContainer =
+            serde_jsonc::from_str(r#"{"dockerComposeFile": ["a.yml", "b.yml"], "service": "app"}"#)
+                .unwrap();
+        assert_eq!(
+            dc.summary().as_deref(),
+            Some("compose a.yml, b.yml, service app")
+        );
+        let dc: DevContainer = serde_jsonc::from_str("{}").unwrap();
+        assert_eq!(dc.summary(), None);
+    }
+
+    #[test]
+    fn test_customizations() {
+        let dc: DevContainer = serde_jsonc::from_str(
+            r#"{"name": "Go", "customizations": {"cope": {"default": true, "displayName": "Go (fast)", "openMode": "folder"}}}"#,
+        )
+        .unwrap();
+        let c = &dc.customizations.cope;
+        assert!(c.default);
+        assert_eq!(c.open_mode, Some(OpenMode::Folder));
+        assert_eq!(
+            dc.label(Path::new("/r/.devcontainer/devcontainer.json")),
+            "Go (fast)"
+        );
+        let dc: DevContainer =
+            serde_jsonc::from_str(r#"{"customizations": {"vscode": {"extensions": []}}}"#).unwrap();
+        assert!(!dc.customizations.cope.default);
+        assert_eq!(dc.customizations.cope.open_mode, None);
+    }
+
+    #[test]
+    fn test_mounted_any_order() {
+        let dir = env::temp_dir().join(format!("cope-test-mounted-{}", std::process::id()));
+        let root = dir.join("repo");
+        let outside = dir.join("outside");
+        fs::create_dir_all(root.join(".git")).unwrap();
+        fs::create_dir_all(root.join(DEVCONTAINER_DIR)).unwrap();
+        fs::create_dir_all(&outside).unwrap();
+        fs::write(
+            root.join(DEVCONTAINER_DIR).join(CONFIG_FILE),
+            format!(
+                r#"{{"image": "x", "mounts": ["source={},target=/ext,type=bind"]}}"#,
+                outside.display()
+            ),
+        )
+        .unwrap();
+        let file = outside.join("a.txt").into_os_string();
+        let repo = root.clone().into_os_string();
+        for args in [
+            vec!["cope".into(), file.clone(), repo.clone()],
+            vec!["cope".into(), repo.clone(), file.clone()],
+            vec![
+                "cope".into(),
+                "--log".into(),
+                "trace".into(),
+                file.clone(),
+                repo.clone(),
+            ],
+        ] {
+            let actual = process_args(args.into_iter(), &mut Resolver::default());
+            assert!(
+                actual
+                    .iter()
+                    .any(|a| a.to_str().unwrap().ends_with("/ext/a.txt")),
+                "{actual:?}"
+            );
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_in_container() {
+        assert!(in_devcontainer(Some("true")));
+        assert!(!in_devcontainer(None));
+
+        // A repo whose workspace folder is where it is on the host, the
+        // way it looks from inside its container.
+        let root = env::temp_dir().join(format!("cope-test-in-container-{}", std::process::id()));
+        fs::create_dir_all(root.join(".git")).unwrap();
+        fs::create_dir_all(root.join(DEVCONTAINER_DIR)).unwrap();
+        fs::write(
+            root.join(DEVCONTAINER_DIR).join(CONFIG_FILE),
+            format!(r#"{{"workspaceFolder": {:?}}}"#, root.display().to_string()),
+        )
+        .unwrap();
+        let file = root.join("a.txt").into_os_string();
+        let mut resolver = Resolver {
+            in_container: true,
+            ..Default::default()
+        };
+        let actual = process_args(vec!["cope".into(), file.clone()].into_iter(), &mut resolver);
+        assert_eq!(actual[1].to_str().ok(), file.to_str());
+        let actual = process_args(
+            vec!["cope".into(), file].into_iter(),
+            &mut Resolver::default(),
+        );
+        assert!(actual[1].to_str().unwrap().starts_with("--file-uri="));
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_describe_error() {
+        let json_data = "{\n  \"name\": \"x\" \"image\": 1\n}";
+        let er = serde_jsonc::from_str::<DevContainer>(json_data).unwrap_err();
+        assert_eq!(
+            describe_error(&er, json_data),
+            format!("{er}\n      \"name\": \"x\" \"image\": 1\n                  ^")
+        );
+        let er = serde_jsonc::from_str::<DevContainer>("").unwrap_err();
+        assert_eq!(describe_error(&er, ""), er.to_string());
+    }
+
+    #[test]
+    fn test_strict_problems() {
+        assert!(strict_problems("// comment\n{\"image\": \"x\"}").is_empty());
+        for json5 in ["{\"image\": \"x\",}", "{'image': 'x'}"] {
+            let problems = strict_problems(json5);
+            assert_eq!(problems.len(), 2, "{json5}");
+            assert_eq!(problems[1], t!("validate-json5"));
+        }
+        let problems = strict_problems("{\"image\": 1,}");
+        assert_eq!(problems.len(), 3);
+        assert_eq!(strict_problems("{").len(), 1);
+    }
+
+    #[test]
+    fn test_users() {
+        let dir = env::temp_dir().join(format!("cope-test-users-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let config_file = dir.join("devcontainer.json");
+        fs::write(
+            &config_file,
+            r#"{"image": "x", "containerUser": "${localEnv:NOPE:root}"}"#,
+        )
+        .unwrap();
+        let props = dir_properties(&dir, vec![config_file]).unwrap();
+        assert_eq!(props.user.as_deref(), Some("root"));
+        assert_eq!(props.users(), "remoteUser (unset), containerUser root");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_read_json() {
+        let dir = env::temp_dir().join(format!("cope-test-read-json-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let good = dir.join("good.json");
+        let bad = dir.join("bad.json");
+        fs::write(&good, r#"{name: 'ok', /* comment */}"#).unwrap();
+        fs::write(&bad, r#"{"name": "#).unwrap();
+        assert_eq!(
+            read_json(good.clone()).map(|r| r.dev_container.name),
+            Some(Some("ok".into()))
+        );
+        assert!(read_json(bad.clone()).is_none());
+        assert!(read_json(dir.join("missing.json")).is_none());
+        let props = dir_properties(&dir, vec![bad, good.clone()]).unwrap();
+        assert_eq!(props.config_file, good);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_info_report() {
+        let dir = env::temp_dir().join(format!("cope-test-info-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let config_file = dir.join("devcontainer.json");
+        fs::write(
+            &config_file,
+            r#"{
+                "image": "node:22",
+                "remoteUser": "node",
+                "forwardPorts": [3000],
+                "features": {"ghcr.io/devcontainers/features/go:1": {}}
+            }"#,
+        )
+        .unwrap();
+        let props = dir_properties(&dir, vec![config_file.clone()]).unwrap();
+        let report = info_report(&dir, &props);
+        assert!(report.contains(&format!("Config:          {}\n", config_file.display())));
+        assert!(report.contains("From:            node:22\n"));
+        assert!(report.contains("Remote user:     node\n"));
+        assert!(report.contains("Features:        ghcr.io/devcontainers/features/go:1\n"));
+        assert!(report.contains("Forwarded ports: 3000\n"));
+        assert!(report.contains("Mounts:          (none)\n"));
+        assert!(!report.contains("Compose"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_wsl() {
+        assert_eq!(
+            wsl_distro(Some("Ubuntu".into()), Path::new("/___NO_SUCH_FILE___")),
+            None
+        );
+        assert_eq!(
+            wsl_distro(Some("Ubuntu".into()), Path::new("/")),
+            Some("Ubuntu".into())
+        );
+        assert_eq!(wsl_distro(None, Path::new("/")), None);
+        assert_eq!(
+            wsl_uri("Ubuntu", Path::new("/")),
+            "--folder-uri=vscode-remote://wsl+Ubuntu/"
+        );
+        assert_eq!(
+            wsl_uri("Ubuntu", Path::new("/mnt/c/___NO_SUCH_FILE___.txt")),
+            "--file-uri=vscode-remote://wsl+Ubuntu/mnt/c/___NO_SUCH_FILE___.txt"
+        );
+        assert_eq!(
+            wsl_uri("Ubuntu", Path::new("/mnt/c/My Files/#1.txt")),
+            "--file-uri=vscode-remote://wsl+Ubuntu/mnt/c/My%20Files/%231.txt"
+        );
+    }
+
+    #[test]
+    fn test_tunnel_project() {
+        let cur = env::current_dir().unwrap();
+        let mut resolver = Resolver::default();
+        resolver.tunnel_projects.insert(
+            cur.clone(),
+            TunnelProject {
+                tunnel: "build-box".into(),
+                path: Some("/home/me/cope".into()),
+            },
+        );
+        let oa: Vec<OsString> = vec!["cope".into(), "src".into(), "Cargo.toml".into()];
+        let actual = process_args(oa.into_iter(), &mut resolver);
+        assert_eq!(
+            actual[1].to_str().unwrap(),
+            "--folder-uri=vscode-remote://tunnel+build-box/home/me/cope/src"
+        );
+        assert_eq!(
+            actual[2].to_str().unwrap(),
+            "--file-uri=vscode-remote://tunnel+build-box/home/me/cope/Cargo.toml"
+        );
+        assert!(resolver.roots().is_empty());
+    }
+
+    #[test]
+    fn test_workspace_folder_override() {
+        let dir = env::temp_dir().join(format!("cope-test-folder-override-{}", std::process::id()));
+        fs::create_dir_all(dir.join(".devcontainer")).unwrap();
+        let config_file = dir.join(".devcontainer/devcontainer.json");
+        fs::write(
+            &config_file,
+            r#"{"image": "x", "workspaceMount": "source=${localWorkspaceFolder},target=/w,type=bind"}"#,
+        )
+        .unwrap();
+        fs::write(
+            dir.join(".devcontainer/cope.json"),
+            r#"{"workspaceFolder": "/src/cope"}"#,
+        )
+        .unwrap();
+        let props = dir_properties(&dir, vec![config_file]).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(props.folder, "/src/cope");
+        assert_eq!(props.workspace_mount, None);
+        assert_eq!(
+            props.container_path(&dir, &dir.join("Cargo.toml")),
+            Some("/src/cope/Cargo.toml".into())
+        );
+    }
+
+    #[test]
+    fn test_ssh_devcontainer_uri() {
+        assert_eq!(
+            ssh_devcontainer_uri(
+                "me@build",
+                "/home/me/cope",
+                "/workspaces/cope",
+                Path::new("src"),
+                true
+            ),
+            format!(
+                "--folder-uri=vscode-remote://dev-container+{}@ssh-remote+me@build/workspaces/cope/src",
+                hex(b"/home/me/cope")
+            )
+        );
+        assert_eq!(
+            ssh_devcontainer_uri("build", "/r", "/workspaces/my app", Path::new(""), true),
+            format!(
+                "--folder-uri=vscode-remote://dev-container+{}@ssh-remote+build/workspaces/my%20app",
+                hex(b"/r")
+            )
+        );
+        let cur = env::current_dir().unwrap();
+        let mut resolver = Resolver::default();
+        resolver.ssh_projects.insert(
+            cur.clone(),
+            SshProject {
+                host: "build".into(),
+                path: None,
+            },
+        );
+        let oa: Vec<OsString> = vec!["cope".into(), "Cargo.toml".into()];
+        let actual = process_args(oa.into_iter(), &mut resolver);
+        assert!(
+            actual[1]
+                .to_str()
+                .unwrap()
+                .starts_with("--file-uri=vscode-remote://dev-container+")
+        );
+        assert!(actual[1].to_str().unwrap().contains("@ssh-remote+build/"));
+    }
+
+    #[test]
+    fn test_uri_scheme() {
+        assert_eq!(uri_scheme("file:///tmp/x"), Some("file"));
+        assert_eq!(
+            uri_scheme("vscode-remote://ssh-remote+build/srv"),
+            Some("vscode-remote")
+        );
+        assert_eq!(uri_scheme("C://x"), None);
+        assert_eq!(uri_scheme("src/a://b"), None);
+        assert_eq!(uri_scheme("build:/srv"), None);
+        for uri in [
+            "file:///tmp/x",
+            "vscode-remote://ssh-remote+build/srv",
+            "vscode://vscode.git/clone?url=x",
+            "https://github.com/hildjj/cope/blob/main/README.md",
+            "http://localhost:3000/",
+            "git+ssh://git@github.com/hildjj/cope.git",
+        ] {
+            assert_eq!(convert_args(&["cope", uri])[1], uri);
+        }
+    }
+
+    #[test]
+    fn test_ssh_uri() {
+        assert_eq!(
+            ssh_uri("me@build:/home/me/src"),
+            Some("--folder-uri=vscode-remote://ssh-remote+me@build/home/me/src".into())
+        );
+        assert_eq!(
+            ssh_uri("build:/etc/hosts.allow"),
+            Some("--file-uri=vscode-remote://ssh-remote+build/etc/hosts.allow".into())
+        );
+        assert_eq!(
+            ssh_uri("build:/srv/my #1 100%/"),
+            Some("--folder-uri=vscode-remote://ssh-remote+build/srv/my%20%231%20100%25/".into())
+        );
+        assert_eq!(ssh_uri("src/main.rs"), None);
+        assert_eq!(ssh_uri("a:b"), None);
+        assert_eq!(ssh_uri("./x:/y"), None);
+        assert_eq!(
+            convert_args(&["cope", "me@build:/srv/"])[1],
+            "--folder-uri=vscode-remote://ssh-remote+me@build/srv/"
+        );
+    }
+
+    #[test]
+    fn test_attached_uri() {
+        assert_eq!(
+            attached_uri("/redis", "/data", true),
+            format!(
+                "--folder-uri=vscode-remote://attached-container+{}/data",
+                hex(br#"{"containerName":"/redis"}"#)
+            )
+        );
+        assert!(attached_uri("/redis", "/etc/hosts", false).starts_with("--file-uri="));
+        assert_eq!(
+            attached_uri("/a\"b\u{7}", "/my data", true),
+            format!(
+                "--folder-uri=vscode-remote://attached-container+{}/my%20data",
+                hex(br#"{"containerName":"/a\"b\u0007"}"#)
+            )
+        );
     }
 
     #[test]
     fn test_empty_dir() {
-        let mut cache: BTreeMap<PathBuf, Option<DirProperties>> = BTreeMap::new();
-        let u = to_devcontainer_uri(&OsStr::new(std::file!()), "src", &mut cache);
+        let mut resolver = Resolver::new(discovery::from_names(
+            &["devcontainer-dir".into()],
+            &discovery::DevcontainerDir::new("src"),
+        ));
+        let u = to_devcontainer_uri(&OsStr::new(std::file!()), &mut resolver);
         assert_eq!(u, to_cstring(std::file!().into()));
     }
 }
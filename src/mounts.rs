@@ -0,0 +1,160 @@
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+use crate::file_utils::normalize;
+
+/// A bind mount of a host directory into the container.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mount {
+    pub source: PathBuf,
+    pub target: String,
+}
+
+impl Mount {
+    /// Where the host path pth is in the container, if it is inside this
+    /// mount.
+    pub fn container_path(&self, pth: &Path) -> Option<String> {
+        let rel = pth.strip_prefix(&self.source).ok()?;
+        if rel.as_os_str().is_empty() {
+            Some(self.target.clone())
+        } else {
+            Some(format!(
+                "{}/{}",
+                self.target.trim_end_matches('/'),
+                rel.display()
+            ))
+        }
+    }
+
+    /// Where the container path pth is on the host, if it is inside this
+    /// mount.
+    pub fn host_path(&self, pth: &Path) -> Option<PathBuf> {
+        let rel = pth.strip_prefix(&self.target).ok()?;
+        Some(self.source.join(rel))
+    }
+}
+
+/// An entry in devcontainer.json's `mounts`, which can be a string in the
+/// `--mount` syntax or an object with the same keys.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum MountSpec {
+    Text(String),
+    Object {
+        source: Option<String>,
+        target: Option<String>,
+        #[serde(rename = "type")]
+        kind: Option<String>,
+    },
+}
+
+impl MountSpec {
+    /// The mount in the `--mount` syntax, after substitute has replaced its
+    /// variables.
+    pub fn to_mount(&self, substitute: impl Fn(&str) -> String) -> Option<Mount> {
+        match self {
+            MountSpec::Text(s) => parse(&substitute(s)),
+            MountSpec::Object {
+                source,
+                target,
+                kind,
+            } => parse(&format!(
+                "source={},target={},type={}",
+                substitute(source.as_deref()?),
+                target.as_deref()?,
+                kind.as_deref().unwrap_or("bind")
+            )),
+        }
+    }
+}
+
+/// Parse a mount in the `docker run --mount` syntax that devcontainer.json
+/// uses, such as `source=/src,target=/workspace,type=bind`.  Only bind
+/// mounts of absolute host paths are interesting; volumes have no host
+/// path.
+pub fn parse(spec: &str) -> Option<Mount> {
+    let mut source = None;
+    let mut target = None;
+    let mut kind = "bind";
+    for part in spec.split(',') {
+        // Options such as `readonly` are flags, with no value.
+        let (key, value) = part.split_once('=').unwrap_or((part, ""));
+        match key.trim() {
+            "source" | "src" => source = Some(value.trim()),
+            "target" | "dst" | "destination" => target = Some(value.trim()),
+            "type" => kind = value.trim(),
+            _ => {}
+        }
+    }
+    let source = source.filter(|s| kind == "bind" && s.starts_with('/'))?;
+    Some(Mount {
+        source: normalize(source.as_ref()),
+        target: target?.into(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let m =
+            parse("source=/home/me/src/cope/..,target=/src,type=bind,consistency=cached").unwrap();
+        assert_eq!(m.source, PathBuf::from("/home/me/src"));
+        assert_eq!(
+            m.container_path(Path::new("/home/me/src/cope/a.rs")),
+            Some("/src/cope/a.rs".into())
+        );
+        assert_eq!(
+            m.container_path(Path::new("/home/me/src")),
+            Some("/src".into())
+        );
+        assert_eq!(m.container_path(Path::new("/etc")), None);
+        assert_eq!(
+            m.host_path(Path::new("/src/cope/a.rs")),
+            Some("/home/me/src/cope/a.rs".into())
+        );
+        assert_eq!(m.host_path(Path::new("/src")), Some("/home/me/src".into()));
+        assert_eq!(m.host_path(Path::new("/srcx")), None);
+        assert_eq!(parse("source=data,target=/data,type=volume"), None);
+        assert_eq!(
+            parse("source=/a,target=/b,type=bind,readonly"),
+            Some(Mount {
+                source: "/a".into(),
+                target: "/b".into(),
+            })
+        );
+        assert_eq!(parse("src=/a,dst=/b").map(|m| m.target), Some("/b".into()));
+        assert_eq!(parse("nonsense"), None);
+    }
+
+    #[test]
+    fn test_mount_spec() {
+        let specs: Vec<MountSpec> = serde_jsonc::from_str(
+            r#"[
+                "source=${HOME}/.config,target=/home/vscode/.config,type=bind",
+                {"source": "${HOME}/.ssh", "target": "/home/vscode/.ssh", "type": "bind"},
+                {"source": "cache", "target": "/cache", "type": "volume"}
+            ]"#,
+        )
+        .unwrap();
+        let mounts: Vec<Mount> = specs
+            .iter()
+            .filter_map(|m| m.to_mount(|s| s.replace("${HOME}", "/home/me")))
+            .collect();
+        assert_eq!(
+            mounts,
+            vec![
+                Mount {
+                    source: "/home/me/.config".into(),
+                    target: "/home/vscode/.config".into()
+                },
+                Mount {
+                    source: "/home/me/.ssh".into(),
+                    target: "/home/vscode/.ssh".into()
+                }
+            ]
+        );
+    }
+}
@@ -0,0 +1,85 @@
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// The first of `base/name-1`, `base/name-2`, ... that doesn't exist yet.
+pub fn unique_dir(base: &Path, name: &str) -> PathBuf {
+    (1..)
+        .map(|n| base.join(format!("{name}-{n}")))
+        .find(|p| !p.exists())
+        .expect("Ran out of numbers")
+}
+
+/// The command that makes a copy of the repo at dest.  Git repos get a
+/// detached worktree (which is quick, and shares history); anything else is
+/// copied.
+pub fn copy_args(root: &Path, dest: &Path) -> Vec<String> {
+    let root = root.display().to_string();
+    let dest = dest.display().to_string();
+    if Path::new(&root).join(".git").exists() {
+        vec![
+            "git".into(),
+            "-C".into(),
+            root,
+            "worktree".into(),
+            "add".into(),
+            "--detach".into(),
+            dest,
+        ]
+    } else {
+        vec!["cp".into(), "-a".into(), root, dest]
+    }
+}
+
+/// Make the copy.  Returns true on success.
+pub fn provision(root: &Path, dest: &Path) -> bool {
+    if let Some(parent) = dest.parent() {
+        let _ = std::fs::create_dir_all(parent);
+    }
+    let args = copy_args(root, dest);
+    Command::new(&args[0])
+        .args(&args[1..])
+        .status()
+        .is_ok_and(|s| s.success())
+}
+
+/// The path in the copy that corresponds to pth, if pth is inside root.
+pub fn remap(pth: &Path, root: &Path, dest: &Path) -> Option<PathBuf> {
+    pth.strip_prefix(root).ok().map(|rel| dest.join(rel))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    #[test]
+    fn test_unique_dir() {
+        let cur = env::current_dir().unwrap();
+        assert_eq!(
+            unique_dir(&cur, "___NO_SUCH___"),
+            cur.join("___NO_SUCH___-1")
+        );
+    }
+
+    #[test]
+    fn test_copy_args() {
+        let cur = env::current_dir().unwrap();
+        let a = copy_args(&cur, Path::new("/tmp/x"));
+        assert_eq!(a[0], "git");
+        assert_eq!(a[6], "/tmp/x");
+        let a = copy_args(&cur.join("src"), Path::new("/tmp/x"));
+        assert_eq!(a[..2], ["cp", "-a"]);
+    }
+
+    #[test]
+    fn test_remap() {
+        let root = Path::new("/r");
+        let dest = Path::new("/d/r-1");
+        assert_eq!(
+            remap(Path::new("/r/src/a.rs"), root, dest),
+            Some("/d/r-1/src/a.rs".into())
+        );
+        assert_eq!(remap(Path::new("/r"), root, dest), Some("/d/r-1".into()));
+        assert_eq!(remap(Path::new("/x"), root, dest), None);
+    }
+}
@@ -0,0 +1,63 @@
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Turn desktop notifications on or off for the rest of the run.
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// The command that shows a desktop notification.
+pub fn command(macos: bool, title: &str, body: &str) -> Vec<String> {
+    if macos {
+        vec![
+            "osascript".into(),
+            "-e".into(),
+            format!("display notification {body:?} with title {title:?}"),
+        ]
+    } else {
+        vec![
+            "notify-send".into(),
+            "--app-name=cope".into(),
+            title.into(),
+            body.into(),
+        ]
+    }
+}
+
+/// Show a notification, if they are turned on, since the terminal cope was
+/// started from may be buried by now.  Never waits, and never fails.
+pub fn send(title: &str, body: &str) {
+    if !enabled() {
+        return;
+    }
+    let args = command(cfg!(target_os = "macos"), title, body);
+    let _ = Command::new(&args[0])
+        .args(&args[1..])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_command() {
+        assert_eq!(
+            command(false, "cope", "Ready"),
+            vec!["notify-send", "--app-name=cope", "cope", "Ready"]
+        );
+        assert_eq!(
+            command(true, "cope", "say \"hi\"")[2],
+            r#"display notification "say \"hi\"" with title "cope""#
+        );
+    }
+}
@@ -0,0 +1,160 @@
+use std::ffi::OsString;
+use std::time::Duration;
+
+use crate::command_utils::parse_timeout;
+use crate::i18n::t;
+
+/// Flags that are for cope itself, rather than code.  They all start with
+/// `--cope-`, and are removed before the rest of the args are processed.
+#[derive(Debug, Default, PartialEq)]
+pub struct CopeOptions {
+    /// Open a fresh copy of the repo, so it gets its own container.
+    pub new_container: bool,
+
+    /// Don't query the network or the container engine.
+    pub offline: bool,
+
+    /// How long to wait for docker, registries and the like.
+    pub timeout: Option<Duration>,
+
+    /// Open the paths on this VS Code tunnel.  Empty means pick one of the
+    /// tunnels that can be found.
+    pub tunnel: Option<String>,
+
+    /// Show a desktop notification when slow work finishes or launching
+    /// fails.
+    pub notify: bool,
+
+    /// The editor binary to run, instead of `code`.
+    pub code_bin: Option<String>,
+
+    /// The editor to run this time, by name or path.  Wins over code_bin.
+    pub editor: Option<String>,
+
+    /// Open each repo in its codespace, if it has one.
+    pub codespace: bool,
+
+    /// Run VS Code Insiders, unless there is a code_bin.
+    pub insiders: bool,
+
+    /// Expand glob patterns in the path args.
+    pub glob: bool,
+
+    /// Also open the files named in this file, or on stdin for `-`.
+    pub files_from: Option<String>,
+}
+
+/// Pull the cope flags out of args.  Anything after `--` is left alone.
+pub fn extract(args: Vec<OsString>) -> (CopeOptions, Vec<OsString>) {
+    let mut opts = CopeOptions::default();
+    let mut rest: Vec<OsString> = Vec::with_capacity(args.len());
+    let mut it = args.into_iter();
+    while let Some(a) = it.next() {
+        match a.to_str() {
+            Some("--") => {
+                rest.push(a);
+                rest.extend(it.by_ref());
+            }
+            Some("--cope-new-container") => opts.new_container = true,
+            Some("--cope-offline") => opts.offline = true,
+            Some("--cope-notify") => opts.notify = true,
+            Some("--cope-tunnel") => opts.tunnel = Some(String::new()),
+            Some("--cope-codespace") => {
+                opts.codespace = true;
+            }
+            Some("--cope-insiders") => {
+                opts.insiders = true;
+            }
+            Some("--cope-glob") => opts.glob = true,
+            Some(f) if f.starts_with("--cope-editor=") => {
+                opts.editor = Some(f["--cope-editor=".len()..].into());
+            }
+            Some(f) if f.starts_with("--cope-code-bin=") => {
+                opts.code_bin = Some(f["--cope-code-bin=".len()..].into());
+            }
+            Some(f) if f.starts_with("--cope-files-from=") => {
+                opts.files_from = Some(f["--cope-files-from=".len()..].into());
+            }
+            Some(f) if f.starts_with("--cope-tunnel=") => {
+                opts.tunnel = Some(f["--cope-tunnel=".len()..].into());
+            }
+            Some("--cope-timeout") => {
+                let secs = it.next().unwrap_or_default();
+                set_timeout(&mut opts, &secs.to_string_lossy());
+            }
+            Some(f) if f.starts_with("--cope-timeout=") => {
+                set_timeout(&mut opts, &f["--cope-timeout=".len()..])
+            }
+            Some(f) if f.starts_with("--cope-") => {
+                eprintln!("{}", t!("unknown-flag", flag = format!("{f:?}")))
+            }
+            _ => rest.push(a),
+        }
+    }
+    (opts, rest)
+}
+
+/// The timeout from `--cope-timeout`, or a warning if secs isn't one.
+fn set_timeout(opts: &mut CopeOptions, secs: &str) {
+    match parse_timeout(secs) {
+        Some(t) => opts.timeout = Some(t),
+        None => eprintln!("{}", t!("bad-timeout", value = format!("{secs:?}"))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn os(args: &[&str]) -> Vec<OsString> {
+        args.iter().map(OsString::from).collect()
+    }
+
+    #[test]
+    fn test_extract() {
+        let (opts, rest) = extract(os(&["cope", "--cope-new-container", ".", "--cope-offline"]));
+        assert!(opts.new_container);
+        assert!(opts.offline);
+        assert_eq!(rest, os(&["cope", "."]));
+
+        let (opts, _) = extract(os(&["cope", "--cope-timeout=1.5"]));
+        assert_eq!(opts.timeout, Some(Duration::from_millis(1500)));
+        let (opts, _) = extract(os(&["cope", "--cope-timeout=never"]));
+        assert_eq!(opts.timeout, None);
+        let (opts, rest) = extract(os(&["cope", "--cope-timeout", "5", "."]));
+        assert_eq!(opts.timeout, Some(Duration::from_secs(5)));
+        assert_eq!(rest, os(&["cope", "."]));
+
+        let (opts, _) = extract(os(&["cope", "--cope-tunnel"]));
+        assert_eq!(opts.tunnel, Some(String::new()));
+        let (opts, _) = extract(os(&["cope", "--cope-tunnel=devbox"]));
+        assert_eq!(opts.tunnel, Some("devbox".into()));
+
+        let (opts, _) = extract(os(&["cope", "--cope-notify"]));
+        assert!(opts.notify);
+
+        let (opts, _) = extract(os(&["cope", "--cope-code-bin=/opt/vscode/bin/code"]));
+        assert_eq!(opts.code_bin, Some("/opt/vscode/bin/code".into()));
+
+        let (opts, _) = extract(os(&["cope", "--cope-codespace"]));
+        assert!(opts.codespace);
+
+        let (opts, _) = extract(os(&["cope", "--cope-editor=cursor"]));
+        assert_eq!(opts.editor, Some("cursor".into()));
+
+        let (opts, args) = extract(os(&["cope", "--cope-insiders", "."]));
+        assert!(opts.insiders);
+        assert_eq!(args, os(&["cope", "."]));
+
+        let (opts, _) = extract(os(&["cope", "--cope-files-from=-"]));
+        assert_eq!(opts.files_from, Some("-".into()));
+
+        let (opts, args) = extract(os(&["cope", "--cope-glob", "src/*.rs"]));
+        assert!(opts.glob);
+        assert_eq!(args, os(&["cope", "src/*.rs"]));
+
+        let (opts, rest) = extract(os(&["cope", "--cope-bogus", "--", "--cope-new-container"]));
+        assert_eq!(opts, CopeOptions::default());
+        assert_eq!(rest, os(&["cope", "--", "--cope-new-container"]));
+    }
+}
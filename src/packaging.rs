@@ -0,0 +1,106 @@
+use std::ffi::{CString, OsStr};
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+
+use crate::editor::Profile;
+
+/// A packaged editor that doesn't put its command on the PATH.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Packaging {
+    /// The flatpak application ID.
+    Flatpak(&'static str),
+    /// The snap name.
+    Snap(&'static str),
+}
+
+impl Packaging {
+    /// The start of the command line that runs the packaged editor.
+    pub fn prefix(&self) -> Vec<&'static str> {
+        match self {
+            Packaging::Flatpak(id) => vec!["flatpak", "run", id],
+            Packaging::Snap(name) => vec!["snap", "run", name],
+        }
+    }
+}
+
+/// Look for a flatpak (user or system-wide) or snap install of the editor.
+/// This only checks the filesystem, so it is cheap enough to do on every
+/// launch that can't find the editor.
+pub fn detect(profile: Profile, home: &Path, root: &Path) -> Option<Packaging> {
+    if let Some(id) = profile.flatpak_id() {
+        let flatpak = [
+            home.join(".local/share/flatpak/app").join(id),
+            root.join("var/lib/flatpak/app").join(id),
+        ];
+        if flatpak.iter().any(|p| p.is_dir()) {
+            return Some(Packaging::Flatpak(id));
+        }
+    }
+    profile
+        .snap_name()
+        .filter(|name| root.join("snap").join(name).join("current").exists())
+        .map(Packaging::Snap)
+}
+
+/// An editor binary setting that is really a command line, such as
+/// `flatpak run com.visualstudio.code`, split into words.  A path with
+/// spaces in it that exists is still just a path.
+pub fn words(bin: &str) -> Option<Vec<&str>> {
+    (bin.contains(char::is_whitespace) && !Path::new(bin).exists())
+        .then(|| bin.split_whitespace().collect())
+}
+
+/// The command line that runs prefix with the same arguments.  args[0] is
+/// the editor, which is replaced by the prefix.  Everything after it is
+/// passed through untouched.
+pub fn wrap<S: AsRef<OsStr>>(prefix: &[S], args: &[CString]) -> Vec<CString> {
+    prefix
+        .iter()
+        .map(|p| CString::new(p.as_ref().as_bytes()).expect("Editor command with NUL"))
+        .chain(args[1..].iter().cloned())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect() {
+        let none = Path::new("/___NO_SUCH_DIR___");
+        assert_eq!(detect(Profile::Code, none, none), None);
+        assert_eq!(detect(Profile::Cursor, none, none), None);
+    }
+
+    #[test]
+    fn test_words() {
+        assert_eq!(words("code"), None);
+        assert_eq!(
+            words("flatpak run com.visualstudio.code"),
+            Some(vec!["flatpak", "run", "com.visualstudio.code"])
+        );
+    }
+
+    #[test]
+    fn test_wrap() {
+        let args = vec![
+            c"code".to_owned(),
+            c"--new-window".to_owned(),
+            c"--".to_owned(),
+        ];
+        assert_eq!(
+            wrap(&Packaging::Flatpak("com.visualstudio.code").prefix(), &args),
+            vec![
+                c"flatpak".to_owned(),
+                c"run".to_owned(),
+                c"com.visualstudio.code".to_owned(),
+                c"--new-window".to_owned(),
+                c"--".to_owned()
+            ]
+        );
+        assert_eq!(
+            wrap(&Packaging::Snap("code").prefix(), &args)[..3],
+            [c"snap".to_owned(), c"run".to_owned(), c"code".to_owned()]
+        );
+    }
+}
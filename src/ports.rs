@@ -0,0 +1,132 @@
+use serde::Deserialize;
+
+/// An entry in `forwardPorts`, which is either a port number or a string
+/// like `"db:5432"`.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum PortSpec {
+    Number(u16),
+    Host(String),
+}
+
+impl PortSpec {
+    /// The port number, if there is a valid one.
+    pub fn port(&self) -> Option<u16> {
+        match self {
+            PortSpec::Number(n) => Some(*n),
+            PortSpec::Host(s) => s.rsplit(':').next()?.parse().ok(),
+        }
+    }
+}
+
+/// Parse the output of `docker port <id>`, which looks like
+/// `3000/tcp -> 0.0.0.0:49153`, into (container port, host port) pairs.
+pub fn parse_docker_port(output: &str) -> Vec<(u16, u16)> {
+    let mut res: Vec<(u16, u16)> = output
+        .lines()
+        .filter_map(|line| {
+            let (container, host) = line.split_once(" -> ")?;
+            let container = container.split('/').next()?.parse().ok()?;
+            let host = host.rsplit(':').next()?.parse().ok()?;
+            Some((container, host))
+        })
+        .collect();
+    // IPv4 and IPv6 bindings usually show up as duplicates.
+    res.dedup();
+    res
+}
+
+/// Which localhost port to browse to.  A published port is reached through
+/// its host port; a forwarded one is (normally) on the same port.  With no
+/// port requested, the first forwarded port wins, then the first published
+/// one.
+pub fn browse_port(
+    wanted: Option<u16>,
+    forwarded: &[u16],
+    published: &[(u16, u16)],
+) -> Option<u16> {
+    match wanted {
+        Some(p) => Some(
+            published
+                .iter()
+                .find(|(container, _)| *container == p)
+                .map_or(p, |(_, host)| *host),
+        ),
+        None => forwarded
+            .first()
+            .copied()
+            .or_else(|| published.first().map(|(_, host)| *host)),
+    }
+}
+
+/// Parse `port[:host-port]`.  The host port defaults to the container port.
+pub fn parse_forward(spec: &str) -> Option<(u16, u16)> {
+    match spec.split_once(':') {
+        Some((port, host)) => Some((port.parse().ok()?, host.parse().ok()?)),
+        None => {
+            let port = spec.parse().ok()?;
+            Some((port, port))
+        }
+    }
+}
+
+/// Arguments for socat that listen on localhost:host_port, and for each
+/// connection run a second socat inside the container that connects to the
+/// container's port.  This needs socat on both sides, but doesn't need the
+/// container to have been created with the port published.
+pub fn forward_args(id: &str, port: u16, host_port: u16) -> Vec<String> {
+    vec![
+        "socat".into(),
+        format!("TCP-LISTEN:{host_port},bind=127.0.0.1,fork,reuseaddr"),
+        format!("EXEC:docker exec -i {id} socat STDIO TCP\\:localhost\\:{port}"),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_forward() {
+        assert_eq!(parse_forward("3000"), Some((3000, 3000)));
+        assert_eq!(parse_forward("3000:8080"), Some((3000, 8080)));
+        assert_eq!(parse_forward("x"), None);
+        assert_eq!(parse_forward("3000:"), None);
+        assert_eq!(parse_forward("99999"), None);
+    }
+
+    #[test]
+    fn test_port_spec() {
+        let specs: Vec<PortSpec> = serde_jsonc::from_str(r#"[3000, "db:5432", "bad"]"#).unwrap();
+        let ports: Vec<Option<u16>> = specs.iter().map(PortSpec::port).collect();
+        assert_eq!(ports, vec![Some(3000), Some(5432), None]);
+    }
+
+    #[test]
+    fn test_parse_docker_port() {
+        let res = parse_docker_port(
+            "3000/tcp -> 0.0.0.0:49153\n3000/tcp -> [::]:49153\n5432/tcp -> 0.0.0.0:5432\n",
+        );
+        assert_eq!(res, vec![(3000, 49153), (5432, 5432)]);
+    }
+
+    #[test]
+    fn test_browse_port() {
+        let published = [(3000, 49153)];
+        assert_eq!(browse_port(Some(3000), &[], &published), Some(49153));
+        assert_eq!(browse_port(Some(8080), &[], &published), Some(8080));
+        assert_eq!(browse_port(None, &[8000], &published), Some(8000));
+        assert_eq!(browse_port(None, &[], &published), Some(49153));
+        assert_eq!(browse_port(None, &[], &[]), None);
+    }
+
+    #[test]
+    fn test_forward_args() {
+        let a = forward_args("abc", 3000, 8080);
+        assert_eq!(a[1], "TCP-LISTEN:8080,bind=127.0.0.1,fork,reuseaddr");
+        assert_eq!(
+            a[2],
+            "EXEC:docker exec -i abc socat STDIO TCP\\:localhost\\:3000"
+        );
+    }
+}
@@ -0,0 +1,131 @@
+use dialoguer::Select;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::discovery::{Strategy, has_configs};
+use crate::i18n::t;
+
+/// How long a project scan stays valid before it is redone.
+const CACHE_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// The results of scanning the configured project roots, as stored in the
+/// cache file.
+#[derive(Deserialize, Serialize, Debug, PartialEq)]
+pub struct ProjectCache {
+    pub roots: Vec<PathBuf>,
+    pub scanned: u64,
+    pub projects: Vec<PathBuf>,
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("Time before epoch")
+        .as_secs()
+}
+
+/// Find every project that has a devcontainer config, looking in each of
+/// the roots and their immediate subdirectories.
+pub fn scan(roots: &[PathBuf], strategies: &[Box<dyn Strategy>]) -> Vec<PathBuf> {
+    let mut res: Vec<PathBuf> = Vec::new();
+    for root in roots {
+        if has_configs(root, strategies) {
+            res.push(root.clone());
+        }
+        if let Ok(entries) = fs::read_dir(root) {
+            res.extend(
+                entries
+                    .filter_map(|e| e.ok().map(|e| e.path()))
+                    .filter(|p| p.is_dir() && has_configs(p, strategies)),
+            );
+        }
+    }
+    res.sort();
+    res.dedup();
+    res
+}
+
+/// Is the cache still usable for these roots?
+pub fn is_fresh(cache: &ProjectCache, roots: &[PathBuf], now: u64) -> bool {
+    cache.roots == roots && now.saturating_sub(cache.scanned) < CACHE_TTL.as_secs()
+}
+
+/// Read the cached scan, rescanning (and rewriting the cache) if it is
+/// missing, stale, for different roots, or a refresh was requested.
+pub fn cached_scan(
+    cache_file: &Path,
+    roots: &[PathBuf],
+    strategies: &[Box<dyn Strategy>],
+    refresh: bool,
+) -> Vec<PathBuf> {
+    if !refresh
+        && let Ok(json_data) = fs::read_to_string(cache_file)
+        && let Ok(cache) = serde_jsonc::from_str::<ProjectCache>(&json_data)
+        && is_fresh(&cache, roots, now())
+    {
+        return cache.projects;
+    }
+
+    let cache = ProjectCache {
+        roots: roots.to_vec(),
+        scanned: now(),
+        projects: scan(roots, strategies),
+    };
+    // A cache that can't be written just means we scan again next time.
+    if let Some(parent) = cache_file.parent() {
+        let _ = fs::create_dir_all(parent);
+    }
+    if let Ok(json_data) = serde_jsonc::to_string_pretty(&cache) {
+        let _ = fs::write(cache_file, json_data);
+    }
+    cache.projects
+}
+
+/// Ask on stderr which project is desired.
+pub fn choose(projects: &[PathBuf]) -> Option<PathBuf> {
+    let items = projects.iter().map(|p| {
+        format!(
+            "{} ({})",
+            p.file_name().unwrap_or(p.as_os_str()).to_string_lossy(),
+            p.display()
+        )
+    });
+    Select::new()
+        .with_prompt(t!("choose-project"))
+        .items(items)
+        .default(0)
+        .interact_opt()
+        .expect("Selection failed")
+        .map(|i| projects[i].clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::discovery::{DevcontainerDir, default_names, from_names};
+    use std::env;
+
+    #[test]
+    fn test_scan() {
+        let cur = env::current_dir().unwrap();
+        // The crate itself has a .devcontainer, and so does nothing in src/.
+        let strategies = from_names(&default_names(), &DevcontainerDir::new(".devcontainer"));
+        let res = scan(&[cur.clone(), cur.join("src")], &strategies);
+        assert_eq!(res, vec![cur]);
+    }
+
+    #[test]
+    fn test_is_fresh() {
+        let roots = vec![PathBuf::from("/src")];
+        let cache = ProjectCache {
+            roots: roots.clone(),
+            scanned: 1000,
+            projects: vec![],
+        };
+        assert!(is_fresh(&cache, &roots, 1000));
+        assert!(!is_fresh(&cache, &roots, 1000 + CACHE_TTL.as_secs()));
+        assert!(!is_fresh(&cache, &[PathBuf::from("/other")], 1000));
+    }
+}
@@ -0,0 +1,74 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The inputs to one invocation of cope, and the `code` command line that
+/// they turned into.
+#[derive(Deserialize, Serialize, Debug, PartialEq)]
+pub struct Recording {
+    pub cwd: PathBuf,
+    pub args: Vec<String>,
+    pub plan: Vec<String>,
+}
+
+pub fn write(file_name: &Path, rec: &Recording) {
+    let json_data = serde_jsonc::to_string_pretty(rec).expect("Serializing recording");
+    fs::write(file_name, json_data + "\n")
+        .unwrap_or_else(|er| panic!("Error writing file {file_name:?} {er}"));
+}
+
+pub fn read(file_name: &Path) -> Recording {
+    let json_data = fs::read_to_string(file_name)
+        .unwrap_or_else(|er| panic!("Error reading file {file_name:?} {er}"));
+    serde_jsonc::from_str(&json_data)
+        .unwrap_or_else(|er| panic!("Error parsing JSON {file_name:?} {er}"))
+}
+
+/// Describe how the actual plan differs from the recorded one, one line per
+/// difference.  Empty if they are the same.
+pub fn diff(recorded: &[String], actual: &[String]) -> Vec<String> {
+    let mut res: Vec<String> = Vec::new();
+    for i in 0..recorded.len().max(actual.len()) {
+        match (recorded.get(i), actual.get(i)) {
+            (Some(r), Some(a)) if r == a => {}
+            (Some(r), Some(a)) => res.push(format!("[{i}] expected {r:?}, got {a:?}")),
+            (Some(r), None) => res.push(format!("[{i}] expected {r:?}, got nothing")),
+            (None, Some(a)) => res.push(format!("[{i}] unexpected {a:?}")),
+            (None, None) => unreachable!(),
+        }
+    }
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strs(v: &[&str]) -> Vec<String> {
+        v.iter().map(|&s| s.into()).collect()
+    }
+
+    #[test]
+    fn test_diff() {
+        assert!(diff(&strs(&["a", "b"]), &strs(&["a", "b"])).is_empty());
+        assert_eq!(
+            diff(&strs(&["a", "b"]), &strs(&["a", "c", "d"])),
+            vec![r#"[1] expected "b", got "c""#, r#"[2] unexpected "d""#]
+        );
+        assert_eq!(
+            diff(&strs(&["a", "b"]), &strs(&["a"])),
+            vec![r#"[1] expected "b", got nothing"#]
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let rec = Recording {
+            cwd: "/r".into(),
+            args: strs(&["cope", "."]),
+            plan: strs(&["code", "--folder-uri=x"]),
+        };
+        let json_data = serde_jsonc::to_string(&rec).unwrap();
+        assert_eq!(serde_jsonc::from_str::<Recording>(&json_data).unwrap(), rec);
+    }
+}
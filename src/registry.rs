@@ -0,0 +1,201 @@
+use std::process::Command;
+
+use crate::command_utils::capture;
+
+/// A reference to an image or feature in an OCI registry, such as
+/// `ghcr.io/devcontainers/features/node:1` or `node:18-bullseye`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImageRef {
+    pub registry: String,
+    pub repository: String,
+    pub tag: String,
+}
+
+impl ImageRef {
+    /// Parse a reference the way docker does: the first component is a
+    /// registry if it looks like a host name, and Docker Hub otherwise.
+    /// References pinned by digest can't be upgraded, so give None.
+    pub fn parse(reference: &str) -> Option<ImageRef> {
+        if reference.contains('@') {
+            return None;
+        }
+        let (name, tag) = match reference.rsplit_once(':') {
+            Some((n, t)) if !t.contains('/') => (n, t),
+            _ => (reference, "latest"),
+        };
+        let (registry, repository) = match name.split_once('/') {
+            Some((first, rest))
+                if first.contains('.') || first.contains(':') || first == "localhost" =>
+            {
+                (first.to_string(), rest.to_string())
+            }
+            Some(_) => ("docker.io".to_string(), name.to_string()),
+            None => ("docker.io".to_string(), format!("library/{name}")),
+        };
+        Some(ImageRef {
+            registry,
+            repository,
+            tag: tag.to_string(),
+        })
+    }
+
+    /// The base URL of the registry's API.
+    fn api(&self) -> String {
+        if self.registry == "docker.io" {
+            "https://registry-1.docker.io".into()
+        } else {
+            format!("https://{}", self.registry)
+        }
+    }
+}
+
+/// A tag split into its version numbers and everything else, so that
+/// `18-bullseye` is only compared with other `N-bullseye` tags.
+#[derive(Debug, PartialEq)]
+pub struct TagVersion {
+    pub prefix: String,
+    pub numbers: Vec<u64>,
+    pub suffix: String,
+}
+
+impl TagVersion {
+    /// None for tags without a version, such as `latest`.
+    pub fn parse(tag: &str) -> Option<TagVersion> {
+        let prefix = if tag.starts_with('v') { "v" } else { "" };
+        let rest = &tag[prefix.len()..];
+        let end = rest
+            .find(|c: char| !c.is_ascii_digit() && c != '.')
+            .unwrap_or(rest.len());
+        let (version, suffix) = rest.split_at(end);
+        let numbers: Vec<u64> = version
+            .split('.')
+            .map(|n| n.parse().ok())
+            .collect::<Option<Vec<u64>>>()?;
+        Some(TagVersion {
+            prefix: prefix.into(),
+            numbers,
+            suffix: suffix.into(),
+        })
+    }
+
+    fn same_shape(&self, other: &TagVersion) -> bool {
+        self.prefix == other.prefix
+            && self.suffix == other.suffix
+            && self.numbers.len() == other.numbers.len()
+    }
+}
+
+/// The highest tag with the same shape as current that is newer than it.
+pub fn newest_tag(current: &str, tags: &[String]) -> Option<String> {
+    let cur = TagVersion::parse(current)?;
+    tags.iter()
+        .filter_map(|t| TagVersion::parse(t).map(|v| (v, t)))
+        .filter(|(v, _)| v.same_shape(&cur) && v.numbers > cur.numbers)
+        .max_by(|(a, _), (b, _)| a.numbers.cmp(&b.numbers))
+        .map(|(_, t)| t.clone())
+}
+
+/// Pull a parameter out of a `WWW-Authenticate: Bearer realm="...",...`
+/// header.
+pub fn auth_param(header: &str, name: &str) -> Option<String> {
+    let start = header.find(&format!("{name}=\""))? + name.len() + 2;
+    let len = header[start..].find('"')?;
+    Some(header[start..start + len].to_string())
+}
+
+/// Get an anonymous pull token, if the registry wants one.  Registries say
+/// where to get it in the WWW-Authenticate header of an unauthorized
+/// response.
+fn token(img: &ImageRef, url: &str) -> Option<String> {
+    let headers = capture(Command::new("curl").args(["-sS", "-o", "/dev/null", "-D", "-", url]))?;
+    let header = headers
+        .lines()
+        .find(|l| l.to_lowercase().starts_with("www-authenticate:"))?;
+    let realm = auth_param(header, "realm")?;
+    let mut token_url = format!("{realm}?scope=repository:{}:pull", img.repository);
+    if let Some(service) = auth_param(header, "service") {
+        token_url.push_str(&format!("&service={service}"));
+    }
+    let body = capture(Command::new("curl").args(["-sSf", &token_url]))?;
+    let v: serde_jsonc::Value = serde_jsonc::from_str(&body).ok()?;
+    v.get("token")
+        .or_else(|| v.get("access_token"))?
+        .as_str()
+        .map(String::from)
+}
+
+/// Every tag the registry has for the image.  None if the registry can't
+/// be reached.
+pub fn list_tags(img: &ImageRef) -> Option<Vec<String>> {
+    let url = format!("{}/v2/{}/tags/list?n=1000", img.api(), img.repository);
+    let mut cmd = Command::new("curl");
+    cmd.args(["-sSfL", &url]);
+    if let Some(t) = token(img, &url) {
+        cmd.args(["-H", &format!("Authorization: Bearer {t}")]);
+    }
+    let body = capture(&mut cmd)?;
+    let v: serde_jsonc::Value = serde_jsonc::from_str(&body).ok()?;
+    Some(
+        v.get("tags")?
+            .as_array()?
+            .iter()
+            .filter_map(|t| t.as_str().map(String::from))
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_ref() {
+        assert_eq!(
+            ImageRef::parse("node:18"),
+            Some(ImageRef {
+                registry: "docker.io".into(),
+                repository: "library/node".into(),
+                tag: "18".into()
+            })
+        );
+        let r = ImageRef::parse("ghcr.io/devcontainers/features/node:1").unwrap();
+        assert_eq!(r.registry, "ghcr.io");
+        assert_eq!(r.repository, "devcontainers/features/node");
+        let r = ImageRef::parse("localhost:5000/me/app").unwrap();
+        assert_eq!(
+            (r.registry.as_str(), r.tag.as_str()),
+            ("localhost:5000", "latest")
+        );
+        assert_eq!(ImageRef::parse("me/app:2").unwrap().repository, "me/app");
+        assert_eq!(ImageRef::parse("node@sha256:abcd"), None);
+    }
+
+    #[test]
+    fn test_newest_tag() {
+        let tags: Vec<String> = [
+            "16-bullseye",
+            "18",
+            "20-bullseye",
+            "22-bookworm",
+            "latest",
+            "19.1-bullseye",
+        ]
+        .iter()
+        .map(|&s| s.into())
+        .collect();
+        assert_eq!(newest_tag("18-bullseye", &tags), Some("20-bullseye".into()));
+        assert_eq!(newest_tag("20-bullseye", &tags), None);
+        assert_eq!(newest_tag("latest", &tags), None);
+        let tags: Vec<String> = vec!["1.2.3".into(), "1.10.0".into(), "v2".into()];
+        assert_eq!(newest_tag("1.9.9", &tags), Some("1.10.0".into()));
+        assert_eq!(newest_tag("v1", &tags), Some("v2".into()));
+    }
+
+    #[test]
+    fn test_auth_param() {
+        let h = r#"www-authenticate: Bearer realm="https://ghcr.io/token",service="ghcr.io",scope="repository:a:pull""#;
+        assert_eq!(auth_param(h, "realm"), Some("https://ghcr.io/token".into()));
+        assert_eq!(auth_param(h, "service"), Some("ghcr.io".into()));
+        assert_eq!(auth_param(h, "nope"), None);
+    }
+}
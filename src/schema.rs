@@ -0,0 +1,265 @@
+use serde_jsonc::Value;
+
+use crate::i18n::t;
+
+/// What a property's value may be, as far as the devcontainer.json schema
+/// says.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Type {
+    Any,
+    Bool,
+    Number,
+    Str,
+    Array,
+    Object,
+    StrOrArray,
+    /// A lifecycle command: a string, an array of arguments, or an object
+    /// of named commands.
+    Command,
+    /// A port number, or a `host:port` string.
+    Port,
+    Enum(&'static [&'static str]),
+    /// An object whose own properties are known.
+    Props(&'static [(&'static str, Type)]),
+}
+
+const BUILD: &[(&str, Type)] = &[
+    ("dockerfile", Type::Str),
+    ("dockerFile", Type::Str),
+    ("context", Type::Str),
+    ("target", Type::Str),
+    ("args", Type::Object),
+    ("cacheFrom", Type::StrOrArray),
+    ("options", Type::Array),
+];
+const HOST_REQUIREMENTS: &[(&str, Type)] = &[
+    ("cpus", Type::Number),
+    ("memory", Type::Str),
+    ("storage", Type::Str),
+    ("gpu", Type::Any),
+];
+
+/// The top-level properties of devcontainer.json, from the published schema
+/// at https://containers.dev/implementors/json_schema/, covering image,
+/// Dockerfile and Compose configs.
+pub const PROPERTIES: &[(&str, Type)] = &[
+    ("$schema", Type::Str),
+    ("name", Type::Str),
+    ("image", Type::Str),
+    ("build", Type::Props(BUILD)),
+    ("dockerFile", Type::Str),
+    ("context", Type::Str),
+    ("dockerComposeFile", Type::StrOrArray),
+    ("service", Type::Str),
+    ("runServices", Type::Array),
+    ("workspaceFolder", Type::Str),
+    ("workspaceMount", Type::Str),
+    ("mounts", Type::Array),
+    ("forwardPorts", Type::Array),
+    ("appPort", Type::Any),
+    ("portsAttributes", Type::Object),
+    ("otherPortsAttributes", Type::Object),
+    ("runArgs", Type::Array),
+    (
+        "shutdownAction",
+        Type::Enum(&["none", "stopContainer", "stopCompose"]),
+    ),
+    ("overrideCommand", Type::Bool),
+    ("containerEnv", Type::Object),
+    ("containerUser", Type::Str),
+    ("remoteEnv", Type::Object),
+    ("remoteUser", Type::Str),
+    ("updateRemoteUserUID", Type::Bool),
+    (
+        "userEnvProbe",
+        Type::Enum(&[
+            "none",
+            "loginShell",
+            "loginInteractiveShell",
+            "interactiveShell",
+        ]),
+    ),
+    ("init", Type::Bool),
+    ("privileged", Type::Bool),
+    ("capAdd", Type::Array),
+    ("securityOpt", Type::Array),
+    ("initializeCommand", Type::Command),
+    ("onCreateCommand", Type::Command),
+    ("updateContentCommand", Type::Command),
+    ("postCreateCommand", Type::Command),
+    ("postStartCommand", Type::Command),
+    ("postAttachCommand", Type::Command),
+    (
+        "waitFor",
+        Type::Enum(&[
+            "initializeCommand",
+            "onCreateCommand",
+            "updateContentCommand",
+            "postCreateCommand",
+            "postStartCommand",
+        ]),
+    ),
+    ("features", Type::Object),
+    ("overrideFeatureInstallOrder", Type::Array),
+    ("hostRequirements", Type::Props(HOST_REQUIREMENTS)),
+    ("customizations", Type::Object),
+    ("secrets", Type::Object),
+    // Deprecated in favor of customizations.vscode, but still accepted.
+    ("extensions", Type::Array),
+    ("settings", Type::Object),
+];
+
+/// Something wrong with a devcontainer.json.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Problem {
+    /// The value at the path isn't of the type the schema wants.
+    WrongType(String, Type),
+    /// The schema has no such property.
+    Unknown(String),
+    /// There is no image, Dockerfile or Compose file to make the container
+    /// from.
+    NoSource,
+    /// dockerComposeFile without a service.
+    NoService,
+}
+
+impl Problem {
+    pub fn message(&self) -> String {
+        match self {
+            Problem::WrongType(path, Type::Enum(values)) => {
+                t!("validate-one-of", path = path, values = values.join(", "))
+            }
+            Problem::WrongType(path, expected) => {
+                t!("validate-type", path = path, expected = describe(*expected))
+            }
+            Problem::Unknown(path) => t!("validate-unknown", path = path),
+            Problem::NoSource => t!("validate-no-source"),
+            Problem::NoService => t!("validate-no-service"),
+        }
+    }
+}
+
+/// The schema's name for a type.
+fn describe(ty: Type) -> &'static str {
+    match ty {
+        Type::Any => "anything",
+        Type::Bool => "a boolean",
+        Type::Number => "a number",
+        Type::Str | Type::Enum(_) => "a string",
+        Type::Array => "an array",
+        Type::Object | Type::Props(_) => "an object",
+        Type::StrOrArray => "a string or an array",
+        Type::Command => "a string, an array or an object",
+        Type::Port => "a number or a string",
+    }
+}
+
+/// Does value have the given type?
+fn matches(value: &Value, ty: Type) -> bool {
+    match ty {
+        Type::Any => true,
+        Type::Bool => value.is_boolean(),
+        Type::Number => value.is_number(),
+        Type::Str => value.is_string(),
+        Type::Array => value.is_array(),
+        Type::Object | Type::Props(_) => value.is_object(),
+        Type::StrOrArray => value.is_string() || value.is_array(),
+        Type::Command => value.is_string() || value.is_array() || value.is_object(),
+        Type::Port => value.is_number() || value.is_string(),
+        Type::Enum(values) => value.as_str().is_some_and(|s| values.contains(&s)),
+    }
+}
+
+/// Check the properties of obj, whose path is prefix, against props.
+fn check_props(obj: &Value, prefix: &str, props: &[(&str, Type)], problems: &mut Vec<Problem>) {
+    let Some(map) = obj.as_object() else {
+        return;
+    };
+    for (key, value) in map {
+        let path = format!("{prefix}{key}");
+        match props.iter().find(|(name, _)| name == key) {
+            None => problems.push(Problem::Unknown(path)),
+            Some((_, ty)) if !matches(value, *ty) => problems.push(Problem::WrongType(path, *ty)),
+            Some((_, Type::Props(inner))) => {
+                check_props(value, &format!("{path}."), inner, problems)
+            }
+            Some(_) => {}
+        }
+    }
+}
+
+/// Check a parsed devcontainer.json against the schema.
+pub fn check(config: &Value) -> Vec<Problem> {
+    let mut problems = Vec::new();
+    if !config.is_object() {
+        problems.push(Problem::WrongType("(top level)".into(), Type::Object));
+        return problems;
+    }
+    check_props(config, "", PROPERTIES, &mut problems);
+    if let Some(ports) = config["forwardPorts"].as_array() {
+        for (i, port) in ports.iter().enumerate() {
+            if !matches(port, Type::Port) {
+                problems.push(Problem::WrongType(format!("forwardPorts[{i}]"), Type::Port));
+            }
+        }
+    }
+    let has = |key: &str| !config[key].is_null();
+    if !has("image")
+        && !has("dockerFile")
+        && config["build"]["dockerfile"].is_null()
+        && !has("dockerComposeFile")
+    {
+        problems.push(Problem::NoSource);
+    }
+    if has("dockerComposeFile") && !has("service") {
+        problems.push(Problem::NoService);
+    }
+    problems
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn problems(json_data: &str) -> Vec<Problem> {
+        check(&serde_jsonc::from_str(json_data).unwrap())
+    }
+
+    #[test]
+    fn test_check() {
+        assert_eq!(
+            problems(
+                r#"{"image": "node", "forwardPorts": [3000, "db:5432"], "postCreateCommand": ["npm", "i"]}"#
+            ),
+            vec![]
+        );
+        assert_eq!(
+            problems(
+                r#"{"build": {"dockerfile": "Dockerfile", "dockerfil": "x"}, "imagee": "node"}"#
+            ),
+            vec![
+                Problem::Unknown("build.dockerfil".into()),
+                Problem::Unknown("imagee".into())
+            ]
+        );
+        assert_eq!(
+            problems(r#"{"image": 3, "forwardPorts": [true], "shutdownAction": "stop"}"#),
+            vec![
+                Problem::WrongType("image".into(), Type::Str),
+                Problem::WrongType(
+                    "shutdownAction".into(),
+                    Type::Enum(&["none", "stopContainer", "stopCompose"])
+                ),
+                Problem::WrongType("forwardPorts[0]".into(), Type::Port),
+            ]
+        );
+        assert_eq!(
+            problems(r#"{"dockerComposeFile": "c.yml"}"#),
+            vec![Problem::NoService]
+        );
+        assert_eq!(
+            problems("[]"),
+            vec![Problem::WrongType("(top level)".into(), Type::Object)]
+        );
+    }
+}
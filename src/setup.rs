@@ -0,0 +1,264 @@
+use dialoguer::{Confirm, Input};
+use std::env;
+use std::ffi::OsStr;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::command_utils::capture;
+use crate::config;
+use crate::doctor;
+use crate::engine;
+use crate::file_utils::which;
+use crate::i18n::t;
+
+/// Editors that cope knows how to drive.
+pub const KNOWN_EDITORS: &[&str] = &[
+    "code",
+    "code-insiders",
+    "codium",
+    "code-oss",
+    "cursor",
+    "windsurf",
+];
+
+/// The known editors that are on the PATH.
+pub fn detect_editors(path: Option<&OsStr>) -> Vec<(&'static str, PathBuf)> {
+    KNOWN_EDITORS
+        .iter()
+        .filter_map(|&e| which(e, path).map(|p| (e, p)))
+        .collect()
+}
+
+/// The startup file for the given shell, and the line that routes `code`
+/// through cope in it.
+pub fn shell_integration(shell: &str, home: &Path) -> Option<(PathBuf, &'static str)> {
+    match Path::new(shell).file_name()?.to_str()? {
+        "bash" => Some((home.join(".bashrc"), "alias code=cope")),
+        "zsh" => Some((home.join(".zshrc"), "alias code=cope")),
+        "fish" => Some((home.join(".config/fish/config.fish"), "alias code cope")),
+        _ => None,
+    }
+}
+
+/// The startup file for the given shell, and the line that has it complete
+/// cope's arguments with `cope complete`.  zsh needs bash's completion
+/// loaded for that; fish can't use it.
+pub fn shell_completion(shell: &str, home: &Path) -> Option<(PathBuf, &'static str)> {
+    match Path::new(shell).file_name()?.to_str()? {
+        "bash" => Some((
+            home.join(".bashrc"),
+            "complete -o nospace -C 'cope complete' cope",
+        )),
+        "zsh" => Some((
+            home.join(".zshrc"),
+            "autoload -U +X bashcompinit && bashcompinit && complete -o nospace -C 'cope complete' cope",
+        )),
+        _ => None,
+    }
+}
+
+/// Ask whether to add line to rc, and add it if so.
+fn offer_line(rc: &Path, line: &str, prompt: String) {
+    let yes = Confirm::new()
+        .with_prompt(prompt)
+        .default(false)
+        .interact()
+        .expect("Confirm failed");
+    if yes {
+        match append_line(rc, line) {
+            Ok(true) => println!("{}", t!("updated", file = rc.display())),
+            Ok(false) => println!("{}", t!("already-set-up", file = rc.display())),
+            Err(er) => eprintln!("{}", t!("update-failed", file = rc.display(), error = er)),
+        }
+    }
+}
+
+/// Add line to the end of file, unless it is already there.
+pub fn append_line(file_name: &Path, line: &str) -> std::io::Result<bool> {
+    let contents = fs::read_to_string(file_name).unwrap_or_default();
+    if contents.lines().any(|l| l.trim() == line) {
+        return Ok(false);
+    }
+    if let Some(parent) = file_name.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let sep = if contents.is_empty() || contents.ends_with('\n') {
+        ""
+    } else {
+        "\n"
+    };
+    fs::write(file_name, format!("{contents}{sep}{line}\n"))?;
+    Ok(true)
+}
+
+/// Remove line from file, if it is there.
+pub fn remove_line(file_name: &Path, line: &str) -> std::io::Result<bool> {
+    let Ok(contents) = fs::read_to_string(file_name) else {
+        return Ok(false);
+    };
+    let kept: Vec<&str> = contents.lines().filter(|l| l.trim() != line).collect();
+    if kept.len() == contents.lines().count() {
+        return Ok(false);
+    }
+    let mut res = kept.join("\n");
+    if !res.is_empty() {
+        res.push('\n');
+    }
+    fs::write(file_name, res)?;
+    Ok(true)
+}
+
+/// Split a comma-separated list, dropping blanks.
+pub fn split_list(s: &str) -> Vec<String> {
+    s.split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+/// `cope setup`: look around, write a config file, optionally set up the
+/// shell and its completion of cope's arguments, then run the doctor
+/// checks.
+pub fn run() {
+    let path = env::var_os("PATH");
+    let editors = detect_editors(path.as_deref());
+    if editors.is_empty() {
+        println!("{}", t!("setup-no-editors"));
+    }
+    for (name, p) in &editors {
+        println!("{}", t!("setup-found", name = name, path = p.display()));
+    }
+    // Podman has no `context show`; engine::context knows that.
+    match capture(Command::new(engine::program()).args(["version"])) {
+        Some(_) => {
+            let settings = engine::settings();
+            println!(
+                "{}",
+                t!(
+                    "setup-context",
+                    engine = engine::program(),
+                    context = settings.context
+                )
+            );
+            if let Some(host) = &settings.host {
+                println!(
+                    "{}",
+                    t!("setup-host", engine = engine::program(), host = host)
+                );
+            }
+        }
+        None => println!("{}", t!("setup-no-engine", engine = engine::program())),
+    }
+    let shell = env::var("SHELL").unwrap_or_default();
+    println!("{}", t!("setup-shell-name", shell = shell));
+
+    let config_file = config::config_file();
+    let write = !config_file.exists()
+        || Confirm::new()
+            .with_prompt(t!("setup-overwrite", file = config_file.display()))
+            .default(false)
+            .interact()
+            .expect("Confirm failed");
+    if write {
+        let roots: String = Input::new()
+            .with_prompt(t!("setup-projects"))
+            .default("~/src".into())
+            .interact_text()
+            .expect("Input failed");
+        // Keep whatever else was in the old config, if it was readable.
+        let mut cfg = config::try_read_config(&config_file).unwrap_or_default();
+        cfg.projects = split_list(&roots);
+        match config::write_config(&config_file, &cfg) {
+            Ok(()) => println!("{}", t!("wrote", file = config_file.display())),
+            Err(er) => eprintln!(
+                "{}",
+                t!("write-failed", file = config_file.display(), error = er)
+            ),
+        }
+    }
+
+    if let Some(home) = env::var_os("HOME") {
+        let home = Path::new(&home);
+        if let Some((rc, line)) = shell_integration(&shell, home) {
+            offer_line(
+                &rc,
+                line,
+                t!("setup-shell", line = line, file = rc.display()),
+            );
+        }
+        if let Some((rc, line)) = shell_completion(&shell, home) {
+            offer_line(
+                &rc,
+                line,
+                t!("setup-completion", line = line, file = rc.display()),
+            );
+        }
+    }
+
+    print!("{}", doctor::format(&doctor::run()));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_editors() {
+        assert!(detect_editors(None).is_empty());
+    }
+
+    #[test]
+    fn test_shell_integration() {
+        let home = Path::new("/home/me");
+        assert_eq!(
+            shell_integration("/bin/zsh", home),
+            Some((PathBuf::from("/home/me/.zshrc"), "alias code=cope"))
+        );
+        assert_eq!(
+            shell_integration("/bin/fish", home).unwrap().1,
+            "alias code cope"
+        );
+        assert_eq!(shell_integration("/bin/tcsh", home), None);
+        assert_eq!(shell_integration("", home), None);
+    }
+
+    #[test]
+    fn test_shell_completion() {
+        let home = Path::new("/home/me");
+        assert_eq!(
+            shell_completion("/bin/bash", home),
+            Some((
+                PathBuf::from("/home/me/.bashrc"),
+                "complete -o nospace -C 'cope complete' cope"
+            ))
+        );
+        assert!(
+            shell_completion("zsh", home)
+                .unwrap()
+                .1
+                .starts_with("autoload")
+        );
+        assert_eq!(shell_completion("/usr/bin/fish", home), None);
+    }
+
+    #[test]
+    fn test_append_line() {
+        let f = env::temp_dir().join(format!("cope-test-append-{}", std::process::id()));
+        let _ = fs::remove_file(&f);
+        assert!(append_line(&f, "alias code=cope").unwrap());
+        assert!(!append_line(&f, "alias code=cope").unwrap());
+        assert_eq!(fs::read_to_string(&f).unwrap(), "alias code=cope\n");
+        fs::write(&f, "a\nalias code=cope\nb\n").unwrap();
+        assert!(remove_line(&f, "alias code=cope").unwrap());
+        assert!(!remove_line(&f, "alias code=cope").unwrap());
+        assert_eq!(fs::read_to_string(&f).unwrap(), "a\nb\n");
+        fs::remove_file(&f).unwrap();
+    }
+
+    #[test]
+    fn test_split_list() {
+        assert_eq!(split_list(" ~/src, ,/work "), vec!["~/src", "/work"]);
+    }
+}
@@ -0,0 +1,200 @@
+use std::env;
+use std::ffi::{OsStr, OsString};
+use std::fs;
+use std::io::Read;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+
+use crate::config;
+use crate::i18n::t;
+use crate::setup::{append_line, remove_line, shell_integration};
+use crate::string_utils::aligned;
+
+/// Marks a `code` that cope installed, so it is never mistaken for the real
+/// one.
+const MARKER: &str = "# Installed by `cope shim install`";
+
+/// Where the shim lives.  Only the shim goes in here, so it can be put at
+/// the front of PATH without hiding anything else.
+pub fn shim_dir() -> PathBuf {
+    config::data_dir().join("bin")
+}
+
+/// The contents of the shim, which runs the given cope.
+pub fn script(cope: &Path) -> String {
+    format!(
+        "#!/bin/sh\n{MARKER}\nexec {:?} \"$@\"\n",
+        cope.display().to_string()
+    )
+}
+
+/// Is this file a shim, rather than a real editor?
+pub fn is_shim(file_name: &Path) -> bool {
+    // The marker is near the top; don't read all of a real editor binary.
+    let mut head = Vec::new();
+    fs::File::open(file_name)
+        .and_then(|f| f.take(256).read_to_end(&mut head))
+        .is_ok_and(|_| String::from_utf8_lossy(&head).contains(MARKER))
+}
+
+/// Is this file cope itself, perhaps through a symlink named after an
+/// editor?
+pub fn is_self(file_name: &Path) -> bool {
+    let me = env::current_exe().and_then(fs::canonicalize);
+    fs::canonicalize(file_name).is_ok_and(|f| me.is_ok_and(|me| f == me))
+}
+
+/// Find name on PATH, skipping the shim, so that cope never runs itself.
+pub fn real(name: &str, path: Option<&OsStr>) -> Option<PathBuf> {
+    let shims = shim_dir();
+    env::split_paths(path?)
+        .filter(|dir| *dir != shims)
+        .map(|dir| dir.join(name))
+        .find(|p| {
+            p.metadata()
+                .is_ok_and(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+                && !is_shim(p)
+                && !is_self(p)
+        })
+}
+
+/// The startup file for the shell, and the line that puts dir at the front
+/// of PATH in it.
+pub fn path_line(shell: &str, home: &Path, dir: &Path) -> Option<(PathBuf, String)> {
+    let (rc, _) = shell_integration(shell, home)?;
+    let line = if rc.ends_with("config.fish") {
+        format!("fish_add_path --prepend {:?}", dir.display().to_string())
+    } else {
+        format!("export PATH={:?}:\"$PATH\"", dir.display().to_string())
+    };
+    Some((rc, line))
+}
+
+fn rc_line() -> Option<(PathBuf, String)> {
+    let shell = env::var("SHELL").unwrap_or_default();
+    let home = env::var_os("HOME")?;
+    path_line(&shell, Path::new(&home), &shim_dir())
+}
+
+/// `cope shim install|remove|status`.
+pub fn run(args: &[OsString]) {
+    let dir = shim_dir();
+    let shim = dir.join("code");
+    match args.first().and_then(|a| a.to_str()) {
+        Some("install") => {
+            let cope = env::current_exe().expect("Finding cope");
+            let written = fs::create_dir_all(&dir)
+                .and_then(|_| fs::write(&shim, script(&cope)))
+                .and_then(|_| fs::set_permissions(&shim, fs::Permissions::from_mode(0o755)));
+            if let Err(er) = written {
+                eprintln!("{}", t!("write-failed", file = shim.display(), error = er));
+                return;
+            }
+            println!("{}", t!("wrote", file = shim.display()));
+            match rc_line() {
+                Some((rc, line)) => match append_line(&rc, &line) {
+                    Ok(true) => println!("{}", t!("shim-updated-rc", file = rc.display())),
+                    Ok(false) => println!("{}", t!("already-set-up", file = rc.display())),
+                    Err(er) => {
+                        eprintln!("{}", t!("update-failed", file = rc.display(), error = er))
+                    }
+                },
+                None => println!("{}", t!("shim-add-to-path", dir = dir.display())),
+            }
+        }
+        Some("remove") => {
+            match fs::remove_file(&shim) {
+                Ok(()) => println!("{}", t!("removed", file = shim.display())),
+                Err(er) => eprintln!("{}", t!("remove-failed", file = shim.display(), error = er)),
+            }
+            if let Some((rc, line)) = rc_line() {
+                match remove_line(&rc, &line) {
+                    Ok(true) => println!("{}", t!("updated", file = rc.display())),
+                    Ok(false) => {}
+                    Err(er) => {
+                        eprintln!("{}", t!("update-failed", file = rc.display(), error = er))
+                    }
+                }
+            }
+        }
+        Some("status") | None => {
+            let path = env::var_os("PATH");
+            let installed = is_shim(&shim);
+            let on_path = path
+                .as_deref()
+                .is_some_and(|p| env::split_paths(p).next() == Some(dir.clone()));
+            let rows = [
+                (
+                    t!("shim-shim"),
+                    if installed {
+                        shim.display().to_string()
+                    } else {
+                        t!("shim-not-installed")
+                    },
+                ),
+                (
+                    t!("shim-on-path"),
+                    if on_path {
+                        t!("shim-first")
+                    } else {
+                        t!("shim-not-first")
+                    },
+                ),
+                (
+                    t!("shim-real-code"),
+                    real("code", path.as_deref())
+                        .map_or_else(|| t!("shim-not-found"), |p| p.display().to_string()),
+                ),
+            ];
+            print!("{}", aligned(&rows));
+        }
+        Some(other) => eprintln!("{}", t!("shim-unknown", command = format!("{other:?}"))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_script() {
+        let f = env::temp_dir().join(format!("cope-test-shim-{}", std::process::id()));
+        fs::write(&f, script(Path::new("/opt/cope"))).unwrap();
+        assert!(is_shim(&f));
+        assert!(
+            fs::read_to_string(&f)
+                .unwrap()
+                .ends_with("exec \"/opt/cope\" \"$@\"\n")
+        );
+        fs::remove_file(&f).unwrap();
+        assert!(!is_shim(Path::new("Cargo.toml")));
+    }
+
+    #[test]
+    fn test_real() {
+        let path = env::var_os("PATH");
+        assert!(real("sh", path.as_deref()).is_some());
+        assert!(is_self(&env::current_exe().unwrap()));
+        assert!(!is_self(Path::new("Cargo.toml")));
+        let shims = env::join_paths([shim_dir()]).unwrap();
+        assert_eq!(real("code", Some(&shims)), None);
+    }
+
+    #[test]
+    fn test_path_line() {
+        let home = Path::new("/home/me");
+        let dir = Path::new("/home/me/.local/share/cope/bin");
+        assert_eq!(
+            path_line("/bin/bash", home, dir),
+            Some((
+                PathBuf::from("/home/me/.bashrc"),
+                "export PATH=\"/home/me/.local/share/cope/bin\":\"$PATH\"".into()
+            ))
+        );
+        assert_eq!(
+            path_line("fish", home, dir).unwrap().1,
+            "fish_add_path --prepend \"/home/me/.local/share/cope/bin\""
+        );
+        assert_eq!(path_line("tcsh", home, dir), None);
+    }
+}
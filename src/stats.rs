@@ -0,0 +1,112 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Launch statistics for one repo.  These never leave the machine.
+#[derive(Deserialize, Serialize, Debug, Default, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct RepoStats {
+    pub launches: u64,
+
+    /// Total time spent finding and choosing configs, in milliseconds.
+    pub resolve_ms: u64,
+
+    /// When it was last launched, in seconds since the epoch.
+    pub last: u64,
+}
+
+impl RepoStats {
+    pub fn average_ms(&self) -> u64 {
+        self.resolve_ms.checked_div(self.launches).unwrap_or(0)
+    }
+}
+
+pub type Stats = BTreeMap<PathBuf, RepoStats>;
+
+/// Read the stats.  Missing or corrupt files are empty.
+pub fn read(file_name: &Path) -> Stats {
+    fs::read_to_string(file_name)
+        .ok()
+        .and_then(|json_data| serde_jsonc::from_str(&json_data).ok())
+        .unwrap_or_default()
+}
+
+/// Write the stats, ignoring errors, since they are only informational.
+pub fn write(file_name: &Path, stats: &Stats) {
+    if let Some(parent) = file_name.parent() {
+        let _ = fs::create_dir_all(parent);
+    }
+    if let Ok(json_data) = serde_jsonc::to_string_pretty(stats) {
+        let _ = fs::write(file_name, json_data);
+    }
+}
+
+/// Count a launch of each root, which took `resolve` to work out.
+pub fn add(stats: &mut Stats, roots: &[PathBuf], resolve: Duration, now: u64) {
+    for root in roots {
+        let s = stats.entry(root.clone()).or_default();
+        s.launches += 1;
+        s.resolve_ms += resolve.as_millis() as u64;
+        s.last = now;
+    }
+}
+
+/// A table of the stats, most-launched first.
+pub fn format(stats: &Stats) -> String {
+    let mut rows: Vec<(&PathBuf, &RepoStats)> = stats.iter().collect();
+    rows.sort_by(|a, b| b.1.launches.cmp(&a.1.launches).then(a.0.cmp(b.0)));
+    let mut res = format!("{:>8} {:>10}  {}\n", "launches", "resolve ms", "repo");
+    for (path, s) in rows {
+        res.push_str(&format!(
+            "{:>8} {:>10}  {}\n",
+            s.launches,
+            s.average_ms(),
+            path.display()
+        ));
+    }
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_add() {
+        let mut stats = Stats::new();
+        let a = PathBuf::from("/a");
+        add(
+            &mut stats,
+            std::slice::from_ref(&a),
+            Duration::from_millis(30),
+            1,
+        );
+        add(
+            &mut stats,
+            &[a.clone(), "/b".into()],
+            Duration::from_millis(10),
+            2,
+        );
+        assert_eq!(
+            stats[&a],
+            RepoStats {
+                launches: 2,
+                resolve_ms: 40,
+                last: 2
+            }
+        );
+        assert_eq!(stats[&a].average_ms(), 20);
+        assert_eq!(RepoStats::default().average_ms(), 0);
+        assert_eq!(
+            format(&stats),
+            "launches resolve ms  repo\n       2         20  /a\n       1         10  /b\n"
+        );
+    }
+
+    #[test]
+    fn test_read_missing() {
+        assert!(read(Path::new("/___NO_SUCH_FILE___")).is_empty());
+    }
+}
@@ -1,5 +1,6 @@
 use std::ffi::{CString, OsString};
 use std::fmt::Write as FmtWrite;
+use std::iter::once;
 use std::os::unix::ffi::OsStrExt;
 
 pub fn to_cstring(s: OsString) -> CString {
@@ -27,6 +28,226 @@ pub fn hex(input: &[u8]) -> String {
     res
 }
 
+/// Percent-encode a path for a `file://` URI the way VS Code's `URI.toString`
+/// does: everything but letters, digits, `-._~` and `/` is encoded, as
+/// UTF-8.
+pub fn encode_uri_path(path: &str) -> String {
+    let mut res = String::with_capacity(path.len());
+    for b in path.bytes() {
+        if b.is_ascii_alphanumeric() || b"-._~/".contains(&b) {
+            res.push(b as char);
+        } else {
+            write!(res, "%{b:02X}").expect("String write");
+        }
+    }
+    res
+}
+
+/// Percent-encode one part of a URI the way JavaScript's
+/// `encodeURIComponent` does: everything but letters, digits and
+/// `-_.!~*'()` is encoded, as UTF-8, so `/`, `:` and `+` can't end the
+/// part early.
+pub fn encode_uri_component(part: &str) -> String {
+    let mut res = String::with_capacity(part.len());
+    for b in part.bytes() {
+        if b.is_ascii_alphanumeric() || b"-_.!~*'()".contains(&b) {
+            res.push(b as char);
+        } else {
+            write!(res, "%{b:02X}").expect("String write");
+        }
+    }
+    res
+}
+
+/// Undo hex.  None if input isn't an even number of hex digits.
+pub fn unhex(input: &str) -> Option<Vec<u8>> {
+    if !input.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..input.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(input.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+/// Undo the %XX escapes of a URI path.  Malformed escapes are left alone.
+pub fn decode_uri_path(path: &str) -> String {
+    let bytes = path.as_bytes();
+    let mut res = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = (bytes[i] == b'%')
+            .then(|| path.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|h| u8::from_str_radix(h, 16).ok());
+        match escaped {
+            Some(b) => {
+                res.push(b);
+                i += 3;
+            }
+            None => {
+                res.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&res).into_owned()
+}
+
+/// Split a command line into words the way the shell would, with single
+/// and double quotes and backslash escapes, but no expansions.  None if a
+/// quote isn't closed.
+pub fn shell_split(line: &str) -> Option<Vec<String>> {
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => words.extend(word.take()),
+            '\'' => {
+                let w = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next()? {
+                        '\'' => break,
+                        c => w.push(c),
+                    }
+                }
+            }
+            '"' => {
+                let w = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next()? {
+                        '"' => break,
+                        '\\' => match chars.next()? {
+                            c @ ('"' | '\\' | '$' | '`') => w.push(c),
+                            '\n' => {}
+                            c => {
+                                w.push('\\');
+                                w.push(c);
+                            }
+                        },
+                        c => w.push(c),
+                    }
+                }
+            }
+            '\\' => {
+                if let Some(c) = chars.next()
+                    && c != '\n'
+                {
+                    word.get_or_insert_with(String::new).push(c);
+                }
+            }
+            c => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+    words.extend(word);
+    Some(words)
+}
+
+const K: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+/// The SHA-256 digest of input.  Only used for the short identifiers the
+/// Dev Containers extension derives from paths, so speed doesn't matter.
+pub fn sha256(input: &[u8]) -> [u8; 32] {
+    let mut h: [u32; 8] = [
+        0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab,
+        0x5be0cd19,
+    ];
+    let mut msg = input.to_vec();
+    msg.push(0x80);
+    while msg.len() % 64 != 56 {
+        msg.push(0);
+    }
+    msg.extend_from_slice(&((input.len() as u64) * 8).to_be_bytes());
+
+    for chunk in msg.chunks_exact(64) {
+        let mut w = [0u32; 64];
+        for (i, word) in chunk.chunks_exact(4).enumerate() {
+            w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+        }
+        for i in 16..64 {
+            let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+            let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+            w[i] = w[i - 16]
+                .wrapping_add(s0)
+                .wrapping_add(w[i - 7])
+                .wrapping_add(s1);
+        }
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut hh] = h;
+        for i in 0..64 {
+            let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+            let ch = (e & f) ^ (!e & g);
+            let t1 = hh
+                .wrapping_add(s1)
+                .wrapping_add(ch)
+                .wrapping_add(K[i])
+                .wrapping_add(w[i]);
+            let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+            let maj = (a & b) ^ (a & c) ^ (b & c);
+            let t2 = s0.wrapping_add(maj);
+            hh = g;
+            g = f;
+            f = e;
+            e = d.wrapping_add(t1);
+            d = c;
+            c = b;
+            b = a;
+            a = t1.wrapping_add(t2);
+        }
+        for (x, v) in h.iter_mut().zip([a, b, c, d, e, f, g, hh]) {
+            *x = x.wrapping_add(v);
+        }
+    }
+    let mut res = [0u8; 32];
+    for (out, x) in res.chunks_exact_mut(4).zip(h) {
+        out.copy_from_slice(&x.to_be_bytes());
+    }
+    res
+}
+
+/// The bytes as one big-endian number, in base 32 with the digits 0-9a-v,
+/// the way JavaScript's `BigInt.toString(32)` writes it, zero-padded to
+/// width digits.
+pub fn base32(input: &[u8], width: usize) -> String {
+    const DIGITS: &[u8; 32] = b"0123456789abcdefghijklmnopqrstuv";
+    let bits: Vec<u8> = input
+        .iter()
+        .flat_map(|b| (0..8).rev().map(move |i| (b >> i) & 1))
+        .collect();
+    // Group from the least significant end.
+    let first = bits.len() % 5;
+    let mut res: String = once(&bits[..first])
+        .filter(|g| !g.is_empty())
+        .chain(bits[first..].chunks(5))
+        .map(|g| DIGITS[g.iter().fold(0, |acc, b| (acc << 1) | *b as usize)] as char)
+        .collect();
+    res = res.trim_start_matches('0').to_string();
+    format!("{res:0>width$}")
+}
+
+/// Label and value rows, with the values lined up after the longest
+/// label.
+pub fn aligned<L: AsRef<str>>(rows: &[(L, String)]) -> String {
+    let width = rows
+        .iter()
+        .map(|(label, _)| label.as_ref().chars().count())
+        .max()
+        .unwrap_or(0)
+        + 1;
+    rows.iter()
+        .map(|(label, value)| format!("{:width$} {value}\n", format!("{}:", label.as_ref())))
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -36,6 +257,85 @@ mod tests {
         assert_eq!(hex("\x01\x7f".as_bytes()), "017f");
     }
 
+    #[test]
+    fn test_encode_uri_path() {
+        assert_eq!(encode_uri_path("/a/b-c_d.e~"), "/a/b-c_d.e~");
+        assert_eq!(
+            encode_uri_path("/my repo/\"q\"/é"),
+            "/my%20repo/%22q%22/%C3%A9"
+        );
+    }
+
+    #[test]
+    fn test_encode_uri_component() {
+        assert_eq!(
+            encode_uri_component("ghcr.io/org/app:1.2"),
+            "ghcr.io%2Forg%2Fapp%3A1.2"
+        );
+        assert_eq!(encode_uri_component("a+b c"), "a%2Bb%20c");
+    }
+
+    #[test]
+    fn test_unhex() {
+        assert_eq!(unhex(&hex(b"/home/me/src")), Some(b"/home/me/src".to_vec()));
+        assert_eq!(unhex("2f7"), None);
+        assert_eq!(unhex("zz"), None);
+        assert_eq!(
+            decode_uri_path("/my%20repo/%22q%22/%C3%A9"),
+            "/my repo/\"q\"/é"
+        );
+        assert_eq!(decode_uri_path("/100%/%zz"), "/100%/%zz");
+    }
+
+    #[test]
+    fn test_shell_split() {
+        assert_eq!(
+            shell_split("  --disable-gpu   --profile Work "),
+            Some(vec![
+                "--disable-gpu".into(),
+                "--profile".into(),
+                "Work".into()
+            ])
+        );
+        assert_eq!(
+            shell_split(r#"--profile 'My Work' --log="a b" c\ d "" x"\"y""#),
+            Some(vec![
+                "--profile".into(),
+                "My Work".into(),
+                "--log=a b".into(),
+                "c d".into(),
+                "".into(),
+                "x\"y".into()
+            ])
+        );
+        assert_eq!(shell_split(""), Some(vec![]));
+        assert_eq!(shell_split("'open"), None);
+    }
+
+    #[test]
+    fn test_sha256() {
+        assert_eq!(
+            hex(&sha256(b"")),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        assert_eq!(
+            hex(&sha256(b"abc")),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        // Two blocks.
+        assert_eq!(
+            hex(&sha256(&[b'a'; 64])),
+            "ffe054fe7ae0cb6dc65c3af9b61d5209f439851db43d0ba5997337df154668eb"
+        );
+    }
+
+    #[test]
+    fn test_base32() {
+        assert_eq!(base32(&[0x01, 0x00], 0), "80");
+        assert_eq!(base32(&[0xff], 4), "007v");
+        assert_eq!(base32(&[0], 2), "00");
+    }
+
     #[test]
     fn test_to_cstring() {
         let res = to_cstring(OsString::from("foo"));
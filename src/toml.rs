@@ -0,0 +1,215 @@
+use serde_jsonc::{Map, Value};
+
+/// Parse the part of TOML that cope's own small files use into a JSON
+/// value, for serde: `[table]` headers (dotted names too), `key = value`
+/// lines with bare or quoted keys, and values that are strings, integers,
+/// booleans or one-line arrays of those.  Errors name the line, and say
+/// when it uses a part of TOML that isn't supported (floats, dates,
+/// multi-line strings and arrays, inline tables, and arrays of tables).
+pub fn to_json(input: &str) -> Result<Value, String> {
+    let mut root = Map::new();
+    let mut table: Vec<String> = Vec::new();
+    for (i, raw) in input.lines().enumerate() {
+        let line = strip_comment(raw).trim();
+        let error = |msg: &str| format!("line {}: {msg}: {}", i + 1, raw.trim());
+        if line.is_empty() {
+            continue;
+        }
+        if line.starts_with("[[") {
+            return Err(error("arrays of tables aren't supported"));
+        }
+        if let Some(name) = line.strip_prefix('[') {
+            let name = name.strip_suffix(']').ok_or_else(|| error("expected ]"))?;
+            table = name.split('.').map(|k| unquote(k.trim())).collect();
+            current(&mut root, &table).ok_or_else(|| error("not a table"))?;
+            continue;
+        }
+        let (key, value) = line
+            .split_once('=')
+            .ok_or_else(|| error("expected key = value"))?;
+        let key = unquote(key.trim());
+        let (value, rest) = parse_value(value.trim()).map_err(&error)?;
+        if !rest.trim().is_empty() {
+            return Err(error("unexpected text after the value"));
+        }
+        let map = current(&mut root, &table).ok_or_else(|| error("not a table"))?;
+        if map.insert(key, value).is_some() {
+            return Err(error("duplicate key"));
+        }
+    }
+    Ok(Value::Object(root))
+}
+
+/// The table at path, made if it is missing.
+fn current<'a>(
+    root: &'a mut Map<String, Value>,
+    path: &[String],
+) -> Option<&'a mut Map<String, Value>> {
+    let mut map = root;
+    for key in path {
+        map = map
+            .entry(key.clone())
+            .or_insert_with(|| Value::Object(Map::new()))
+            .as_object_mut()?;
+    }
+    Some(map)
+}
+
+/// The line without a `#` comment, leaving any `#` in strings alone.
+fn strip_comment(line: &str) -> &str {
+    let mut quote = None;
+    let mut escaped = false;
+    for (i, c) in line.char_indices() {
+        match (quote, c) {
+            (Some('"'), '\\') if !escaped => {
+                escaped = true;
+                continue;
+            }
+            (Some(q), c) if c == q && !escaped => quote = None,
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '#') => return &line[..i],
+            _ => {}
+        }
+        escaped = false;
+    }
+    line
+}
+
+/// A key, without its quotes if it has them.
+fn unquote(key: &str) -> String {
+    match parse_string(key) {
+        Some((s, "")) => s,
+        _ => key.to_string(),
+    }
+}
+
+/// A quoted string at the start of s, and the rest of s.
+fn parse_string(s: &str) -> Option<(String, &str)> {
+    let mut chars = s.char_indices();
+    let quote = match chars.next()? {
+        (_, q @ ('"' | '\'')) => q,
+        _ => return None,
+    };
+    let mut res = String::new();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            c if c == quote => return Some((res, &s[i + 1..])),
+            '\\' if quote == '"' => match chars.next()?.1 {
+                'n' => res.push('\n'),
+                't' => res.push('\t'),
+                e => res.push(e),
+            },
+            c => res.push(c),
+        }
+    }
+    None
+}
+
+/// A value at the start of s, and the rest of s, or what is wrong with it
+/// (including the parts of TOML that aren't supported).
+fn parse_value(s: &str) -> Result<(Value, &str), &'static str> {
+    if s.starts_with(['"', '\'']) {
+        if s.starts_with("\"\"\"") || s.starts_with("'''") {
+            return Err("multi-line strings aren't supported");
+        }
+        let (string, rest) = parse_string(s).ok_or("unclosed string")?;
+        return Ok((Value::String(string), rest));
+    }
+    if let Some(mut rest) = s.strip_prefix('[') {
+        let mut items = Vec::new();
+        loop {
+            rest = rest.trim_start();
+            if rest.is_empty() {
+                return Err("arrays must be on one line");
+            }
+            if let Some(after) = rest.strip_prefix(']') {
+                return Ok((Value::Array(items), after));
+            }
+            let (item, after) = parse_value(rest)?;
+            items.push(item);
+            rest = after.trim_start();
+            rest = rest.strip_prefix(',').unwrap_or(rest);
+        }
+    }
+    if s.starts_with('{') {
+        return Err("inline tables aren't supported");
+    }
+    let end = s.find([',', ']', ' ']).unwrap_or(s.len());
+    let (word, rest) = s.split_at(end);
+    let value = match word {
+        "" => return Err("missing value"),
+        "true" => Value::Bool(true),
+        "false" => Value::Bool(false),
+        _ => match word.replace('_', "").parse::<i64>() {
+            Ok(n) => Value::from(n),
+            Err(_)
+                if word.parse::<f64>().is_ok()
+                    || ["inf", "nan"].contains(&word.trim_start_matches(['+', '-'])) =>
+            {
+                return Err("only whole numbers are supported");
+            }
+            Err(_)
+                if word.contains(['-', ':']) && word.starts_with(|c: char| c.is_ascii_digit()) =>
+            {
+                return Err("dates and times aren't supported");
+            }
+            Err(_) => return Err("expected a string, number, boolean or array"),
+        },
+    };
+    Ok((value, rest))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_json() {
+        let v = to_json(
+            r#"
+            # A comment.
+            terminal = ["agent", 'x#y'] # after
+            [params]
+            --new-flag = 2
+            "-q" = 1_0
+            [a.b]
+            on = true
+            name = "say \"hi\""
+            "#,
+        )
+        .unwrap();
+        assert_eq!(v["terminal"][1], "x#y");
+        assert_eq!(v["params"]["--new-flag"], 2);
+        assert_eq!(v["params"]["-q"], 10);
+        assert_eq!(v["a"]["b"]["on"], true);
+        assert_eq!(v["a"]["b"]["name"], "say \"hi\"");
+
+        assert_eq!(
+            to_json("x = 1\nx = 2").unwrap_err(),
+            "line 2: duplicate key: x = 2"
+        );
+        assert_eq!(
+            to_json("x = nope").unwrap_err(),
+            "line 1: expected a string, number, boolean or array: x = nope"
+        );
+        assert_eq!(
+            to_json("x = 1.5").unwrap_err(),
+            "line 1: only whole numbers are supported: x = 1.5"
+        );
+        assert_eq!(
+            to_json("x = [1,\n2]").unwrap_err(),
+            "line 1: arrays must be on one line: x = [1,"
+        );
+        assert_eq!(
+            to_json("x = {a = 1}").unwrap_err(),
+            "line 1: inline tables aren't supported: x = {a = 1}"
+        );
+        assert_eq!(
+            to_json("x = 2024-01-01").unwrap_err(),
+            "line 1: dates and times aren't supported: x = 2024-01-01"
+        );
+        assert!(to_json("[params").is_err());
+        assert!(to_json("x = 1 2").is_err());
+        assert!(to_json("x = 1\n[x]").is_err());
+    }
+}
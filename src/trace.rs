@@ -0,0 +1,184 @@
+use std::cell::RefCell;
+use std::env;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::config;
+
+/// A finished span.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Record {
+    pub id: u64,
+    pub parent: Option<u64>,
+    pub name: String,
+    pub start: u128,
+    pub end: u128,
+}
+
+/// Where to write traces.  Tracing is off unless COPE_TRACE or the
+/// `traceFile` config setting names a file.
+fn trace_file() -> &'static Option<PathBuf> {
+    static FILE: OnceLock<Option<PathBuf>> = OnceLock::new();
+    FILE.get_or_init(|| {
+        env::var_os("COPE_TRACE")
+            .map(PathBuf::from)
+            .or_else(|| config::load().trace_file.map(PathBuf::from))
+    })
+}
+
+static FINISHED: Mutex<Vec<Record>> = Mutex::new(Vec::new());
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+thread_local! {
+    static STACK: RefCell<Vec<u64>> = const { RefCell::new(Vec::new()) };
+}
+
+fn now_nanos() -> u128 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("Time before epoch")
+        .as_nanos()
+}
+
+/// Times everything until it is dropped.  Spans started while this one is
+/// active are its children.
+pub struct Span {
+    record: Option<Record>,
+}
+
+impl Span {
+    pub fn enter(name: impl Into<String>) -> Span {
+        if trace_file().is_none() {
+            return Span { record: None };
+        }
+        Span::start(name.into())
+    }
+
+    fn start(name: String) -> Span {
+        let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+        let parent = STACK.with(|s| {
+            let mut s = s.borrow_mut();
+            let parent = s.last().copied();
+            s.push(id);
+            parent
+        });
+        Span {
+            record: Some(Record {
+                id,
+                parent,
+                name,
+                start: now_nanos(),
+                end: 0,
+            }),
+        }
+    }
+}
+
+impl Drop for Span {
+    fn drop(&mut self) {
+        if let Some(mut r) = self.record.take() {
+            r.end = now_nanos();
+            STACK.with(|s| s.borrow_mut().retain(|&i| i != r.id));
+            FINISHED.lock().expect("Trace lock").push(r);
+        }
+    }
+}
+
+fn json_str(s: &str) -> String {
+    serde_jsonc::to_string(s).expect("Serializing string")
+}
+
+/// Format the spans as one line of OTLP/JSON (an ExportTraceServiceRequest),
+/// which is what the OpenTelemetry collector's `otlpjsonfile` receiver
+/// reads.
+pub fn to_otlp_json(records: &[Record], trace_id: &str, pid: u32) -> String {
+    let spans: Vec<String> = records
+        .iter()
+        .map(|r| {
+            format!(
+                r#"{{"traceId":"{trace_id}","spanId":"{:016x}",{}"name":{},"kind":1,"startTimeUnixNano":"{}","endTimeUnixNano":"{}"}}"#,
+                r.id,
+                r.parent
+                    .map_or(String::new(), |p| format!(r#""parentSpanId":"{p:016x}","#)),
+                json_str(&r.name),
+                r.start,
+                r.end
+            )
+        })
+        .collect();
+    format!(
+        r#"{{"resourceSpans":[{{"resource":{{"attributes":[{{"key":"service.name","value":{{"stringValue":"cope"}}}},{{"key":"process.pid","value":{{"intValue":"{pid}"}}}}]}},"scopeSpans":[{{"scope":{{"name":"cope","version":{}}},"spans":[{}]}}]}}]}}"#,
+        json_str(env!("CARGO_PKG_VERSION")),
+        spans.join(",")
+    )
+}
+
+/// Append everything recorded so far to the trace file.  Must be called
+/// before exec, since exec doesn't run destructors.
+pub fn flush() {
+    let Some(file_name) = trace_file() else {
+        return;
+    };
+    let records: Vec<Record> = std::mem::take(&mut *FINISHED.lock().expect("Trace lock"));
+    if records.is_empty() {
+        return;
+    }
+    let pid = std::process::id();
+    let trace_id = format!(
+        "{:016x}{:08x}{:08x}",
+        records[0].start as u64,
+        pid,
+        records.len()
+    );
+    let line = to_otlp_json(&records, &trace_id, pid);
+    // Tracing must never get in the way of opening the editor.
+    if let Ok(mut f) = OpenOptions::new().create(true).append(true).open(file_name) {
+        let _ = writeln!(f, "{line}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nesting() {
+        {
+            let _outer = Span::start("outer".into());
+            let _inner = Span::start("inner".into());
+        }
+        let records = FINISHED.lock().unwrap().clone();
+        let inner = records.iter().find(|r| r.name == "inner").unwrap();
+        let outer = records.iter().find(|r| r.name == "outer").unwrap();
+        assert_eq!(inner.parent, Some(outer.id));
+        assert!(outer.start <= inner.start && inner.end <= outer.end);
+    }
+
+    #[test]
+    fn test_to_otlp_json() {
+        let records = [Record {
+            id: 2,
+            parent: Some(1),
+            name: "a\"b".into(),
+            start: 10,
+            end: 20,
+        }];
+        let line = to_otlp_json(&records, "00", 7);
+        assert!(
+            line.contains(
+                r#""spanId":"0000000000000002","parentSpanId":"0000000000000001","name":"a\"b""#
+            ),
+            "{line}"
+        );
+        // It's real JSON.
+        let v: serde_jsonc::Value = serde_jsonc::from_str(&line).unwrap();
+        assert_eq!(
+            v["resourceSpans"][0]["scopeSpans"][0]["spans"][0]["endTimeUnixNano"],
+            "20"
+        );
+    }
+}
@@ -0,0 +1,248 @@
+use dialoguer::Select;
+use std::ffi::OsStr;
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::command_utils::capture;
+use crate::i18n::t;
+use crate::shim;
+use crate::string_utils::{encode_uri_path, hex};
+
+/// The port `code serve-web` listens on.
+pub const PORT: u16 = 8000;
+
+/// Desktop VS Code's `code` is a launcher script; the standalone CLI (the
+/// one used for `code tunnel` on servers) is a native binary.  Checking the
+/// first bytes avoids starting Electron just to ask.
+pub fn is_script(file_name: &Path) -> bool {
+    let mut magic = [0u8; 2];
+    File::open(file_name)
+        .and_then(|mut f| f.read_exact(&mut magic))
+        .is_ok_and(|_| &magic == b"#!")
+}
+
+/// Is the file at code the standalone CLI, rather than desktop VS Code?  This
+/// is decided from the disk alone, since it is asked on every launch.
+/// Desktop VS Code's `code` is a script (for deb, rpm, tarball and macOS
+/// installs), a snap, or the Electron binary next to its `resources/app`;
+/// the CLI is a binary on its own.
+pub fn is_standalone(code: &Path) -> bool {
+    let real = fs::canonicalize(code).unwrap_or_else(|_| code.to_path_buf());
+    real.is_file()
+        && !is_script(&real)
+        && !real.starts_with("/snap")
+        && !real
+            .parent()
+            .is_some_and(|dir| dir.join("resources/app").exists())
+}
+
+/// Is the `code` on this PATH the standalone CLI, rather than desktop VS
+/// Code?
+pub fn standalone_cli(path: Option<&OsStr>) -> bool {
+    shim::real("code", path).is_some_and(|code| is_standalone(&code))
+}
+
+/// A connection token for serve-web, so the URL we print is the only way
+/// in.
+pub fn token() -> String {
+    let mut bytes = [0u8; 16];
+    File::open("/dev/urandom")
+        .and_then(|mut f| f.read_exact(&mut bytes))
+        .expect("Reading /dev/urandom");
+    hex(&bytes)
+}
+
+/// The `code serve-web` command line.
+pub fn serve_web_args(token: &str) -> Vec<String> {
+    vec![
+        "code".into(),
+        "serve-web".into(),
+        "--host".into(),
+        "127.0.0.1".into(),
+        "--port".into(),
+        PORT.to_string(),
+        "--connection-token".into(),
+        token.into(),
+    ]
+}
+
+/// The URL that opens folder in the served editor.  The folder is
+/// percent-encoded, so that spaces, `&` and `#` in it stay in the folder.
+pub fn url(token: &str, folder: &Path) -> String {
+    format!(
+        "http://127.0.0.1:{PORT}/?tkn={token}&folder={}",
+        encode_uri_path(&folder.to_string_lossy())
+    )
+}
+
+/// Where the VS Code CLI keeps the name of this machine's tunnel, for each
+/// flavor of VS Code.
+const STATE_FILES: &[&str] = &[
+    ".vscode/cli/code_tunnel.json",
+    ".vscode-insiders/cli/code_tunnel.json",
+];
+
+/// The tunnel name from the CLI's state file, or from the output of
+/// `code tunnel status`.  Both are JSON, with the name either at the top
+/// level or under "tunnel".
+pub fn parse_name(json_data: &str) -> Option<String> {
+    let v: serde_jsonc::Value = serde_jsonc::from_str(json_data).ok()?;
+    v.get("name")
+        .or_else(|| v.get("tunnel")?.get("name"))?
+        .as_str()
+        .filter(|n| !n.is_empty())
+        .map(String::from)
+}
+
+/// The names of the tunnels that the local VS Code CLI knows about.
+pub fn discover(home: &Path, path: Option<&OsStr>) -> Vec<String> {
+    let mut res: Vec<String> = STATE_FILES
+        .iter()
+        .filter_map(|f| parse_name(&fs::read_to_string(home.join(f)).ok()?))
+        .collect();
+    if let Some(code) = shim::real("code", path)
+        && let Some(status) = capture(Command::new(code).args(["tunnel", "status"]))
+        && let Some(name) = parse_name(&status)
+    {
+        res.push(name);
+    }
+    res.sort();
+    res.dedup();
+    res
+}
+
+/// The tunnel to use: the one asked for, the only one there is, or the one
+/// picked from those discovered.
+pub fn pick(wanted: &str, names: &[String]) -> Option<String> {
+    if !wanted.is_empty() {
+        return Some(wanted.into());
+    }
+    match names.len() {
+        0 => {
+            eprintln!("{}", t!("no-tunnels"));
+            None
+        }
+        1 => Some(names[0].clone()),
+        _ => Select::new()
+            .with_prompt(t!("choose-tunnel"))
+            .items(names)
+            .default(0)
+            .interact_opt()
+            .expect("Selection failed")
+            .map(|i| names[i].clone()),
+    }
+}
+
+/// The argument that opens the path rel, inside the repo at remote_root on
+/// the tunnel's machine.
+pub fn uri(name: &str, remote_root: &str, rel: &Path, folder: bool) -> String {
+    let mut pth = PathBuf::from(remote_root);
+    if !rel.as_os_str().is_empty() {
+        pth.push(rel);
+    }
+    format!(
+        "--{}-uri=vscode-remote://tunnel+{name}{}",
+        if folder { "folder" } else { "file" },
+        encode_uri_path(&pth.to_string_lossy())
+    )
+}
+
+/// The code args that open the given paths on the tunnel.  The paths are
+/// on the remote machine, so they are passed through unchanged.
+pub fn remote_args(name: &str, args: &[String]) -> Vec<String> {
+    let mut res: Vec<String> = vec!["code".into(), "--remote".into(), format!("tunnel+{name}")];
+    res.extend(args.iter().cloned());
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_utils::which;
+    use std::env;
+
+    #[test]
+    fn test_is_script() {
+        assert!(!is_script(Path::new("Cargo.toml")));
+        assert!(!is_script(Path::new("___NO_SUCH_FILE___")));
+        let sh = which("sh", env::var_os("PATH").as_deref()).unwrap();
+        assert!(!is_script(&sh));
+    }
+
+    #[test]
+    fn test_is_standalone() {
+        let dir = env::temp_dir().join(format!("cope-test-standalone-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let code = dir.join("code");
+        fs::write(&code, b"\x7fELF").unwrap();
+        assert!(is_standalone(&code));
+        fs::create_dir_all(dir.join("resources/app")).unwrap();
+        assert!(!is_standalone(&code));
+        fs::remove_dir_all(dir.join("resources")).unwrap();
+        fs::write(&code, "#!/bin/sh\n").unwrap();
+        assert!(!is_standalone(&code));
+        assert!(!is_standalone(&dir.join("___NO_SUCH_FILE___")));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_parse_name() {
+        assert_eq!(
+            parse_name(r#"{"name": "devbox", "id": "x"}"#),
+            Some("devbox".into())
+        );
+        assert_eq!(
+            parse_name(
+                r#"{"tunnel": {"name": "devbox", "tunnel": "Connected"}, "service_installed": false}"#
+            ),
+            Some("devbox".into())
+        );
+        assert_eq!(parse_name(r#"{"tunnel": null}"#), None);
+        assert_eq!(parse_name("not json"), None);
+    }
+
+    #[test]
+    fn test_pick() {
+        assert_eq!(pick("a", &[]), Some("a".into()));
+        assert_eq!(pick("", &["b".into()]), Some("b".into()));
+        assert_eq!(pick("", &[]), None);
+        assert_eq!(
+            remote_args("b", &["/srv/app".into()]),
+            vec!["code", "--remote", "tunnel+b", "/srv/app"]
+        );
+    }
+
+    #[test]
+    fn test_discover() {
+        assert!(discover(Path::new("/___NO_SUCH_HOME___"), None).is_empty());
+    }
+
+    #[test]
+    fn test_url() {
+        let t = token();
+        assert_eq!(t.len(), 32);
+        assert_eq!(serve_web_args(&t)[1], "serve-web");
+        assert_eq!(
+            url("ab", Path::new("/src/app")),
+            "http://127.0.0.1:8000/?tkn=ab&folder=/src/app"
+        );
+        assert_eq!(
+            url("ab", Path::new("/src/R&D #2")),
+            "http://127.0.0.1:8000/?tkn=ab&folder=/src/R%26D%20%232"
+        );
+    }
+
+    #[test]
+    fn test_uri() {
+        assert_eq!(
+            uri("box", "/home/me/src", Path::new(""), true),
+            "--folder-uri=vscode-remote://tunnel+box/home/me/src"
+        );
+        assert_eq!(
+            uri("box", "/home/me/my src", Path::new("docs/100%.md"), false),
+            "--file-uri=vscode-remote://tunnel+box/home/me/my%20src/docs/100%25.md"
+        );
+    }
+}
@@ -0,0 +1,146 @@
+use serde::Serialize;
+
+use crate::registry::{ImageRef, newest_tag};
+
+/// A newer version of something a devcontainer.json refers to.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Upgrade {
+    /// "image" or "feature".
+    pub kind: &'static str,
+    pub from: String,
+    pub to: String,
+}
+
+/// The image and feature references in a devcontainer.json: the image
+/// first, then the features sorted by name.
+pub fn references(contents: &str) -> Vec<(&'static str, String)> {
+    let Ok(v) = serde_jsonc::from_str::<serde_jsonc::Value>(contents) else {
+        return vec![];
+    };
+    let mut res: Vec<(&'static str, String)> = Vec::new();
+    if let Some(image) = v.get("image").and_then(|i| i.as_str()) {
+        res.push(("image", image.into()));
+    }
+    if let Some(features) = v.get("features").and_then(|f| f.as_object()) {
+        res.extend(features.keys().map(|k| ("feature", k.clone())));
+    }
+    res
+}
+
+/// Look up each reference's tags with list_tags, and suggest the newest
+/// tag of the same shape.  References that can't be looked up are skipped.
+pub fn find_upgrades(
+    contents: &str,
+    list_tags: impl Fn(&ImageRef) -> Option<Vec<String>>,
+) -> Vec<Upgrade> {
+    references(contents)
+        .into_iter()
+        .filter_map(|(kind, reference)| {
+            let img = ImageRef::parse(&reference)?;
+            let tag = newest_tag(&img.tag, &list_tags(&img)?)?;
+            let base = reference
+                .rsplit_once(':')
+                .map_or(reference.as_str(), |(b, _)| b);
+            Some(Upgrade {
+                kind,
+                to: format!("{base}:{tag}"),
+                from: reference,
+            })
+        })
+        .collect()
+}
+
+/// Rewrite the references in contents.  Only the quoted strings change, so
+/// comments and formatting survive.
+pub fn apply(contents: &str, upgrades: &[Upgrade]) -> String {
+    upgrades.iter().fold(contents.to_string(), |acc, u| {
+        acc.replace(&format!("\"{}\"", u.from), &format!("\"{}\"", u.to))
+    })
+}
+
+/// One aligned line per upgrade.
+pub fn format(upgrades: &[Upgrade]) -> String {
+    let width = upgrades.iter().map(|u| u.from.len()).max().unwrap_or(0);
+    upgrades
+        .iter()
+        .map(|u| format!("{:<7} {:<width$} -> {}\n", u.kind, u.from, u.to))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = r#"{
+  // The base.
+  "image": "mcr.microsoft.com/devcontainers/base:1-bookworm",
+  "features": {
+    "ghcr.io/devcontainers/features/node:1": {},
+    "ghcr.io/devcontainers/features/go@sha256:00": {}
+  }
+}
+"#;
+
+    #[test]
+    fn test_references() {
+        assert_eq!(
+            references(CONFIG),
+            vec![
+                (
+                    "image",
+                    "mcr.microsoft.com/devcontainers/base:1-bookworm".to_string()
+                ),
+                (
+                    "feature",
+                    "ghcr.io/devcontainers/features/go@sha256:00".to_string()
+                ),
+                (
+                    "feature",
+                    "ghcr.io/devcontainers/features/node:1".to_string()
+                ),
+            ]
+        );
+        assert!(references("not json").is_empty());
+    }
+
+    #[test]
+    fn test_find_upgrades() {
+        let ups = find_upgrades(CONFIG, |img| match img.repository.as_str() {
+            "devcontainers/base" => Some(vec!["1-bookworm".into(), "2-bookworm".into()]),
+            _ => None,
+        });
+        assert_eq!(
+            ups,
+            vec![Upgrade {
+                kind: "image",
+                from: "mcr.microsoft.com/devcontainers/base:1-bookworm".into(),
+                to: "mcr.microsoft.com/devcontainers/base:2-bookworm".into(),
+            }]
+        );
+        let out = apply(CONFIG, &ups);
+        assert!(out.contains(
+            "  // The base.\n  \"image\": \"mcr.microsoft.com/devcontainers/base:2-bookworm\","
+        ));
+        assert!(out.contains("features/node:1"));
+    }
+
+    #[test]
+    fn test_format() {
+        let ups = [
+            Upgrade {
+                kind: "image",
+                from: "node:18".into(),
+                to: "node:22".into(),
+            },
+            Upgrade {
+                kind: "feature",
+                from: "ghcr.io/x/y:1".into(),
+                to: "ghcr.io/x/y:2".into(),
+            },
+        ];
+        assert_eq!(
+            format(&ups),
+            "image   node:18       -> node:22\nfeature ghcr.io/x/y:1 -> ghcr.io/x/y:2\n"
+        );
+    }
+}
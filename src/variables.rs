@@ -0,0 +1,145 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use crate::string_utils::{base32, sha256};
+
+/// What the standard devcontainer.json variables refer to.
+pub struct Context<'a> {
+    /// The repo root on the host.
+    pub local_workspace_folder: &'a Path,
+
+    /// The devcontainer.json these variables are in, for
+    /// `${devcontainerId}`.
+    pub config_file: Option<&'a Path>,
+
+    /// The workspace folder in the container, once it is known.
+    pub container_workspace_folder: Option<&'a str>,
+
+    /// Looks up the host's environment variables, for `${localEnv:NAME}`.
+    pub local_env: fn(&str) -> Option<String>,
+}
+
+impl Context<'_> {
+    /// The value of the variable called name, if it is one we know.
+    fn lookup(&self, name: &str) -> Option<String> {
+        let basename = |p: &str| {
+            p.rsplit('/')
+                .find(|s| !s.is_empty())
+                .unwrap_or_default()
+                .to_string()
+        };
+        match name {
+            "localWorkspaceFolder" => Some(self.local_workspace_folder.display().to_string()),
+            "localWorkspaceFolderBasename" => {
+                Some(basename(&self.local_workspace_folder.to_string_lossy()))
+            }
+            "containerWorkspaceFolder" => self.container_workspace_folder.map(String::from),
+            "containerWorkspaceFolderBasename" => self.container_workspace_folder.map(basename),
+            "devcontainerId" => Some(devcontainer_id(
+                self.local_workspace_folder,
+                self.config_file?,
+            )),
+            _ => {
+                // ${localEnv:NAME:default}, or its older spelling
+                // ${env:NAME}.  Unset variables without a default are empty.
+                let spec = name
+                    .strip_prefix("localEnv:")
+                    .or_else(|| name.strip_prefix("env:"))?;
+                let (var, default) = spec.split_once(':').unwrap_or((spec, ""));
+                Some((self.local_env)(var).unwrap_or_else(|| default.into()))
+            }
+        }
+    }
+}
+
+/// The ID the Dev Containers extension gives the container for a repo and
+/// config, which stays the same when the container is rebuilt.  It is the
+/// SHA-256 of the container's ID labels as JSON, with the keys sorted, as a
+/// 52-digit base 32 number.
+pub fn devcontainer_id(local_folder: &Path, config_file: &Path) -> String {
+    let labels = BTreeMap::from([
+        (
+            "devcontainer.config_file",
+            config_file.display().to_string(),
+        ),
+        (
+            "devcontainer.local_folder",
+            local_folder.display().to_string(),
+        ),
+    ]);
+    let json_data = serde_jsonc::to_string(&labels).expect("Serializing labels");
+    base32(&sha256(json_data.as_bytes()), 52)
+}
+
+/// Replace the `${...}` variables in s, the way the Dev Containers
+/// extension does.  Unknown variables are left as they are.
+pub fn substitute(s: &str, ctx: &Context) -> String {
+    let mut res = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(start) = rest.find("${") {
+        res.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let Some(end) = after.find('}') else {
+            rest = &rest[start..];
+            break;
+        };
+        match ctx.lookup(&after[..end]) {
+            Some(value) => res.push_str(&value),
+            None => res.push_str(&rest[start..start + 2 + end + 1]),
+        }
+        rest = &after[end + 1..];
+    }
+    res.push_str(rest);
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fake_env(name: &str) -> Option<String> {
+        (name == "USER").then(|| "me".into())
+    }
+
+    #[test]
+    fn test_substitute() {
+        let ctx = Context {
+            local_workspace_folder: Path::new("/home/me/src/cope"),
+            config_file: Some(Path::new(
+                "/home/me/src/cope/.devcontainer/devcontainer.json",
+            )),
+            container_workspace_folder: Some("/work/cope"),
+            local_env: fake_env,
+        };
+        assert_eq!(
+            substitute("/workspaces/${localWorkspaceFolderBasename}", &ctx),
+            "/workspaces/cope"
+        );
+        assert_eq!(
+            substitute("${localWorkspaceFolder}:${containerWorkspaceFolder}", &ctx),
+            "/home/me/src/cope:/work/cope"
+        );
+        assert_eq!(
+            substitute("${containerWorkspaceFolderBasename}", &ctx),
+            "cope"
+        );
+        assert_eq!(substitute("${nope} ${unclosed", &ctx), "${nope} ${unclosed");
+        assert_eq!(substitute("/home/${localEnv:USER}", &ctx), "/home/me");
+        assert_eq!(substitute("${localEnv:NOPE}", &ctx), "");
+        assert_eq!(substitute("${localEnv:NOPE:vscode}", &ctx), "vscode");
+        assert_eq!(substitute("${localEnv:USER:vscode}", &ctx), "me");
+        assert_eq!(substitute("${env:USER}", &ctx), "me");
+        assert_eq!(
+            substitute("${devcontainerId}", &ctx),
+            "1h82eop9s7at00anbjtmh55gm0769k55k02t58j6rvii2q584r5g"
+        );
+        let ctx = Context {
+            container_workspace_folder: None,
+            ..ctx
+        };
+        assert_eq!(
+            substitute("${containerWorkspaceFolder}", &ctx),
+            "${containerWorkspaceFolder}"
+        );
+    }
+}